@@ -41,4 +41,55 @@ defmodule Physics do
   def nearest_entity_position_in_range(_entity, _entities, _range), do: :erlang.nif_error(:nif_not_loaded)
 
   def maybe_triangulate_concave_entities(_obstacles), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Loads a map file (JSON) and validates it.
+
+  Returns `{:ok, %{map: map, report: report}}` where the report lists overlapping obstacles, entities outside
+  the external wall, unreachable spawn points and non convex polygons, or `{:error, reason}` if it can't be loaded.
+  """
+  def load_map(_json), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Imports the object layers of a map saved as JSON by Tiled into entities, with the category taken from the type
+  of each object (obstacle when missing). Concave polygons come split in triangles.
+  """
+  def import_tiled_map(_json), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Renders the external wall, obstacles (with the triangles concave ones are split in), entities colored by
+  category, the collision normals of entities overlapping obstacles and the given paths into an SVG string.
+  """
+  def render_debug_svg(_external_wall, _obstacles, _entities, _paths), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Ids of the entities overlapping the circle, from the closest to the farthest to its center. Entities are as close
+  as their closest point, not their position.
+
+  Only entities of the given categories are considered, any category when the list is empty, and the ids in
+  `exclude` are left out. The same goes for the other queries.
+  """
+  def query_circle(_center, _radius, _entities, _categories, _exclude), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Ids of the entities overlapping the circular sector of `range` radius spanning `half_angle` degrees at each side
+  of `direction`, from the closest to the farthest to `origin`.
+  """
+  def query_cone(_origin, _direction, _range, _half_angle, _entities, _categories, _exclude),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Ids of the entities overlapping the polygon, which may be concave.
+  """
+  def query_polygon(_vertices, _entities, _categories, _exclude), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Ids of the entities overlapping the axis aligned box between the `min` and `max` corners.
+  """
+  def query_aabb(_min, _max, _entities, _categories, _exclude), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Ids of the entities within `radius` of the segment between `a` and `b`.
+  """
+  def query_capsule(_a, _b, _radius, _entities, _categories, _exclude), do: :erlang.nif_error(:nif_not_loaded)
 end
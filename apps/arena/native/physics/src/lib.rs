@@ -1,10 +1,10 @@
 #![allow(non_snake_case)] // rustler macros generate non snake case names and dont use this allow themselves
 
-mod collision_detection;
-mod map;
+mod map_file;
 
-use crate::collision_detection::ear_clipping;
-use crate::map::{Category, Direction, Entity, Position};
+use crate::map_file::LoadedMap;
+use arena_geometry::collision_detection::ear_clipping;
+use arena_geometry::{Category, Direction, Entity, Position, QueryArea, QueryFilter};
 use std::collections::HashMap;
 
 #[rustler::nif()]
@@ -19,17 +19,17 @@ fn move_entities(
     external_wall: Entity,
     obstacles: HashMap<u64, Entity>,
 ) -> HashMap<u64, Entity> {
-    let mut entities: HashMap<u64, Entity> = entities;
-
-    for entity in entities.values_mut() {
-        if entity.is_moving {
-            entity.move_entity(delta_time);
-
-            move_entity_to_closest_available_position(entity, &external_wall, &obstacles);
-        }
-    }
-
     entities
+        .into_iter()
+        .map(|(id, mut entity)| {
+            if entity.is_moving {
+                entity.move_entity(delta_time);
+
+                entity.move_to_closest_available_position(&external_wall, &obstacles);
+            }
+            (id, entity)
+        })
+        .collect()
 }
 
 #[rustler::nif()]
@@ -39,10 +39,10 @@ fn move_entity(
     external_wall: Entity,
     obstacles: HashMap<u64, Entity>,
 ) -> Entity {
-    let mut entity: Entity = entity;
+    let mut entity = entity;
     if entity.is_moving {
         entity.move_entity(delta_time);
-        move_entity_to_closest_available_position(&mut entity, &external_wall, &obstacles);
+        entity.move_to_closest_available_position(&external_wall, &obstacles);
     }
 
     entity
@@ -55,10 +55,10 @@ fn get_closest_available_position(
     external_wall: Entity,
     obstacles: HashMap<u64, Entity>,
 ) -> Position {
-    let mut entity: Entity = entity;
+    let mut entity = entity;
     entity.position = new_position;
 
-    move_entity_to_closest_available_position(&mut entity, &external_wall, &obstacles);
+    entity.move_to_closest_available_position(&external_wall, &obstacles);
     entity.position
 }
 
@@ -70,9 +70,9 @@ fn move_entity_to_direction(
     external_wall: Entity,
     obstacles: HashMap<u64, Entity>,
 ) -> Entity {
-    let mut entity: Entity = entity;
+    let mut entity = entity;
     entity.move_entity_to_direction(direction, amount);
-    move_entity_to_closest_available_position(&mut entity, &external_wall, &obstacles);
+    entity.move_to_closest_available_position(&external_wall, &obstacles);
 
     entity
 }
@@ -81,7 +81,7 @@ fn move_entity_to_direction(
 /// Check players inside the player_id radius
 /// Return a list of the players id inside the radius Vec<player_id>
 fn check_collisions(entity: Entity, entities: HashMap<u64, Entity>) -> Vec<u64> {
-    let mut entity: Entity = entity;
+    let mut entity = entity;
     let ent = entities.into_values().collect();
 
     entity.collides_with(&ent)
@@ -148,7 +148,7 @@ fn calculate_duration(
 
         let new_target = Position::add(&position_a, &Position::mult(&direction, max_range));
 
-        let len = distance_between_positions(position_a, new_target);
+        let len = position_a.distance_to_position(&new_target);
 
         (len / speed) as u64
     } else {
@@ -174,11 +174,9 @@ fn nearest_entity_position_in_range(
             let distance = distance_between_positions(entity.position, other_entity.position);
             if distance < max_distance {
                 max_distance = distance;
-                let difference_between_positions =
-                    Position::sub(&other_entity.position, &entity.position);
                 position = Position {
-                    x: difference_between_positions.x,
-                    y: difference_between_positions.y,
+                    x: other_entity.position.x - entity.position.x,
+                    y: other_entity.position.y - entity.position.y,
                 };
                 use_autoaim = true;
             }
@@ -204,28 +202,123 @@ fn maybe_triangulate_concave_entities(obstacles: Vec<Entity>) -> Vec<Entity> {
     result
 }
 
-fn move_entity_to_closest_available_position(
-    entity: &mut Entity,
-    external_wall: &Entity,
-    obstacles: &HashMap<u64, Entity>,
-) {
-    let process_entity: bool = entity.category == Category::Player
-        || entity.category == Category::PowerUp
-        || entity.category == Category::Item;
-
-    if process_entity && !entity.is_inside_map(external_wall) {
-        entity.move_to_next_valid_position_inside(external_wall);
-    }
+/// Loads a map file and validates it
+/// Returns the map entities with a report of the problems found, or an error if it can't be loaded
+#[rustler::nif(schedule = "DirtyCpu")]
+fn load_map(json: String) -> Result<LoadedMap, String> {
+    let (map, report) = arena_geometry::load_map(&json)?;
 
-    let collides_with = entity.collides_with(&obstacles.clone().into_values().collect());
+    Ok(LoadedMap {
+        map,
+        report: report.into(),
+    })
+}
 
-    if process_entity && !collides_with.is_empty() {
-        let collided_with: Vec<&Entity> = collides_with
-            .iter()
-            .map(|id| obstacles.get(id).unwrap())
-            .collect();
-        entity.move_to_next_valid_position_outside(collided_with, obstacles, external_wall);
-    }
+/// Imports the object layers of a Tiled JSON map as entities, triangulating concave polygons
+/// Returns an error if the map can't be read or an object can't become an entity
+#[rustler::nif()]
+fn import_tiled_map(json: String) -> Result<Vec<Entity>, String> {
+    arena_geometry::import_tiled_map(&json)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn render_debug_svg(
+    external_wall: Entity,
+    obstacles: Vec<Entity>,
+    entities: Vec<Entity>,
+    paths: Vec<Vec<Position>>,
+) -> String {
+    let scene = arena_geometry::DebugScene {
+        external_wall: Some(external_wall),
+        obstacles,
+        entities,
+        grid: None,
+        paths,
+    };
+
+    arena_geometry::render_svg(&scene)
+}
+
+#[rustler::nif()]
+fn query_circle(
+    center: Position,
+    radius: f32,
+    entities: HashMap<u64, Entity>,
+    categories: Vec<Category>,
+    exclude: Vec<u64>,
+) -> Vec<u64> {
+    let area = QueryArea::Circle { center, radius };
+    query_entities(&area, entities, categories, exclude)
+}
+
+#[rustler::nif()]
+fn query_cone(
+    origin: Position,
+    direction: Direction,
+    range: f32,
+    half_angle: f32,
+    entities: HashMap<u64, Entity>,
+    categories: Vec<Category>,
+    exclude: Vec<u64>,
+) -> Vec<u64> {
+    let area = QueryArea::Cone {
+        origin,
+        direction,
+        range,
+        half_angle,
+    };
+    query_entities(&area, entities, categories, exclude)
+}
+
+#[rustler::nif()]
+fn query_polygon(
+    vertices: Vec<Position>,
+    entities: HashMap<u64, Entity>,
+    categories: Vec<Category>,
+    exclude: Vec<u64>,
+) -> Vec<u64> {
+    let area = QueryArea::Polygon { vertices };
+    query_entities(&area, entities, categories, exclude)
+}
+
+#[rustler::nif()]
+fn query_aabb(
+    min: Position,
+    max: Position,
+    entities: HashMap<u64, Entity>,
+    categories: Vec<Category>,
+    exclude: Vec<u64>,
+) -> Vec<u64> {
+    let area = QueryArea::Aabb { min, max };
+    query_entities(&area, entities, categories, exclude)
+}
+
+#[rustler::nif()]
+fn query_capsule(
+    a: Position,
+    b: Position,
+    radius: f32,
+    entities: HashMap<u64, Entity>,
+    categories: Vec<Category>,
+    exclude: Vec<u64>,
+) -> Vec<u64> {
+    let area = QueryArea::Capsule { a, b, radius };
+    query_entities(&area, entities, categories, exclude)
+}
+
+fn query_entities(
+    area: &QueryArea,
+    entities: HashMap<u64, Entity>,
+    categories: Vec<Category>,
+    exclude: Vec<u64>,
+) -> Vec<u64> {
+    let entities: Vec<Entity> = entities.into_values().collect();
+    let filter = QueryFilter {
+        categories,
+        exclude,
+    };
+
+    arena_geometry::query(area, &entities, &filter)
 }
 
 fn distance_between_positions(entity_a_postion: Position, entity_b_postion: Position) -> f32 {
@@ -263,6 +356,14 @@ rustler::init!(
         distance_between_entities,
         nearest_entity_position_in_range,
         get_closest_available_position,
-        maybe_triangulate_concave_entities
+        maybe_triangulate_concave_entities,
+        load_map,
+        import_tiled_map,
+        render_debug_svg,
+        query_circle,
+        query_cone,
+        query_polygon,
+        query_aabb,
+        query_capsule
     ]
 );
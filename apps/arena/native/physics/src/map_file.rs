@@ -0,0 +1,34 @@
+use rustler::NifMap;
+
+use arena_geometry::Map;
+
+/*
+ * Elixir facing result of loading a map file, see `arena_geometry::map_file` for the format
+ */
+
+#[derive(NifMap)]
+pub struct LoadedMap {
+    pub map: Map,
+    pub report: ValidationReport,
+}
+
+#[derive(NifMap)]
+pub struct ValidationReport {
+    pub is_valid: bool,
+    pub overlapping_obstacles: Vec<(u64, u64)>,
+    pub outside_wall: Vec<u64>,
+    pub unreachable_spawn_points: Vec<usize>,
+    pub non_convex_polygons: Vec<u64>,
+}
+
+impl From<arena_geometry::ValidationReport> for ValidationReport {
+    fn from(report: arena_geometry::ValidationReport) -> Self {
+        ValidationReport {
+            is_valid: report.is_valid(),
+            overlapping_obstacles: report.overlapping_obstacles,
+            outside_wall: report.outside_wall,
+            unreachable_spawn_points: report.unreachable_spawn_points,
+            non_convex_polygons: report.non_convex_polygons,
+        }
+    }
+}
@@ -6,7 +6,131 @@ defmodule AStarNative do
   use Rustler, otp_app: :bot_manager, crate: "astarnative"
 
   # When your NIF is loaded, it will override this function.
-  def a_star_shortest_path(_from, _to, _collision_grid), do: :erlang.nif_error(:nif_not_loaded)
+  # Returns `{:ok, path}` or `{:partial, path}` going to the closest reachable position when the goal can't be
+  # reached. A start or goal inside an obstacle is snapped to the closest free cell within 10 cells (1500 units)
+  # first, so the path may start or end away from the given positions. Errors are:
+  #   - `{:error, :start_blocked}` / `{:error, :goal_blocked}` when there's no free cell that close to snap to
+  #   - `{:error, :unreachable}` when the search couldn't move away from the start at all
+  # Available options:
+  #   - mode: `:a_star` (default) walks between neighbor cells and smooths the resulting path,
+  #     `:theta_star` runs an any-angle search that yields near-true-shortest paths,
+  #     `:jump_point_search` prunes symmetric paths, expanding far fewer nodes than A* on open maps,
+  #     `:hierarchical` searches the cluster graph precomputed with the grid, the fastest for long paths
+  #   - cost_weights: extra cost of walking through the cost layers of each category, e.g. `%{pool: 4.0, bush: -0.5}`.
+  #     Negative weights make cells preferred. Only used by the `:a_star` mode.
+  #   - max_expansions: how many nodes the search can expand before giving up with a partial path, unlimited by default
+  def a_star_shortest_path(_from, _to, _collision_grid, _options \\ %{}), do: :erlang.nif_error(:nif_not_loaded)
 
+  # Answers a list of `{from, to, options}` queries on the same grid in one call, returning the results of
+  # `a_star_shortest_path/4` in the same order. With `parallel` set the queries are spread over a pool of worker
+  # threads shared by every call, started on the first parallel batch and sized to half of the cores (at most 8).
+  def batch_shortest_paths(_collision_grid, _queries, _parallel \\ false), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Single search towards every target, returns the one with the shortest path as
+  # `%{index: index_in_targets, target: position, path: [...]}` or nil if none of them can be reached
+  def a_star_nearest_of(_from, _targets, _collision_grid), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Whether there's any path between the positions, positions inside obstacles are snapped like in
+  # `a_star_shortest_path`. Answered from the connected regions labeled when building the grid.
+  def is_reachable(_collision_grid, _from, _to), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Connected region of the position as `%{id: id, size: free_cells, is_main: boolean}`, nil if it's blocked.
+  # Regions other than the main (biggest) one are pockets isolated from most of the map.
+  def region_at(_collision_grid, _position), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Distance from the position to the closest obstacle, zero if it's inside one.
+  def distance_to_obstacle(_collision_grid, _position), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Positions next to obstacles within `radius` of `around` that the threat can't see,
+  # sorted from the closest to `around`. Only positions reachable from `around` are returned.
+  def find_cover_positions(_collision_grid, _threat_position, _around, _radius),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # Hierarchical search that only refines the path until it leaves the start cluster.
+  # Returns `%{path: [...], remaining_waypoints: [...]}`, call it again once `path` is walked to refine the rest.
+  # Endpoints inside obstacles are snapped like in `a_star_shortest_path`, both lists are empty if they can't be.
+  def a_star_next_path_chunk(_from, _to, _collision_grid), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Steers along a path returned by the pathfinding functions, looking ahead to cut corners where nothing is in between.
+  # Returns `%{direction: direction, waypoint_index: index, needs_replan: boolean}`, pass the returned index on the
+  # next call (0 for a new path). The index equals the path length once the end is reached and `needs_replan` is set
+  # when the bot strayed from the path or something blocks the way to the next waypoint.
+  def follow_path(_collision_grid, _path, _waypoint_index, _position, _speed, _delta_time),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # Local avoidance (ORCA) of the moving entities around the agent, e.g. other players and projectiles.
+  # Takes the velocity the agent wants to move at (direction times speed) and returns the closest one
+  # that doesn't collide with the neighbors within `time_horizon` milliseconds, `delta_time` is the tick duration.
+  # Returns `{:ok, velocity}`, or `{:error, reason}` when `time_horizon` or `delta_time` aren't positive.
+  def avoid_collisions(_agent, _desired_velocity, _neighbors, _time_horizon, _delta_time),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # Predicts which projectiles will hit the bot within `time_horizon` milliseconds if it stays still.
+  # Returns `%{threats: [projectile_id], direction: direction | nil}` where direction is the safest way to move
+  # that doesn't run into obstacles or out of the external wall, nil when there's nothing to dodge.
+  def dodge_projectiles(_bot, _projectiles, _time_horizon, _collision_grid, _external_wall),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # Returns `{:ok, collision_grid}` where the grid is a reference meant to be built once per map and shared.
   def build_collision_grid(_obstacles), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Grids are shared between matches so they're never modified, these return `{:ok, updated_copy}` instead.
+  # Meant for obstacles that change during a match, e.g. traps being placed or crates being destroyed.
+  # Only the cells the entity overlaps are updated, lines and points return `{:error, reason}` since they have no area.
+  def block_entity_cells(_collision_grid, _entity), do: :erlang.nif_error(:nif_not_loaded)
+
+  def unblock_entity_cells(_collision_grid, _entity), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Returns a copy of the grid with cost layers built from the entities (e.g. pools, traps and bushes),
+  # one layer per category. Queries choose how much each layer costs through the `cost_weights` option.
+  def set_cost_layers(_collision_grid, _entities), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Incremental planner kept by a bot to walk towards a fixed goal. Calling `replan_path/3` with the current
+  # position and grid repairs the previous path instead of searching again from scratch.
+  # Endpoints inside obstacles are snapped like in `a_star_shortest_path`, returns `{:ok, planner}` or
+  # `{:error, reason}` when there's no free cell close enough to one of them.
+  def new_path_planner(_from, _to, _collision_grid), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Same results as `a_star_shortest_path`: `{:ok, path}`, `{:partial, path}` when the goal got walled off, or
+  # `{:error, reason}`.
+  def replan_path(_path_planner, _from, _collision_grid), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Integration field towards the goal computed once over the whole grid, returns `{:ok, flow_field}`.
+  # Any number of bots heading to the same goal can steer with `flow_field_direction/2` lookups.
+  def build_flow_field(_collision_grid, _goal), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Unit direction to move towards the flow field goal from the position, zero once there or if unreachable
+  def flow_field_direction(_flow_field, _position), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Influence maps are updated in place, every call except the queries returns `:ok`.
+  # `half_life` is the milliseconds it takes the influence to fade to half of its value.
+  def new_influence_map(_half_life), do: :erlang.nif_error(:nif_not_loaded)
+
+  def decay_influence(_influence_map, _elapsed_time), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Sources are maps as `%{position: position, strength: float, radius: float, falloff: falloff}`,
+  # where falloff is one of `:constant`, `:linear` or `:quadratic`. Negative strengths mark dangerous areas.
+  # Sources without a positive radius are skipped.
+  def add_influence(_influence_map, _sources), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Adds the strength to every position outside of the safe zone.
+  def add_zone_influence(_influence_map, _zone_center, _zone_radius, _strength),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def influence_at(_influence_map, _position), do: :erlang.nif_error(:nif_not_loaded)
+
+  # Reachable position within `radius` of `around` with the highest influence, nil if there's none.
+  def best_influence_position(_influence_map, _collision_grid, _around, _radius),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # Triangulates the walkable area inside the external wall, with obstacles inflated by the agent radius.
+  # Returns `{:ok, mesh}` with a reference to the built mesh, meant to be built once per map and shared, or
+  # `{:error, reason}` when an obstacle can't be turned into a polygon or its border can't be kept in the mesh.
+  def build_navigation_mesh(_obstacles, _external_wall, _agent_radius), do: :erlang.nif_error(:nif_not_loaded)
+
+  def navigation_mesh_shortest_path(_navigation_mesh, _from, _to), do: :erlang.nif_error(:nif_not_loaded)
+
+  # SVG of the blocked cells of the grid with the external wall, obstacles, entities and paths drawn on top.
+  def render_debug_svg(_collision_grid, _external_wall, _obstacles, _entities, _paths),
+    do: :erlang.nif_error(:nif_not_loaded)
 end
@@ -324,19 +324,22 @@ defmodule BotManager.BotStateMachine do
     from = %{x: bot_state_machine.current_position.x, y: bot_state_machine.current_position.y}
     to = %{x: position_to_move_to.x, y: position_to_move_to.y}
 
-    shortest_path = AStarNative.a_star_shortest_path(from, to, bot_state_machine.collision_grid)
+    case AStarNative.a_star_shortest_path(from, to, bot_state_machine.collision_grid) do
+      # A partial path gets us as close as possible to an unreachable position, so we head to its end instead
+      {status, [_ | _] = shortest_path} when status in [:ok, :partial] ->
+        position_to_move_to = if status == :ok, do: position_to_move_to, else: List.last(shortest_path)
+
+        Map.put(bot_state_machine, :position_to_move_to, position_to_move_to)
+        |> Map.put(
+          :path_towards_position,
+          shortest_path
+        )
+        |> Map.put(:last_time_position_changed, :os.system_time(:millisecond))
 
-    # If we don't have a path, retry finding new position in map
-    if Enum.empty?(shortest_path) do
-      Map.put(bot_state_machine, :path_towards_position, nil)
-      |> Map.put(:position_to_move_to, nil)
-    else
-      Map.put(bot_state_machine, :position_to_move_to, position_to_move_to)
-      |> Map.put(
-        :path_towards_position,
-        shortest_path
-      )
-      |> Map.put(:last_time_position_changed, :os.system_time(:millisecond))
+      # If we don't have a path, retry finding new position in map
+      _ ->
+        Map.put(bot_state_machine, :path_towards_position, nil)
+        |> Map.put(:position_to_move_to, nil)
     end
   end
 end
@@ -42,7 +42,7 @@ defmodule BotManager.BotStateMachineChecker do
           ranged_attack_distance: integer(),
           melee_attack_distance: integer(),
           is_melee: boolean() | nil,
-          collision_grid: binary() | nil,
+          collision_grid: reference() | nil,
           last_time_state_changed: integer(),
           last_time_tracking_exited: integer(),
           last_time_attacking_exited: integer()
@@ -0,0 +1,200 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+
+use crate::cost_layers::CellCosts;
+use crate::grid::{octile_distance, Grid};
+use crate::AStarPathResult;
+
+#[derive(Clone, Copy, PartialEq)]
+struct NodeEntry {
+    node: usize,
+    parent: usize,
+    cost: f32,
+    estimate_reach_cost: f32,
+}
+
+impl Eq for NodeEntry {}
+
+impl Ord for NodeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate_reach_cost
+            .total_cmp(&self.estimate_reach_cost)
+    }
+}
+
+impl PartialOrd for NodeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub(crate) fn a_star_find_path(
+    start: (i64, i64),
+    goal: (i64, i64),
+    grid: &Grid,
+    cell_costs: &CellCosts,
+    max_expansions: usize,
+) -> AStarPathResult {
+    // Moving into a cell costs its cost layers weight, times sqrt(2) for diagonal moves. The
+    // heuristic is the octile distance over the cheapest cells, so it never overestimates
+    let min_cell_cost = cell_costs.min_cost();
+
+    // For each cell of the grid, its parent node on the shortest path tree,
+    // cells that weren't expanded yet have no parent
+    let mut parents: Vec<Option<usize>> = vec![None; grid.len()];
+    let goal_index = grid.index(goal);
+    let mut closest_node = grid.index(start);
+    let mut expansions = 0;
+
+    let mut priority_queue = BinaryHeap::new();
+    priority_queue.push(NodeEntry {
+        node: grid.index(start),
+        parent: grid.index(start),
+        cost: 0.0,
+        estimate_reach_cost: 0.0,
+    });
+
+    while let Some(NodeEntry {
+        node, parent, cost, ..
+    }) = priority_queue.pop()
+    {
+        if parents[node].is_some() {
+            continue;
+        }
+
+        parents[node] = Some(parent);
+
+        if node == goal_index {
+            return AStarPathResult::Found(build_path(start, goal, grid, &parents));
+        }
+
+        // Remember the expanded node closest to the goal in case the goal can't be reached
+        if octile_distance(grid.cell(node), goal) < octile_distance(grid.cell(closest_node), goal) {
+            closest_node = node;
+        }
+
+        expansions += 1;
+        if expansions >= max_expansions {
+            break;
+        }
+
+        let cell = grid.cell(node);
+        for neigh in grid.neighbors(cell) {
+            let neigh_index = grid.index(neigh);
+            if parents[neigh_index].is_none() {
+                let step = if neigh.0 != cell.0 && neigh.1 != cell.1 {
+                    SQRT_2
+                } else {
+                    1.0
+                };
+                let neigh_cost = cost + step * cell_costs.cost(neigh_index);
+                priority_queue.push(NodeEntry {
+                    node: neigh_index,
+                    parent: node,
+                    cost: neigh_cost,
+                    estimate_reach_cost: neigh_cost + octile_distance(neigh, goal) * min_cell_cost,
+                });
+            }
+        }
+    }
+
+    AStarPathResult::Partial(build_path(start, grid.cell(closest_node), grid, &parents))
+}
+
+fn build_path(
+    start: (i64, i64),
+    goal: (i64, i64),
+    grid: &Grid,
+    parents: &[Option<usize>],
+) -> Vec<(i64, i64)> {
+    let mut current = grid.index(goal);
+    let mut path = vec![goal];
+
+    while current != grid.index(start) {
+        current = parents[current].expect("Nodes on the path should have been expanded");
+        path.push(grid.cell(current));
+    }
+
+    path.reverse();
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arena_geometry::{Category, Entity, Position};
+
+    use crate::cost_layers::CostLayers;
+    use crate::grid::{self, GRID_CELL_SIZE, NUM_COLS, NUM_ROWS};
+
+    const START: (i64, i64) = (100, 100);
+    const GOAL: (i64, i64) = (100, 110);
+
+    // Pool covering the cells between the start and the goal, the straight route crosses all of it
+    fn pool_between_start_and_goal() -> CostLayers {
+        let inset = GRID_CELL_SIZE / 2.0 - 10.0;
+        let first = grid::grid_to_world_center(&(START.0, START.1 + 1));
+        let last = grid::grid_to_world_center(&(GOAL.0, GOAL.1 - 1));
+        let corner = |x: f32, y: f32| Position { x, y };
+        let mut pool = Entity::new_polygon(
+            1,
+            vec![
+                corner(first.x - inset, first.y - inset),
+                corner(first.x - inset, last.y + inset),
+                corner(last.x + inset, last.y + inset),
+                corner(last.x + inset, first.y - inset),
+            ],
+        );
+        pool.category = Category::Pool;
+
+        CostLayers::build(&[pool])
+    }
+
+    // Cost of the path as A* counts it
+    fn path_cost(path: &[(i64, i64)], grid: &Grid, cell_costs: &CellCosts) -> f32 {
+        path.windows(2)
+            .map(|step| {
+                let diagonal = step[0].0 != step[1].0 && step[0].1 != step[1].1;
+                let cost = cell_costs.cost(grid.index(step[1]));
+                if diagonal {
+                    SQRT_2 * cost
+                } else {
+                    cost
+                }
+            })
+            .sum()
+    }
+
+    fn find_path(pool_weight: f32) -> (f32, Vec<(i64, i64)>) {
+        let grid = Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize]);
+        let cost_layers = pool_between_start_and_goal();
+        let cell_costs = cost_layers.cell_costs(&[(Category::Pool, pool_weight)]);
+
+        match a_star_find_path(START, GOAL, &grid, &cell_costs, usize::MAX) {
+            AStarPathResult::Found(path) => (path_cost(&path, &grid, &cell_costs), path),
+            _ => panic!("There's nothing blocking the path"),
+        }
+    }
+
+    #[test]
+    fn diagonal_detour_around_an_expensive_pool_is_taken() {
+        // Straight through the pool costs 9 * 1.2 + 1, stepping out of its row and back costs
+        // 8 + 2 * sqrt(2)
+        let (cost, path) = find_path(0.2);
+
+        assert!((cost - (8.0 + 2.0 * SQRT_2)).abs() < 1e-3, "cost {}", cost);
+        assert!(path[1..path.len() - 1].iter().all(|cell| cell.0 != START.0));
+    }
+
+    #[test]
+    fn cheap_pool_is_crossed_instead_of_taking_the_diagonal_detour() {
+        // Straight through the pool costs 9 * 1.05 + 1, less than the 8 + 2 * sqrt(2) of the detour
+        let (cost, path) = find_path(0.05);
+
+        assert!((cost - (9.0 * 1.05 + 1.0)).abs() < 1e-3, "cost {}", cost);
+        assert!(path.iter().all(|cell| cell.0 == START.0));
+    }
+}
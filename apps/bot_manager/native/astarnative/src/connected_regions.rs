@@ -0,0 +1,222 @@
+use crate::grid::{Grid, NEIGHBOR_OFFSETS};
+
+/*
+ * Connected components of the free cells of the grid, labeled with a flood fill when the grid is
+ * built. Two cells have the same label if and only if there's a path between them, so checking
+ * if a position can be reached is just comparing two labels.
+ *
+ * Moves follow the same rules as the searches, diagonals can't cut blocked corners.
+ */
+#[derive(Clone)]
+pub(crate) struct ConnectedRegions {
+    // Region of each cell, None for blocked cells
+    labels: Vec<Option<usize>>,
+    // Amount of cells of each region
+    sizes: Vec<usize>,
+}
+
+impl ConnectedRegions {
+    pub(crate) fn build(grid: &Grid) -> ConnectedRegions {
+        let mut regions = ConnectedRegions {
+            labels: vec![None; grid.len()],
+            sizes: Vec::new(),
+        };
+
+        for index in 0..grid.len() {
+            let cell = grid.cell(index);
+            if grid.is_blocked(cell) || regions.labels[index].is_some() {
+                continue;
+            }
+
+            let region = regions.sizes.len();
+            regions.sizes.push(0);
+            regions.fill(cell, region, grid);
+        }
+
+        regions
+    }
+
+    // Labels of the grid after the given cells changed. Blocking cells can split their region and
+    // freeing them can join the regions around, so only the regions touching them are filled again
+    pub(crate) fn update(&self, grid: &Grid, changed_cells: &[(i64, i64)]) -> ConnectedRegions {
+        let cells_around: Vec<(i64, i64)> = changed_cells
+            .iter()
+            .flat_map(|cell| {
+                NEIGHBOR_OFFSETS
+                    .iter()
+                    .map(move |(row_offset, col_offset)| (cell.0 + row_offset, cell.1 + col_offset))
+                    .chain([*cell])
+            })
+            .filter(|cell| grid.contains(*cell))
+            .collect();
+
+        let mut affected_regions: Vec<usize> = cells_around
+            .iter()
+            .filter_map(|cell| self.labels[grid.index(*cell)])
+            .collect();
+        affected_regions.sort();
+        affected_regions.dedup();
+
+        let mut regions = self.clone();
+        for label in regions.labels.iter_mut() {
+            if label.is_some_and(|region| affected_regions.binary_search(&region).is_ok()) {
+                *label = None;
+            }
+        }
+        for &region in &affected_regions {
+            regions.sizes[region] = 0;
+        }
+
+        // Every piece of a split region still touches the changed cells, so filling from the
+        // cells around them labels all of them. The labels of the affected regions are reused
+        for cell in cells_around {
+            if grid.is_blocked(cell) || regions.labels[grid.index(cell)].is_some() {
+                continue;
+            }
+
+            let region = affected_regions.pop().unwrap_or_else(|| {
+                regions.sizes.push(0);
+                regions.sizes.len() - 1
+            });
+            regions.fill(cell, region, grid);
+        }
+
+        regions
+    }
+
+    // Labels every cell reachable from the given one with the region
+    fn fill(&mut self, cell: (i64, i64), region: usize, grid: &Grid) {
+        self.labels[grid.index(cell)] = Some(region);
+
+        let mut pending_cells = vec![cell];
+        while let Some(cell) = pending_cells.pop() {
+            self.sizes[region] += 1;
+
+            for neighbor in grid.neighbors(cell) {
+                let neighbor_index = grid.index(neighbor);
+                if self.labels[neighbor_index].is_none() {
+                    self.labels[neighbor_index] = Some(region);
+                    pending_cells.push(neighbor);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn region(&self, index: usize) -> Option<usize> {
+        self.labels[index]
+    }
+
+    pub(crate) fn size(&self, region: usize) -> usize {
+        self.sizes[region]
+    }
+
+    // The biggest region, where the bulk of the map is
+    pub(crate) fn main_region(&self) -> Option<usize> {
+        (0..self.sizes.len()).max_by_key(|region| self.sizes[*region])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_layers::CellCosts;
+    use crate::d_star_lite::DStarLite;
+    use crate::grid::{NUM_COLS, NUM_ROWS};
+    use crate::{a_star, jump_point_search, theta_star, AStarPathResult};
+
+    const START: (i64, i64) = (100, 100);
+    const GOAL: (i64, i64) = (120, 120);
+
+    // Diamond of blocked cells around the start, its cells only touch each other by the corners
+    // so leaving it means going through a diagonal gap between two blocked cells
+    fn diamond_around_start() -> Grid {
+        let mut grid = Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize]);
+        for row in -3..=3_i64 {
+            let col = 3 - row.abs();
+            grid.set_blocked((START.0 + row, START.1 + col), true);
+            grid.set_blocked((START.0 + row, START.1 - col), true);
+        }
+        grid
+    }
+
+    #[test]
+    fn searches_and_regions_agree_on_diagonal_gaps() {
+        let grid = diamond_around_start();
+        let regions = ConnectedRegions::build(&grid);
+
+        assert_ne!(
+            regions.region(grid.index(START)),
+            regions.region(grid.index(GOAL))
+        );
+
+        let results = [
+            a_star::a_star_find_path(START, GOAL, &grid, &CellCosts::default(), usize::MAX),
+            jump_point_search::jump_point_search_find_path(START, GOAL, &grid, usize::MAX),
+            theta_star::theta_star_find_path(START, GOAL, &grid, usize::MAX),
+            DStarLite::new(START, GOAL, &grid).replan(START, &grid),
+        ];
+        for result in results {
+            assert!(!matches!(result, AStarPathResult::Found(_)));
+        }
+    }
+
+    #[test]
+    fn diagonal_gap_next_to_a_free_cell_can_be_crossed() {
+        let mut grid = diamond_around_start();
+        grid.set_blocked((START.0 - 2, START.1 + 1), false);
+        let regions = ConnectedRegions::build(&grid);
+
+        assert_eq!(
+            regions.region(grid.index(START)),
+            regions.region(grid.index(GOAL))
+        );
+        assert!(matches!(
+            a_star::a_star_find_path(START, GOAL, &grid, &CellCosts::default(), usize::MAX),
+            AStarPathResult::Found(_)
+        ));
+    }
+
+    // Same region for the same cells in both labelings, whatever the labels are
+    fn assert_same_regions(regions: &ConnectedRegions, other_regions: &ConnectedRegions) {
+        let mut labels = std::collections::HashMap::new();
+        for (label, other_label) in regions.labels.iter().zip(&other_regions.labels) {
+            assert_eq!(labels.entry(*label).or_insert(*other_label), other_label);
+            if let Some(region) = label {
+                assert_eq!(
+                    regions.size(*region),
+                    other_regions.size(other_label.unwrap())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn updates_match_labeling_again() {
+        let mut grid = diamond_around_start();
+        let mut regions = ConnectedRegions::build(&grid);
+
+        // A wall across the whole grid splits the main region in two
+        let wall: Vec<(i64, i64)> = (0..NUM_COLS).map(|col| (150, col)).collect();
+        for cell in &wall {
+            grid.set_blocked(*cell, true);
+        }
+        regions = regions.update(&grid, &wall);
+        assert_same_regions(&regions, &ConnectedRegions::build(&grid));
+        assert_ne!(
+            regions.region(grid.index((149, 0))),
+            regions.region(grid.index((151, 0)))
+        );
+
+        // Opening the wall and the diamond joins them again
+        let openings = [(150, 20), (START.0 - 2, START.1 + 1)];
+        for cell in openings {
+            grid.set_blocked(cell, false);
+        }
+        regions = regions.update(&grid, &openings);
+        assert_same_regions(&regions, &ConnectedRegions::build(&grid));
+        assert_eq!(
+            regions.region(grid.index(START)),
+            regions.region(grid.index((151, 0)))
+        );
+    }
+}
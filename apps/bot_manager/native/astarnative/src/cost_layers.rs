@@ -0,0 +1,105 @@
+use arena_geometry::{Category, Entity};
+
+use crate::grid::{self, NUM_COLS, NUM_ROWS};
+
+/*
+ * Cost layers mark the cells covered by entities of a category (pools, traps, bushes...).
+ * The layers themselves don't carry a cost, each query sends a weight per category that gets
+ * added to the base cost of 1 of every cell in that layer. This way the same grid serves bots
+ * that avoid pools at any cost and bots that don't mind walking through them to save distance.
+ *
+ * Negative weights make cells cheaper, e.g. bushes for bots that want to stay hidden.
+ */
+
+// Weighted cells can't get cheaper than this, so searches still prefer shorter paths
+const MIN_CELL_COST: f32 = 0.1;
+
+#[derive(Clone)]
+struct CostLayer {
+    category: Category,
+    covered_cells: Vec<bool>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct CostLayers {
+    layers: Vec<CostLayer>,
+}
+
+impl CostLayers {
+    pub(crate) fn build(entities: &[Entity]) -> CostLayers {
+        let mut cost_layers = CostLayers::default();
+
+        for entity in entities {
+            let layer_index = match cost_layers
+                .layers
+                .iter()
+                .position(|layer| layer.category == entity.category)
+            {
+                Some(layer_index) => layer_index,
+                None => {
+                    cost_layers.layers.push(CostLayer {
+                        category: entity.category.clone(),
+                        covered_cells: vec![false; (NUM_ROWS * NUM_COLS) as usize],
+                    });
+                    cost_layers.layers.len() - 1
+                }
+            };
+
+            let layer = &mut cost_layers.layers[layer_index];
+            for cell in grid::cells_around(entity) {
+                if grid::cell_overlaps(&cell, entity) {
+                    layer.covered_cells[(cell.0 * NUM_COLS + cell.1) as usize] = true;
+                }
+            }
+        }
+
+        cost_layers
+    }
+
+    // Costs for a query given the weight of each category, categories without a layer are ignored
+    pub(crate) fn cell_costs(&self, weights: &[(Category, f32)]) -> CellCosts<'_> {
+        let weighted_layers: Vec<(&[bool], f32)> = weights
+            .iter()
+            .filter_map(|(category, weight)| {
+                self.layers
+                    .iter()
+                    .find(|layer| layer.category == *category)
+                    .map(|layer| (layer.covered_cells.as_slice(), *weight))
+            })
+            .collect();
+
+        CellCosts { weighted_layers }
+    }
+}
+
+// Cost of entering each cell of the grid for a single query
+#[derive(Default)]
+pub(crate) struct CellCosts<'a> {
+    weighted_layers: Vec<(&'a [bool], f32)>,
+}
+
+impl CellCosts<'_> {
+    pub(crate) fn cost(&self, index: usize) -> f32 {
+        let cost = self
+            .weighted_layers
+            .iter()
+            .filter(|(covered_cells, _)| covered_cells[index])
+            .map(|(_, weight)| weight)
+            .sum::<f32>()
+            + 1.0;
+
+        cost.max(MIN_CELL_COST)
+    }
+
+    // Lower bound of the cost of any cell, heuristics get scaled by it to stay admissible
+    pub(crate) fn min_cost(&self) -> f32 {
+        let cost = self
+            .weighted_layers
+            .iter()
+            .map(|(_, weight)| weight.min(0.0))
+            .sum::<f32>()
+            + 1.0;
+
+        cost.max(MIN_CELL_COST)
+    }
+}
@@ -0,0 +1,76 @@
+use std::f32::consts::SQRT_2;
+
+use arena_geometry::Position;
+
+use crate::grid::{self, CollisionGrid, GRID_CELL_SIZE, NUM_COLS, NUM_ROWS};
+use crate::path_smoothing;
+
+/*
+ * Cover positions are free cells right next to an obstacle (according to the distance field)
+ * where that obstacle blocks the line of sight from the threat. Only cells that can be reached
+ * from `around` are considered, and they're ranked by their distance to it so bots retreat to the
+ * closest cover.
+ */
+
+// Cells further away than this from an obstacle don't count as cover, bots have to hug the obstacle
+const MAX_COVER_DISTANCE: f32 = SQRT_2;
+
+pub(crate) fn find_cover_positions(
+    threat_position: &Position,
+    around: &Position,
+    radius: f32,
+    collision_grid: &CollisionGrid,
+) -> Vec<Position> {
+    let grid = &collision_grid.grid;
+
+    // A threat inside an obstacle would have no line of sight at all, so it's snapped like path endpoints
+    let (Some(threat_cell), Some(around_cell)) = (
+        grid.nearest_free_cell(grid::world_to_grid(threat_position)),
+        grid.nearest_free_cell(grid::world_to_grid(around)),
+    ) else {
+        return Vec::new();
+    };
+    let around_region = collision_grid
+        .connected_regions
+        .region(grid.index(around_cell));
+
+    let radius_in_cells = (radius / GRID_CELL_SIZE).ceil() as i64;
+    let mut cover_positions: Vec<(f32, Position)> = Vec::new();
+
+    let rows = (around_cell.0 - radius_in_cells).max(0)
+        ..=(around_cell.0 + radius_in_cells).min(NUM_ROWS - 1);
+    let cols = (around_cell.1 - radius_in_cells).max(0)
+        ..=(around_cell.1 + radius_in_cells).min(NUM_COLS - 1);
+
+    for row in rows {
+        for col in cols.clone() {
+            let cell = (row, col);
+            if grid.is_blocked(cell) {
+                continue;
+            }
+
+            let index = grid.index(cell);
+            if collision_grid.distance_field.distance(index) > MAX_COVER_DISTANCE
+                || collision_grid.connected_regions.region(index) != around_region
+            {
+                continue;
+            }
+
+            let position = grid::grid_to_world_center(&cell);
+            let distance = position.distance_to_position(around);
+            if distance > radius || path_smoothing::has_line_of_sight(threat_cell, cell, grid) {
+                continue;
+            }
+
+            cover_positions.push((distance, position));
+        }
+    }
+
+    cover_positions
+        .sort_by(|(distance, _), (other_distance, _)| distance.total_cmp(other_distance));
+
+    cover_positions
+        .into_iter()
+        .map(|(_, position)| position)
+        .collect()
+}
@@ -0,0 +1,338 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+use std::sync::Mutex;
+
+use crate::grid::{octile_distance, Grid, NEIGHBOR_OFFSETS};
+use crate::AStarPathResult;
+
+/*
+D* Lite incremental planner
+
+The search runs backwards, from the goal towards the start, and keeps its state between queries.
+For every cell we store `g`, the cost to the goal found so far, and `rhs`, a one step lookahead
+computed from the neighbors' `g`. A cell is consistent when both match and only inconsistent
+cells are kept in the open list.
+
+When cells get blocked or unblocked only the cells around them become inconsistent, so repairing
+the path expands the cells affected by the change instead of searching the whole grid again.
+Since the search is rooted at the goal, the start can move along the path without invalidating
+anything, the heuristic values are kept valid by accumulating how much the start moved (`km`).
+
+Moves can't cut blocked corners, straight moves cost 1 and diagonal ones sqrt(2).
+
+- [D* Lite paper](http://idm-lab.org/bib/abstracts/papers/aaai02b.pdf)
+*/
+
+// Resource holding a bot's planner between queries, every query gets exclusive access to it
+pub struct PathPlanner {
+    pub(crate) state: Mutex<DStarLite>,
+}
+
+pub(crate) struct DStarLite {
+    start: (i64, i64),
+    goal: (i64, i64),
+    // Start at the time of the last grid change, `km` adds up how much the start moved at each change
+    last_start: (i64, i64),
+    km: f32,
+    g: Vec<f32>,
+    rhs: Vec<f32>,
+    // Key each cell currently has in the open list, entries of the queue with a different key are stale
+    queued_keys: Vec<Option<Key>>,
+    open_list: BinaryHeap<NodeEntry>,
+    // The grid the current state was computed with, used to find the cells that changed
+    grid: Grid,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Key(f32, f32);
+
+impl Eq for Key {}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .total_cmp(&other.0)
+            .then_with(|| self.1.total_cmp(&other.1))
+    }
+}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct NodeEntry {
+    node: usize,
+    key: Key,
+}
+
+impl Ord for NodeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.cmp(&self.key)
+    }
+}
+
+impl PartialOrd for NodeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl DStarLite {
+    pub(crate) fn new(start: (i64, i64), goal: (i64, i64), grid: &Grid) -> DStarLite {
+        let mut planner = DStarLite {
+            start,
+            goal,
+            last_start: start,
+            km: 0.0,
+            g: vec![f32::INFINITY; grid.len()],
+            rhs: vec![f32::INFINITY; grid.len()],
+            queued_keys: vec![None; grid.len()],
+            open_list: BinaryHeap::new(),
+            grid: grid.clone(),
+        };
+
+        let goal_index = grid.index(goal);
+        planner.rhs[goal_index] = 0.0;
+        planner.push(goal_index);
+
+        planner
+    }
+
+    pub(crate) fn goal(&self) -> (i64, i64) {
+        self.goal
+    }
+
+    // Updates the planner to the current grid and start and returns the path to the goal
+    pub(crate) fn replan(&mut self, start: (i64, i64), grid: &Grid) -> AStarPathResult {
+        self.start = start;
+
+        let changed_cells = self.grid.changed_cells(grid);
+
+        if !changed_cells.is_empty() {
+            self.km += octile_distance(self.last_start, self.start);
+            self.last_start = self.start;
+            self.grid = grid.clone();
+
+            // Only the moves out of cells within one cell of a changed one can change their cost
+            for index in changed_cells {
+                let cell = grid.cell(index);
+                self.update_cell(cell);
+                for (row_offset, col_offset) in NEIGHBOR_OFFSETS {
+                    self.update_cell((cell.0 + row_offset, cell.1 + col_offset));
+                }
+            }
+        }
+
+        self.compute_shortest_path();
+
+        self.build_path()
+    }
+
+    fn compute_shortest_path(&mut self) {
+        let start_index = self.grid.index(self.start);
+
+        while let Some(&NodeEntry { node, key }) = self.open_list.peek() {
+            if self.queued_keys[node] != Some(key) {
+                self.open_list.pop();
+                continue;
+            }
+
+            if key >= self.key(start_index) && self.rhs[start_index] == self.g[start_index] {
+                break;
+            }
+
+            self.open_list.pop();
+            self.queued_keys[node] = None;
+
+            let new_key = self.key(node);
+            if key < new_key {
+                self.push(node);
+                continue;
+            }
+
+            let cell = self.grid.cell(node);
+            if self.g[node] > self.rhs[node] {
+                self.g[node] = self.rhs[node];
+            } else {
+                self.g[node] = f32::INFINITY;
+                self.update_cell(cell);
+            }
+
+            for (row_offset, col_offset) in NEIGHBOR_OFFSETS {
+                self.update_cell((cell.0 + row_offset, cell.1 + col_offset));
+            }
+        }
+    }
+
+    // Recomputes the lookahead of the cell and queues it if it became inconsistent
+    fn update_cell(&mut self, cell: (i64, i64)) {
+        if !self.grid.contains(cell) {
+            return;
+        }
+
+        let index = self.grid.index(cell);
+        if cell != self.goal {
+            self.rhs[index] = NEIGHBOR_OFFSETS
+                .iter()
+                .filter_map(|direction| {
+                    let neighbor = (cell.0 + direction.0, cell.1 + direction.1);
+                    self.move_cost(cell, *direction)
+                        .map(|cost| cost + self.g[self.grid.index(neighbor)])
+                })
+                .fold(f32::INFINITY, f32::min);
+        }
+
+        if self.g[index] != self.rhs[index] {
+            self.push(index);
+        } else {
+            self.queued_keys[index] = None;
+        }
+    }
+
+    // Cost of moving from the cell to its neighbor in the given direction, None if it can't be done
+    fn move_cost(&self, from: (i64, i64), direction: (i64, i64)) -> Option<f32> {
+        if !self.grid.can_move(from, direction) {
+            return None;
+        }
+
+        if direction.0 != 0 && direction.1 != 0 {
+            Some(SQRT_2)
+        } else {
+            Some(1.0)
+        }
+    }
+
+    fn key(&self, node: usize) -> Key {
+        let min_cost = self.g[node].min(self.rhs[node]);
+
+        Key(
+            min_cost + octile_distance(self.start, self.grid.cell(node)) + self.km,
+            min_cost,
+        )
+    }
+
+    fn push(&mut self, node: usize) {
+        let key = self.key(node);
+        self.queued_keys[node] = Some(key);
+        self.open_list.push(NodeEntry { node, key });
+    }
+
+    // Follows the cheapest neighbors from the start down to the goal
+    fn build_path(&self) -> AStarPathResult {
+        if self.g[self.grid.index(self.start)].is_infinite() {
+            return AStarPathResult::NotFound;
+        }
+
+        let mut path = vec![self.start];
+        let mut current = self.start;
+
+        while current != self.goal {
+            let next = NEIGHBOR_OFFSETS
+                .iter()
+                .filter_map(|direction| {
+                    let neighbor = (current.0 + direction.0, current.1 + direction.1);
+                    self.move_cost(current, *direction)
+                        .map(|cost| (neighbor, cost + self.g[self.grid.index(neighbor)]))
+                })
+                .min_by(|(_, cost), (_, other_cost)| cost.total_cmp(other_cost));
+
+            match next {
+                // A path longer than the amount of cells means we are going around in circles
+                Some((neighbor, cost)) if cost.is_finite() && path.len() < self.grid.len() => {
+                    path.push(neighbor);
+                    current = neighbor;
+                }
+                _ => return AStarPathResult::NotFound,
+            }
+        }
+
+        AStarPathResult::Found(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arena_geometry::{Entity, Position};
+
+    use crate::a_star::a_star_find_path;
+    use crate::cost_layers::CellCosts;
+    use crate::grid::{grid_to_world_center, CollisionGrid, GRID_CELL_SIZE};
+
+    const START: (i64, i64) = (100, 80);
+    const GOAL: (i64, i64) = (100, 120);
+
+    // Crate right on the straight line between the start and the goal
+    fn crate_between_start_and_goal() -> Entity {
+        let center = grid_to_world_center(&(100, 100));
+        let half_side = GRID_CELL_SIZE * 5.0;
+        let corner = |x: f32, y: f32| Position {
+            x: center.x + x,
+            y: center.y + y,
+        };
+
+        Entity::new_polygon(
+            1,
+            vec![
+                corner(-half_side, -half_side),
+                corner(-half_side, half_side),
+                corner(half_side, half_side),
+                corner(half_side, -half_side),
+            ],
+        )
+    }
+
+    fn path_cost(path: &[(i64, i64)]) -> f32 {
+        path.windows(2)
+            .map(|pair| octile_distance(pair[0], pair[1]))
+            .sum()
+    }
+
+    fn found_path(path_result: AStarPathResult) -> Vec<(i64, i64)> {
+        match path_result {
+            AStarPathResult::Found(path) => path,
+            _ => panic!("The goal should be reachable"),
+        }
+    }
+
+    // The repaired path costs the same as searching from scratch and only walks free cells
+    fn assert_matches_a_star(planner: &mut DStarLite, grid: &Grid) {
+        let path = found_path(planner.replan(START, grid));
+        let a_star_path = found_path(a_star_find_path(
+            START,
+            GOAL,
+            grid,
+            &CellCosts::default(),
+            usize::MAX,
+        ));
+
+        assert!((path_cost(&path) - path_cost(&a_star_path)).abs() < 1e-3);
+        assert!(path.iter().all(|cell| !grid.is_blocked(*cell)));
+    }
+
+    #[test]
+    fn repaired_paths_match_a_star_after_grid_changes() {
+        let collision_grid = CollisionGrid::build(Vec::new());
+        let mut planner = DStarLite::new(START, GOAL, &collision_grid.grid);
+        assert_matches_a_star(&mut planner, &collision_grid.grid);
+
+        let blocked_grid = collision_grid
+            .with_obstacle(crate_between_start_and_goal())
+            .unwrap();
+        assert!(blocked_grid.grid.is_blocked((100, 100)));
+        assert_matches_a_star(&mut planner, &blocked_grid.grid);
+
+        let unblocked_grid = blocked_grid
+            .without_obstacle(&crate_between_start_and_goal())
+            .unwrap();
+        assert_matches_a_star(&mut planner, &unblocked_grid.grid);
+        assert_eq!(
+            path_cost(&found_path(planner.replan(START, &unblocked_grid.grid))),
+            (GOAL.1 - START.1) as f32
+        );
+    }
+}
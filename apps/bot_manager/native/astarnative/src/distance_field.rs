@@ -0,0 +1,274 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::f32::consts::SQRT_2;
+
+use crate::grid::{Grid, NEIGHBOR_OFFSETS};
+
+/*
+ * Distance from every cell to the closest blocked cell, measured in cells between centers with
+ * straight steps costing 1 and diagonal ones sqrt(2). Cells outside of the grid count as blocked.
+ *
+ * It's computed with two passes over the grid (chamfer distance transform): the first one
+ * propagates distances from the cells above and to the left, the second one from the cells below
+ * and to the right. Together they cover every octile path from a cell to its closest obstacle.
+ *
+ * When cells change the field is repaired instead of computed again. Freed cells take their
+ * distance from the cells around the cells that had them as closest blocked cell, and newly
+ * blocked cells spread shorter distances out. Both are Dijkstra searches that stop as soon as
+ * distances don't change, so they only visit the cells around the changes.
+ */
+
+const FORWARD_OFFSETS: [(i64, i64); 4] = [(-1, -1), (-1, 0), (-1, 1), (0, -1)];
+const BACKWARD_OFFSETS: [(i64, i64); 4] = [(1, 1), (1, 0), (1, -1), (0, 1)];
+
+// Distances that may still be wrong after a change are kept at this margin of the right ones
+const DISTANCE_EPSILON: f32 = 1e-3;
+
+#[derive(Clone)]
+pub(crate) struct DistanceField {
+    distances: Vec<f32>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct CellEntry {
+    index: usize,
+    distance: f32,
+}
+
+impl Eq for CellEntry {}
+
+impl Ord for CellEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+impl PartialOrd for CellEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl DistanceField {
+    pub(crate) fn build(grid: &Grid) -> DistanceField {
+        let mut distances: Vec<f32> = (0..grid.len())
+            .map(|index| {
+                if grid.is_blocked(grid.cell(index)) {
+                    0.0
+                } else {
+                    f32::INFINITY
+                }
+            })
+            .collect();
+
+        for index in 0..grid.len() {
+            relax(index, &FORWARD_OFFSETS, grid, &mut distances);
+        }
+        for index in (0..grid.len()).rev() {
+            relax(index, &BACKWARD_OFFSETS, grid, &mut distances);
+        }
+
+        DistanceField { distances }
+    }
+
+    // Field of the grid after the given cells changed
+    pub(crate) fn update(&self, grid: &Grid, changed_cells: &[(i64, i64)]) -> DistanceField {
+        let (blocked_cells, freed_cells): (Vec<usize>, Vec<usize>) = changed_cells
+            .iter()
+            .map(|cell| grid.index(*cell))
+            .partition(|index| grid.is_blocked(grid.cell(*index)));
+
+        let mut distances = self.distances.clone();
+        for &index in &blocked_cells {
+            distances[index] = 0.0;
+        }
+
+        // Cells that were closest to a freed cell take their distance from the cells around them
+        let orphan_cells = self.cells_closest_to(&freed_cells, grid);
+        for &index in &orphan_cells {
+            distances[index] = f32::INFINITY;
+        }
+
+        let mut priority_queue = BinaryHeap::new();
+        for &index in &orphan_cells {
+            let distance = closest_neighbor_distance(index, grid, &distances);
+            if distance.is_finite() {
+                distances[index] = distance;
+                priority_queue.push(CellEntry { index, distance });
+            }
+        }
+        spread(priority_queue, grid, &mut distances);
+
+        // Newly blocked cells can only bring the cells around them closer to an obstacle
+        let priority_queue = blocked_cells
+            .iter()
+            .map(|&index| CellEntry {
+                index,
+                distance: 0.0,
+            })
+            .collect();
+        spread(priority_queue, grid, &mut distances);
+
+        DistanceField { distances }
+    }
+
+    pub(crate) fn distance(&self, index: usize) -> f32 {
+        self.distances[index]
+    }
+
+    // Cells whose closest blocked cell is one of the given ones. The shortest path from any of
+    // them to that blocked cell only goes through cells that have it as the closest one too,
+    // so we search outwards from the blocked cells while that holds
+    fn cells_closest_to(&self, cells: &[usize], grid: &Grid) -> Vec<usize> {
+        let mut reached: HashMap<usize, f32> = cells.iter().map(|&index| (index, 0.0)).collect();
+        let mut priority_queue: BinaryHeap<CellEntry> = cells
+            .iter()
+            .map(|&index| CellEntry {
+                index,
+                distance: 0.0,
+            })
+            .collect();
+        let mut closest_cells = Vec::new();
+
+        while let Some(CellEntry { index, distance }) = priority_queue.pop() {
+            if distance > reached[&index] {
+                continue;
+            }
+            closest_cells.push(index);
+
+            let cell = grid.cell(index);
+            for (row_offset, col_offset) in NEIGHBOR_OFFSETS {
+                let neighbor = (cell.0 + row_offset, cell.1 + col_offset);
+                if !grid.contains(neighbor) {
+                    continue;
+                }
+
+                let neighbor_index = grid.index(neighbor);
+                let neighbor_distance = distance + step(row_offset, col_offset);
+                let is_closer = reached
+                    .get(&neighbor_index)
+                    .is_none_or(|reached_distance| neighbor_distance < *reached_distance);
+                if is_closer
+                    && neighbor_distance <= self.distances[neighbor_index] + DISTANCE_EPSILON
+                {
+                    reached.insert(neighbor_index, neighbor_distance);
+                    priority_queue.push(CellEntry {
+                        index: neighbor_index,
+                        distance: neighbor_distance,
+                    });
+                }
+            }
+        }
+
+        closest_cells
+    }
+}
+
+// Lowers the distances of the cells around the queued ones until they don't change
+fn spread(mut priority_queue: BinaryHeap<CellEntry>, grid: &Grid, distances: &mut [f32]) {
+    while let Some(CellEntry { index, distance }) = priority_queue.pop() {
+        if distance > distances[index] {
+            continue;
+        }
+
+        let cell = grid.cell(index);
+        for (row_offset, col_offset) in NEIGHBOR_OFFSETS {
+            let neighbor = (cell.0 + row_offset, cell.1 + col_offset);
+            if !grid.contains(neighbor) {
+                continue;
+            }
+
+            let neighbor_index = grid.index(neighbor);
+            let neighbor_distance = distance + step(row_offset, col_offset);
+            if neighbor_distance < distances[neighbor_index] {
+                distances[neighbor_index] = neighbor_distance;
+                priority_queue.push(CellEntry {
+                    index: neighbor_index,
+                    distance: neighbor_distance,
+                });
+            }
+        }
+    }
+}
+
+// Distance through the closest of the cells around, cells outside of the grid count as blocked
+fn closest_neighbor_distance(index: usize, grid: &Grid, distances: &[f32]) -> f32 {
+    let cell = grid.cell(index);
+
+    NEIGHBOR_OFFSETS
+        .iter()
+        .map(|(row_offset, col_offset)| {
+            let neighbor = (cell.0 + row_offset, cell.1 + col_offset);
+            let neighbor_distance = if grid.contains(neighbor) {
+                distances[grid.index(neighbor)]
+            } else {
+                0.0
+            };
+
+            neighbor_distance + step(*row_offset, *col_offset)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+fn step(row_offset: i64, col_offset: i64) -> f32 {
+    if row_offset != 0 && col_offset != 0 {
+        SQRT_2
+    } else {
+        1.0
+    }
+}
+
+fn relax(index: usize, offsets: &[(i64, i64)], grid: &Grid, distances: &mut [f32]) {
+    let cell = grid.cell(index);
+
+    for (row_offset, col_offset) in offsets {
+        let neighbor = (cell.0 + row_offset, cell.1 + col_offset);
+        let neighbor_distance = if grid.contains(neighbor) {
+            distances[grid.index(neighbor)]
+        } else {
+            0.0
+        };
+        distances[index] = distances[index].min(neighbor_distance + step(*row_offset, *col_offset));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{NUM_COLS, NUM_ROWS};
+
+    fn assert_same_distances(field: &DistanceField, other_field: &DistanceField) {
+        for (distance, other_distance) in field.distances.iter().zip(&other_field.distances) {
+            assert!((distance - other_distance).abs() < DISTANCE_EPSILON);
+        }
+    }
+
+    #[test]
+    fn updates_match_computing_again() {
+        let mut grid = Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize]);
+        let obstacle: Vec<(i64, i64)> = (90..110)
+            .flat_map(|row| (40..45).map(move |col| (row, col)))
+            .collect();
+        for cell in &obstacle {
+            grid.set_blocked(*cell, true);
+        }
+        let mut field = DistanceField::build(&grid);
+
+        let added_obstacle: Vec<(i64, i64)> = (60..64)
+            .flat_map(|row| (60..80).map(move |col| (row, col)))
+            .collect();
+        for cell in &added_obstacle {
+            grid.set_blocked(*cell, true);
+        }
+        field = field.update(&grid, &added_obstacle);
+        assert_same_distances(&field, &DistanceField::build(&grid));
+
+        // Freeing part of the first obstacle leaves cells far from any other one
+        let freed_cells = &obstacle[..50];
+        for cell in freed_cells {
+            grid.set_blocked(*cell, false);
+        }
+        field = field.update(&grid, freed_cells);
+        assert_same_distances(&field, &DistanceField::build(&grid));
+    }
+}
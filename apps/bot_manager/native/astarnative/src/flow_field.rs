@@ -0,0 +1,197 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+
+use arena_geometry::{Direction, Position};
+
+use crate::grid::{self, Grid, NEIGHBOR_OFFSETS};
+
+/*
+Flow field
+
+Instead of a path per bot, a Dijkstra search is run once from the goal over the whole grid
+(the integration field) and every cell stores the direction of the neighbor it was reached from,
+which is the first step of its shortest path towards the goal. Any number of bots heading to the
+same goal can then steer with a lookup of the cell they are standing on.
+
+Lookups steer towards the center of the next cell instead of using the raw grid direction, which
+keeps bots close to the cell centers so they don't clip obstacle corners while moving diagonally.
+
+Moves can't cut blocked corners, straight moves cost 1 and diagonal ones sqrt(2).
+*/
+
+pub struct FlowField {
+    goal_cell: (i64, i64),
+    // For each cell, the (row, col) step towards the goal, (0, 0) for the goal and unreachable cells
+    directions: Vec<(i64, i64)>,
+    grid: Grid,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct NodeEntry {
+    node: usize,
+    cost: f32,
+}
+
+impl Eq for NodeEntry {}
+
+impl Ord for NodeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for NodeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FlowField {
+    pub(crate) fn build(goal: Position, grid: &Grid) -> Option<FlowField> {
+        let goal_cell = grid::world_to_grid(&goal);
+        if grid.is_blocked(goal_cell) {
+            return None;
+        }
+
+        let mut costs: Vec<f32> = vec![f32::INFINITY; grid.len()];
+        let mut directions: Vec<(i64, i64)> = vec![(0, 0); grid.len()];
+        let mut expanded: Vec<bool> = vec![false; grid.len()];
+
+        let goal_index = grid.index(goal_cell);
+        costs[goal_index] = 0.0;
+
+        let mut priority_queue = BinaryHeap::new();
+        priority_queue.push(NodeEntry {
+            node: goal_index,
+            cost: 0.0,
+        });
+
+        while let Some(NodeEntry { node, cost }) = priority_queue.pop() {
+            if expanded[node] {
+                continue;
+            }
+            expanded[node] = true;
+
+            let cell = grid.cell(node);
+            for (row_offset, col_offset) in NEIGHBOR_OFFSETS {
+                // We are searching backwards, so check the move from the neighbor into this cell
+                let neighbor = (cell.0 + row_offset, cell.1 + col_offset);
+                let direction = (-row_offset, -col_offset);
+                if !grid.contains(neighbor) || !grid.can_move(neighbor, direction) {
+                    continue;
+                }
+
+                let move_cost = if row_offset != 0 && col_offset != 0 {
+                    SQRT_2
+                } else {
+                    1.0
+                };
+                let neighbor_index = grid.index(neighbor);
+                if cost + move_cost < costs[neighbor_index] {
+                    costs[neighbor_index] = cost + move_cost;
+                    directions[neighbor_index] = direction;
+                    priority_queue.push(NodeEntry {
+                        node: neighbor_index,
+                        cost: cost + move_cost,
+                    });
+                }
+            }
+        }
+
+        Some(FlowField {
+            goal_cell,
+            directions,
+            grid: grid.clone(),
+        })
+    }
+
+    // Unit direction to steer towards the goal from the position, zero when there's no way to it
+    pub(crate) fn direction_at(&self, position: &Position) -> Direction {
+        let cell = grid::world_to_grid(position);
+        if !self.grid.contains(cell) || cell == self.goal_cell {
+            return Direction { x: 0.0, y: 0.0 };
+        }
+
+        let (row_step, col_step) = self.directions[self.grid.index(cell)];
+        if (row_step, col_step) == (0, 0) {
+            return Direction { x: 0.0, y: 0.0 };
+        }
+
+        let next_cell = (cell.0 + row_step, cell.1 + col_step);
+        let mut direction = Position::sub(&grid::grid_to_world_center(&next_cell), position);
+        direction.normalize();
+
+        Direction {
+            x: direction.x,
+            y: direction.y,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connected_regions::ConnectedRegions;
+    use crate::grid::{GRID_CELL_SIZE, NUM_COLS, NUM_ROWS};
+
+    const GOAL: (i64, i64) = (100, 100);
+    // Amount of moves after which a bot following the field should have arrived from anywhere
+    const MAX_MOVES: usize = 2000;
+
+    // A wall to go around, and a closed box whose cells can't reach the goal
+    fn grid_with_wall_and_box() -> Grid {
+        let mut grid = Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize]);
+        for row in 60..140 {
+            grid.set_blocked((row, 90), true);
+        }
+        for offset in 0..10 {
+            grid.set_blocked((30, 30 + offset), true);
+            grid.set_blocked((39, 30 + offset), true);
+            grid.set_blocked((30 + offset, 30), true);
+            grid.set_blocked((30 + offset, 39), true);
+        }
+        grid
+    }
+
+    // Moves half a cell at a time in the direction of the field until it says to stop
+    fn follow(flow_field: &FlowField, grid: &Grid, from: (i64, i64)) -> (i64, i64) {
+        let mut position = grid::grid_to_world_center(&from);
+
+        for _ in 0..MAX_MOVES {
+            let direction = flow_field.direction_at(&position);
+            if direction.x == 0.0 && direction.y == 0.0 {
+                break;
+            }
+
+            position.x += direction.x * GRID_CELL_SIZE / 2.0;
+            position.y += direction.y * GRID_CELL_SIZE / 2.0;
+            assert!(!grid.is_blocked(grid::world_to_grid(&position)));
+        }
+
+        grid::world_to_grid(&position)
+    }
+
+    #[test]
+    fn following_the_field_ends_at_the_goal() {
+        let grid = grid_with_wall_and_box();
+        let regions = ConnectedRegions::build(&grid);
+        let flow_field = FlowField::build(grid::grid_to_world_center(&GOAL), &grid).unwrap();
+        let goal_region = regions.region(grid.index(GOAL));
+
+        for row in (0..NUM_ROWS).step_by(7) {
+            for col in (0..NUM_COLS).step_by(7) {
+                let cell = (row, col);
+                if grid.is_blocked(cell) {
+                    continue;
+                }
+
+                if regions.region(grid.index(cell)) == goal_region {
+                    assert_eq!(follow(&flow_field, &grid, cell), GOAL);
+                } else {
+                    assert_eq!(follow(&flow_field, &grid, cell), cell);
+                }
+            }
+        }
+    }
+}
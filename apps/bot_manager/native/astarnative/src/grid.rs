@@ -0,0 +1,501 @@
+use std::f32::consts::SQRT_2;
+
+use arena_geometry::{Entity, Position, Shape};
+
+use crate::connected_regions::ConnectedRegions;
+use crate::cost_layers::CostLayers;
+use crate::distance_field::DistanceField;
+use crate::hierarchical_pathfinding::ClusterGraph;
+
+pub(crate) const GRID_CELL_SIZE: f32 = 150.0;
+pub(crate) const WORLD_RADIUS: f32 = 15000.0;
+pub(crate) const NUM_ROWS: i64 = (WORLD_RADIUS * 2.0 / GRID_CELL_SIZE) as i64;
+pub(crate) const NUM_COLS: i64 = (WORLD_RADIUS * 2.0 / GRID_CELL_SIZE) as i64;
+// How many cells away from a blocked cell we look for a free one to snap to
+const MAX_SNAP_DISTANCE: i64 = 10;
+
+// Neighbor offsets as (row, col), the first four are the straight ones and the last four the diagonals
+pub(crate) const NEIGHBOR_OFFSETS: [(i64, i64); 8] = [
+    (-1, 0),
+    (1, 0),
+    (0, 1),
+    (0, -1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+
+/*
+ * Collision grid stored as a flat array in row major order. Cells are addressed as (row, col)
+ * and cells outside of the grid are considered blocked.
+ */
+#[derive(Clone)]
+pub(crate) struct Grid {
+    blocked_cells: Vec<bool>,
+}
+
+impl Grid {
+    pub(crate) fn new(blocked_cells: Vec<bool>) -> Grid {
+        Grid { blocked_cells }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.blocked_cells.len()
+    }
+
+    pub(crate) fn contains(&self, cell: (i64, i64)) -> bool {
+        is_inside(cell)
+    }
+
+    pub(crate) fn is_blocked(&self, cell: (i64, i64)) -> bool {
+        !self.contains(cell) || self.blocked_cells[self.index(cell)]
+    }
+
+    pub(crate) fn set_blocked(&mut self, cell: (i64, i64), blocked: bool) {
+        let index = self.index(cell);
+        self.blocked_cells[index] = blocked;
+    }
+
+    // Indexes of the cells that are blocked in one grid and free in the other
+    pub(crate) fn changed_cells(&self, other: &Grid) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&index| self.blocked_cells[index] != other.blocked_cells[index])
+            .collect()
+    }
+
+    pub(crate) fn index(&self, cell: (i64, i64)) -> usize {
+        cell_index(cell)
+    }
+
+    // Blocked cells in the layout the debug renderer draws
+    pub(crate) fn to_debug_grid(&self) -> arena_geometry::DebugGrid {
+        arena_geometry::DebugGrid {
+            origin: grid_to_world(&(0, 0)),
+            cell_size: GRID_CELL_SIZE,
+            columns: NUM_COLS as usize,
+            blocked: self.blocked_cells.clone(),
+        }
+    }
+
+    pub(crate) fn cell(&self, index: usize) -> (i64, i64) {
+        cell_at(index)
+    }
+
+    // The cell itself if it's free, otherwise the closest free cell within `MAX_SNAP_DISTANCE` rings around it
+    pub(crate) fn nearest_free_cell(&self, cell: (i64, i64)) -> Option<(i64, i64)> {
+        if !self.is_blocked(cell) {
+            return Some(cell);
+        }
+
+        (1..=MAX_SNAP_DISTANCE).find_map(|ring| {
+            (-ring..=ring)
+                .flat_map(|row_offset| {
+                    (-ring..=ring).map(move |col_offset| (row_offset, col_offset))
+                })
+                .filter(|(row_offset, col_offset)| {
+                    row_offset.abs() == ring || col_offset.abs() == ring
+                })
+                .map(|(row_offset, col_offset)| (cell.0 + row_offset, cell.1 + col_offset))
+                .filter(|candidate| !self.is_blocked(*candidate))
+                .min_by_key(|candidate| {
+                    (candidate.0 - cell.0).pow(2) + (candidate.1 - cell.1).pow(2)
+                })
+        })
+    }
+
+    // Free cells around the given one that can be reached without cutting a blocked corner.
+    // Every search, the connected regions and the flow fields move with this same rule
+    pub(crate) fn neighbors(&self, cell: (i64, i64)) -> Vec<(i64, i64)> {
+        NEIGHBOR_OFFSETS
+            .iter()
+            .filter(|direction| self.can_move(cell, **direction))
+            .map(|(row_offset, col_offset)| (cell.0 + row_offset, cell.1 + col_offset))
+            .collect()
+    }
+
+    // Diagonal moves need both straight cells next to them to be free
+    pub(crate) fn can_move(&self, from: (i64, i64), direction: (i64, i64)) -> bool {
+        let (row_step, col_step) = direction;
+        let is_diagonal = row_step != 0 && col_step != 0;
+
+        !self.is_blocked((from.0 + row_step, from.1 + col_step))
+            && (!is_diagonal
+                || (!self.is_blocked((from.0 + row_step, from.1))
+                    && !self.is_blocked((from.0, from.1 + col_step))))
+    }
+}
+
+// Cell addressing shared by every per cell layer (blocked cells, costs, influence...)
+pub(crate) fn is_inside(cell: (i64, i64)) -> bool {
+    cell.0 >= 0 && cell.0 < NUM_ROWS && cell.1 >= 0 && cell.1 < NUM_COLS
+}
+
+pub(crate) fn cell_index(cell: (i64, i64)) -> usize {
+    (cell.0 * NUM_COLS + cell.1) as usize
+}
+
+pub(crate) fn cell_at(index: usize) -> (i64, i64) {
+    (index as i64 / NUM_COLS, index as i64 % NUM_COLS)
+}
+
+pub(crate) fn world_to_grid(pos: &Position) -> (i64, i64) {
+    (
+        ((pos.y + WORLD_RADIUS) / GRID_CELL_SIZE) as i64,
+        ((pos.x + WORLD_RADIUS) / GRID_CELL_SIZE) as i64,
+    )
+}
+
+pub(crate) fn grid_to_world(grid_pos: &(i64, i64)) -> Position {
+    Position {
+        x: (grid_pos.1 - NUM_COLS / 2) as f32 * GRID_CELL_SIZE,
+        y: (grid_pos.0 - NUM_ROWS / 2) as f32 * GRID_CELL_SIZE,
+    }
+}
+
+// The center of the cell in world coordinates
+pub(crate) fn grid_to_world_center(grid_pos: &(i64, i64)) -> Position {
+    Position::add(
+        &grid_to_world(grid_pos),
+        &Position {
+            x: GRID_CELL_SIZE / 2.0,
+            y: GRID_CELL_SIZE / 2.0,
+        },
+    )
+}
+
+// Cost of the shortest path between two cells moving in 8 directions on an open grid,
+// straight moves cost 1 and diagonal ones sqrt(2)
+pub(crate) fn octile_distance(from: (i64, i64), to: (i64, i64)) -> f32 {
+    let rows = from.0.abs_diff(to.0) as f32;
+    let cols = from.1.abs_diff(to.1) as f32;
+
+    rows.max(cols) + (SQRT_2 - 1.0) * rows.min(cols)
+}
+
+// Walks every cell crossed by the segment (Amanatides-Woo traversal), unlike cell to cell line of
+// sight the segment can start and end anywhere inside the cells.
+// Segments going exactly through a corner need both cells sharing it free unless `touch_corners` is set
+pub(crate) fn is_segment_free(
+    from: &Position,
+    to: &Position,
+    grid: &Grid,
+    touch_corners: bool,
+) -> bool {
+    let to_grid_units = |position: &Position| {
+        (
+            (position.y + WORLD_RADIUS) / GRID_CELL_SIZE,
+            (position.x + WORLD_RADIUS) / GRID_CELL_SIZE,
+        )
+    };
+    let (start_row, start_col) = to_grid_units(from);
+    let (end_row, end_col) = to_grid_units(to);
+
+    // Step towards the end along an axis and how far along the segment (0 to 1) the first and
+    // every following border of that axis is crossed
+    let axis = |start: f32, end: f32| {
+        let delta = end - start;
+        if delta > 0.0 {
+            (1, (start.floor() + 1.0 - start) / delta, 1.0 / delta)
+        } else if delta < 0.0 {
+            (-1, (start - start.floor()) / -delta, 1.0 / -delta)
+        } else {
+            (0, f32::INFINITY, f32::INFINITY)
+        }
+    };
+    let (row_step, mut next_row_border, row_border_distance) = axis(start_row, end_row);
+    let (col_step, mut next_col_border, col_border_distance) = axis(start_col, end_col);
+
+    let mut cell = (start_row.floor() as i64, start_col.floor() as i64);
+    if grid.is_blocked(cell) {
+        return false;
+    }
+
+    while next_row_border.min(next_col_border) <= 1.0 {
+        if next_row_border < next_col_border {
+            cell.0 += row_step;
+            next_row_border += row_border_distance;
+        } else if next_col_border < next_row_border {
+            cell.1 += col_step;
+            next_col_border += col_border_distance;
+        } else {
+            let row_side_blocked = grid.is_blocked((cell.0 + row_step, cell.1));
+            let col_side_blocked = grid.is_blocked((cell.0, cell.1 + col_step));
+            if (row_side_blocked && col_side_blocked)
+                || (!touch_corners && (row_side_blocked || col_side_blocked))
+            {
+                return false;
+            }
+            cell = (cell.0 + row_step, cell.1 + col_step);
+            next_row_border += row_border_distance;
+            next_col_border += col_border_distance;
+        }
+
+        if grid.is_blocked(cell) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/*
+ * What `build_collision_grid` hands to Elixir. It's built once per map and shared by every bot,
+ * so besides the grid itself it keeps the data precomputed to speed up the queries.
+ *
+ * It's never modified in place since the same grid is shared by every match on the map, adding
+ * or removing obstacles returns an updated copy instead.
+ */
+#[derive(Clone)]
+pub struct CollisionGrid {
+    pub(crate) grid: Grid,
+    pub(crate) cluster_graph: ClusterGraph,
+    pub(crate) connected_regions: ConnectedRegions,
+    pub(crate) distance_field: DistanceField,
+    pub(crate) cost_layers: CostLayers,
+    // Obstacles of the map and the ones added afterwards, needed to recompute the cells
+    // of removed obstacles since other obstacles may still block them
+    map_obstacles: Vec<Entity>,
+    added_obstacles: Vec<Entity>,
+}
+
+impl CollisionGrid {
+    pub(crate) fn build(map_obstacles: Vec<Entity>) -> CollisionGrid {
+        let grid = Grid::new(
+            (0..NUM_ROWS * NUM_COLS)
+                .map(|index| cell_collides(&(index / NUM_COLS, index % NUM_COLS), &map_obstacles))
+                .collect(),
+        );
+
+        CollisionGrid {
+            cluster_graph: ClusterGraph::build(&grid),
+            connected_regions: ConnectedRegions::build(&grid),
+            distance_field: DistanceField::build(&grid),
+            grid,
+            cost_layers: CostLayers::default(),
+            map_obstacles,
+            added_obstacles: Vec::new(),
+        }
+    }
+
+    // Copy of the grid with the cells overlapped by the obstacle blocked
+    pub(crate) fn with_obstacle(&self, obstacle: Entity) -> Result<CollisionGrid, String> {
+        check_can_block_cells(&obstacle)?;
+
+        let changed_cells: Vec<(i64, i64)> = cells_around(&obstacle)
+            .into_iter()
+            .filter(|cell| !self.grid.is_blocked(*cell) && cell_overlaps(cell, &obstacle))
+            .collect();
+
+        let mut grid = self.grid.clone();
+        for cell in &changed_cells {
+            grid.set_blocked(*cell, true);
+        }
+
+        let mut added_obstacles = self.added_obstacles.clone();
+        added_obstacles.push(obstacle);
+
+        Ok(self.updated(grid, &changed_cells, added_obstacles))
+    }
+
+    // Copy of the grid without a previously added obstacle, matched by id
+    pub(crate) fn without_obstacle(&self, obstacle: &Entity) -> Result<CollisionGrid, String> {
+        check_can_block_cells(obstacle)?;
+
+        let Some(removed_obstacle) = self
+            .added_obstacles
+            .iter()
+            .find(|added| added.id == obstacle.id)
+        else {
+            return Ok(self.clone());
+        };
+
+        let added_obstacles: Vec<Entity> = self
+            .added_obstacles
+            .iter()
+            .filter(|added| added.id != obstacle.id)
+            .cloned()
+            .collect();
+
+        // Other obstacles may still block the cells, checked the same way they were blocked
+        let changed_cells: Vec<(i64, i64)> = cells_around(removed_obstacle)
+            .into_iter()
+            .filter(|cell| {
+                self.grid.is_blocked(*cell)
+                    && !cell_collides(cell, &self.map_obstacles)
+                    && !added_obstacles
+                        .iter()
+                        .any(|added| cell_overlaps(cell, added))
+            })
+            .collect();
+
+        let mut grid = self.grid.clone();
+        for cell in &changed_cells {
+            grid.set_blocked(*cell, false);
+        }
+
+        Ok(self.updated(grid, &changed_cells, added_obstacles))
+    }
+
+    // Copy of the grid with its cost layers built from the given entities, replacing the previous ones
+    pub(crate) fn with_cost_layers(&self, entities: &[Entity]) -> CollisionGrid {
+        CollisionGrid {
+            cost_layers: CostLayers::build(entities),
+            ..self.clone()
+        }
+    }
+
+    // Copy with the new grid where only the precomputed data around the changed cells is updated
+    fn updated(
+        &self,
+        grid: Grid,
+        changed_cells: &[(i64, i64)],
+        added_obstacles: Vec<Entity>,
+    ) -> CollisionGrid {
+        CollisionGrid {
+            cluster_graph: self.cluster_graph.update(&grid, changed_cells),
+            connected_regions: self.connected_regions.update(&grid, changed_cells),
+            distance_field: self.distance_field.update(&grid, changed_cells),
+            grid,
+            cost_layers: self.cost_layers.clone(),
+            map_obstacles: self.map_obstacles.clone(),
+            added_obstacles,
+        }
+    }
+}
+
+// Lines and points have no area to block, and the geometry can't check them against the cell sides
+fn check_can_block_cells(obstacle: &Entity) -> Result<(), String> {
+    match obstacle.shape {
+        Shape::Line | Shape::Point => Err(format!(
+            "Entity {} can't block cells, only entities with an area can",
+            obstacle.id
+        )),
+        _ => Ok(()),
+    }
+}
+
+// A cell is blocked if any of its sides collides with an obstacle
+fn cell_collides(cell: &(i64, i64), obstacles: &Vec<Entity>) -> bool {
+    let bottom_left = grid_to_world(cell);
+    let bottom_right = Position::add(
+        &bottom_left,
+        &Position {
+            x: GRID_CELL_SIZE,
+            y: 0.0,
+        },
+    );
+    let top_left = Position::add(
+        &bottom_left,
+        &Position {
+            x: 0.0,
+            y: GRID_CELL_SIZE,
+        },
+    );
+    let top_right = Position::add(
+        &top_left,
+        &Position {
+            x: GRID_CELL_SIZE,
+            y: 0.0,
+        },
+    );
+
+    [
+        [bottom_left, bottom_right],
+        [bottom_left, top_left],
+        [top_left, top_right],
+        [top_right, bottom_right],
+    ]
+    .into_iter()
+    .any(|side| {
+        !Entity::new_line(0, side.to_vec())
+            .collides_with(obstacles)
+            .is_empty()
+    })
+}
+
+// Unlike `cell_collides` this also covers cells completely inside the entity
+pub(crate) fn cell_overlaps(cell: &(i64, i64), entity: &Entity) -> bool {
+    let entities = vec![entity.clone()];
+
+    cell_collides(cell, &entities)
+        || !Entity::new_point(0, grid_to_world_center(cell))
+            .collides_with(&entities)
+            .is_empty()
+}
+
+// Cells of the grid overlapping the bounding box of the entity
+pub(crate) fn cells_around(entity: &Entity) -> Vec<(i64, i64)> {
+    let (min, max) = entity.bounding_box();
+
+    let (min_row, min_col) = world_to_grid(&min);
+    let (max_row, max_col) = world_to_grid(&max);
+
+    // Cells sharing a side with the box may collide too
+    let rows = (min_row - 1).max(0)..=(max_row + 1).min(NUM_ROWS - 1);
+    let cols = (min_col - 1).max(0)..=(max_col + 1).min(NUM_COLS - 1);
+
+    rows.flat_map(|row| cols.clone().map(move |col| (row, col)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Square much bigger than a cell, so most of its cells don't touch its sides
+    fn crate_around(center: (i64, i64)) -> Entity {
+        let center = grid_to_world_center(&center);
+        let half_side = GRID_CELL_SIZE * 5.0;
+        let corner = |x: f32, y: f32| Position {
+            x: center.x + x,
+            y: center.y + y,
+        };
+
+        Entity::new_polygon(
+            1,
+            vec![
+                corner(-half_side, -half_side),
+                corner(-half_side, half_side),
+                corner(half_side, half_side),
+                corner(half_side, -half_side),
+            ],
+        )
+    }
+
+    #[test]
+    fn obstacles_block_the_cells_inside_them() {
+        let collision_grid = CollisionGrid::build(Vec::new());
+        let blocked_grid = collision_grid
+            .with_obstacle(crate_around((100, 100)))
+            .unwrap();
+
+        assert!(blocked_grid.grid.is_blocked((100, 100)));
+        assert!(blocked_grid
+            .connected_regions
+            .region(cell_index((100, 100)))
+            .is_none());
+
+        let unblocked_grid = blocked_grid
+            .without_obstacle(&crate_around((100, 100)))
+            .unwrap();
+        assert!(unblocked_grid
+            .grid
+            .changed_cells(&collision_grid.grid)
+            .is_empty());
+    }
+
+    #[test]
+    fn lines_and_points_can_not_block_cells() {
+        let collision_grid = CollisionGrid::build(Vec::new());
+        let line = Entity::new_line(
+            1,
+            vec![Position { x: 0.0, y: 0.0 }, Position { x: 500.0, y: 0.0 }],
+        );
+        let point = Entity::new_point(2, Position { x: 0.0, y: 0.0 });
+
+        assert!(collision_grid.with_obstacle(line.clone()).is_err());
+        assert!(collision_grid.with_obstacle(point).is_err());
+        assert!(collision_grid.without_obstacle(&line).is_err());
+    }
+}
@@ -0,0 +1,635 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::f32::consts::SQRT_2;
+
+use crate::grid::{octile_distance, Grid, NEIGHBOR_OFFSETS, NUM_COLS, NUM_ROWS};
+use crate::AStarPathResult;
+
+/*
+Hierarchical pathfinding (HPA*)
+
+The grid is split into square clusters. Wherever two neighbor clusters share a run of free
+cells along their border (an entrance) we place transition nodes on both sides, and inside
+every cluster we precompute the cost between each pair of its transition nodes. That gives us
+an abstract graph with a few thousand nodes that is built once per map, together with the grid.
+
+A query then only needs to:
+- Connect the start and the goal to the transition nodes of their clusters
+- Search the abstract graph, which is tiny compared with the grid
+- Refine each abstract edge into cells, and every refinement is a search bounded to one cluster
+
+Refining can be done lazily, a bot only needs the cells of the chunk it's about to walk and
+the rest of the abstract path as coarse waypoints.
+
+Moves inside clusters can't cut blocked corners and cost 1 straight and sqrt(2) diagonally,
+paths aren't guaranteed to be optimal but are usually within a few percent of it.
+
+- [HPA* paper](https://webdocs.cs.ualberta.ca/~mmueller/ps/hpastar.pdf)
+*/
+
+const CLUSTER_SIZE: i64 = 10;
+const CLUSTER_ROWS: i64 = NUM_ROWS / CLUSTER_SIZE;
+const CLUSTER_COLS: i64 = NUM_COLS / CLUSTER_SIZE;
+// Entrances at least this wide get a transition at each end instead of a single one in the middle
+const MIN_DOUBLE_TRANSITION_ENTRANCE: usize = 6;
+
+// A cell next to a cluster border and the one facing it on the other side
+type BorderCells = ((i64, i64), (i64, i64));
+
+#[derive(Clone)]
+struct AbstractNode {
+    cell: (i64, i64),
+    edges: Vec<AbstractEdge>,
+}
+
+#[derive(Clone)]
+struct AbstractEdge {
+    to: usize,
+    cost: f32,
+}
+
+#[derive(Clone)]
+pub(crate) struct ClusterGraph {
+    nodes: Vec<AbstractNode>,
+    // Transition nodes of each cluster, clusters are indexed in row major order
+    cluster_nodes: Vec<Vec<usize>>,
+}
+
+// A path where only the first chunk was refined into cells
+pub(crate) struct PathChunk {
+    pub(crate) refined_path: Vec<(i64, i64)>,
+    pub(crate) remaining_waypoints: Vec<(i64, i64)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct NodeEntry {
+    node: usize,
+    estimate_reach_cost: f32,
+}
+
+impl Eq for NodeEntry {}
+
+impl Ord for NodeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate_reach_cost
+            .total_cmp(&self.estimate_reach_cost)
+    }
+}
+
+impl PartialOrd for NodeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ClusterGraph {
+    pub(crate) fn build(grid: &Grid) -> ClusterGraph {
+        let mut graph = ClusterGraph {
+            nodes: Vec::new(),
+            cluster_nodes: vec![Vec::new(); (CLUSTER_ROWS * CLUSTER_COLS) as usize],
+        };
+        let mut node_by_cell = HashMap::new();
+
+        graph.add_borders(grid, &mut node_by_cell, |_, _| true);
+
+        for cluster in 0..graph.cluster_nodes.len() {
+            graph.connect_cluster_nodes(cluster, grid);
+        }
+
+        graph
+    }
+
+    // Graph of the grid after the given cells changed. Only the borders of the clusters holding
+    // them are scanned again, and only the clusters whose cells or transitions changed are
+    // searched again, the rest keep their edges
+    pub(crate) fn update(&self, grid: &Grid, changed_cells: &[(i64, i64)]) -> ClusterGraph {
+        let changed_clusters: HashSet<usize> =
+            changed_cells.iter().map(|cell| cluster_of(*cell)).collect();
+        let is_changed = |cluster: usize, other_cluster: usize| {
+            changed_clusters.contains(&cluster) || changed_clusters.contains(&other_cluster)
+        };
+
+        let mut graph = ClusterGraph {
+            nodes: Vec::new(),
+            cluster_nodes: vec![Vec::new(); (CLUSTER_ROWS * CLUSTER_COLS) as usize],
+        };
+        let mut node_by_cell = HashMap::new();
+
+        // Transitions between unchanged clusters stay where they were
+        for node in &self.nodes {
+            for edge in &node.edges {
+                let other_cell = self.nodes[edge.to].cell;
+                let (cluster, other_cluster) = (cluster_of(node.cell), cluster_of(other_cell));
+                if cluster != other_cluster
+                    && node.cell < other_cell
+                    && !is_changed(cluster, other_cluster)
+                {
+                    graph.add_transition(node.cell, other_cell, &mut node_by_cell);
+                }
+            }
+        }
+
+        graph.add_borders(grid, &mut node_by_cell, is_changed);
+
+        for cluster in 0..graph.cluster_nodes.len() {
+            if changed_clusters.contains(&cluster)
+                || self.transition_cells(cluster) != graph.transition_cells(cluster)
+            {
+                graph.connect_cluster_nodes(cluster, grid);
+            } else {
+                graph.copy_cluster_edges(cluster, self, &node_by_cell);
+            }
+        }
+
+        graph
+    }
+
+    // Searches the abstract graph and refines the whole path into cells
+    pub(crate) fn find_path(
+        &self,
+        start: (i64, i64),
+        goal: (i64, i64),
+        grid: &Grid,
+    ) -> AStarPathResult {
+        let Some(abstract_path) = self.find_abstract_path(start, goal, grid) else {
+            return AStarPathResult::NotFound;
+        };
+
+        let mut path = vec![start];
+        for segment in abstract_path.windows(2) {
+            path.extend(
+                refine_segment(segment[0], segment[1], grid)
+                    .into_iter()
+                    .skip(1),
+            );
+        }
+
+        AStarPathResult::Found(path)
+    }
+
+    // Searches the abstract graph but only refines the path until it leaves the start cluster
+    pub(crate) fn find_next_chunk(
+        &self,
+        start: (i64, i64),
+        goal: (i64, i64),
+        grid: &Grid,
+    ) -> Option<PathChunk> {
+        let abstract_path = self.find_abstract_path(start, goal, grid)?;
+        let start_cluster = cluster_of(start);
+
+        let mut refined_path = vec![start];
+        let mut refined_waypoints = 1;
+        for segment in abstract_path.windows(2) {
+            refined_path.extend(
+                refine_segment(segment[0], segment[1], grid)
+                    .into_iter()
+                    .skip(1),
+            );
+            refined_waypoints += 1;
+
+            if cluster_of(segment[1]) != start_cluster {
+                break;
+            }
+        }
+
+        Some(PathChunk {
+            refined_path,
+            remaining_waypoints: abstract_path[refined_waypoints..].to_vec(),
+        })
+    }
+
+    // The cells of the abstract path, starting at the start and ending at the goal
+    fn find_abstract_path(
+        &self,
+        start: (i64, i64),
+        goal: (i64, i64),
+        grid: &Grid,
+    ) -> Option<Vec<(i64, i64)>> {
+        if grid.is_blocked(goal) {
+            return None;
+        }
+
+        let start_cluster = cluster_of(start);
+        let goal_cluster = cluster_of(goal);
+        let start_search = ClusterSearch::run(start, start_cluster, grid);
+
+        // Nearby goals don't need the abstract graph at all
+        if start_cluster == goal_cluster && start_search.cost(goal).is_finite() {
+            return Some(vec![start, goal]);
+        }
+
+        let goal_search = ClusterSearch::run(goal, goal_cluster, grid);
+
+        // The goal is a virtual node placed after the real ones, nodes reached straight from
+        // the start have no parent
+        let goal_node = self.nodes.len();
+        let mut costs: Vec<f32> = vec![f32::INFINITY; goal_node + 1];
+        let mut parents: Vec<Option<usize>> = vec![None; goal_node + 1];
+        let mut expanded: Vec<bool> = vec![false; goal_node + 1];
+        let mut priority_queue = BinaryHeap::new();
+
+        for &node in &self.cluster_nodes[start_cluster] {
+            let cell = self.nodes[node].cell;
+            costs[node] = start_search.cost(cell);
+            if costs[node].is_finite() {
+                priority_queue.push(NodeEntry {
+                    node,
+                    estimate_reach_cost: costs[node] + octile_distance(cell, goal),
+                });
+            }
+        }
+
+        while let Some(NodeEntry { node, .. }) = priority_queue.pop() {
+            if expanded[node] {
+                continue;
+            }
+            expanded[node] = true;
+
+            if node == goal_node {
+                return Some(self.build_abstract_path(start, goal, &parents));
+            }
+
+            let cell = self.nodes[node].cell;
+            let goal_edge =
+                (cluster_of(cell) == goal_cluster).then(|| (goal_node, goal_search.cost(cell)));
+            let candidates = self.nodes[node]
+                .edges
+                .iter()
+                .map(|edge| (edge.to, edge.cost))
+                .chain(goal_edge);
+
+            for (neighbor, edge_cost) in candidates {
+                let neighbor_cost = costs[node] + edge_cost;
+                if expanded[neighbor] || neighbor_cost >= costs[neighbor] {
+                    continue;
+                }
+
+                costs[neighbor] = neighbor_cost;
+                parents[neighbor] = Some(node);
+
+                let neighbor_cell = if neighbor == goal_node {
+                    goal
+                } else {
+                    self.nodes[neighbor].cell
+                };
+                priority_queue.push(NodeEntry {
+                    node: neighbor,
+                    estimate_reach_cost: neighbor_cost + octile_distance(neighbor_cell, goal),
+                });
+            }
+        }
+
+        None
+    }
+
+    fn build_abstract_path(
+        &self,
+        start: (i64, i64),
+        goal: (i64, i64),
+        parents: &[Option<usize>],
+    ) -> Vec<(i64, i64)> {
+        let mut path = vec![goal];
+        let mut current = parents[self.nodes.len()];
+
+        while let Some(node) = current {
+            path.push(self.nodes[node].cell);
+            current = parents[node];
+        }
+
+        path.push(start);
+        path.reverse();
+
+        path
+    }
+
+    // Adds the entrances of the borders between neighbor clusters that pass the filter,
+    // which gets the indexes of both clusters
+    fn add_borders(
+        &mut self,
+        grid: &Grid,
+        node_by_cell: &mut HashMap<(i64, i64), usize>,
+        filter: impl Fn(usize, usize) -> bool,
+    ) {
+        for cluster_row in 0..CLUSTER_ROWS {
+            for cluster_col in 0..CLUSTER_COLS {
+                let rows = cluster_row * CLUSTER_SIZE..(cluster_row + 1) * CLUSTER_SIZE;
+                let cols = cluster_col * CLUSTER_SIZE..(cluster_col + 1) * CLUSTER_SIZE;
+                let cluster = cluster_of((rows.start, cols.start));
+
+                // Border with the cluster on the right
+                if cluster_col + 1 < CLUSTER_COLS && filter(cluster, cluster + 1) {
+                    let col = cols.end - 1;
+                    let border = rows.clone().map(|row| ((row, col), (row, col + 1)));
+                    self.add_entrances(border, grid, node_by_cell);
+                }
+
+                // Border with the cluster below
+                if cluster_row + 1 < CLUSTER_ROWS
+                    && filter(cluster, cluster + CLUSTER_COLS as usize)
+                {
+                    let row = rows.end - 1;
+                    let border = cols.clone().map(|col| ((row, col), (row + 1, col)));
+                    self.add_entrances(border, grid, node_by_cell);
+                }
+            }
+        }
+    }
+
+    // Adds transitions for every run of border cells that are free on both sides
+    fn add_entrances(
+        &mut self,
+        border: impl Iterator<Item = BorderCells>,
+        grid: &Grid,
+        node_by_cell: &mut HashMap<(i64, i64), usize>,
+    ) {
+        let mut entrance = Vec::new();
+
+        for (cell, other_cell) in border {
+            if !grid.is_blocked(cell) && !grid.is_blocked(other_cell) {
+                entrance.push((cell, other_cell));
+                continue;
+            }
+
+            self.add_transitions(&entrance, node_by_cell);
+            entrance.clear();
+        }
+
+        self.add_transitions(&entrance, node_by_cell);
+    }
+
+    fn add_transitions(
+        &mut self,
+        entrance: &[BorderCells],
+        node_by_cell: &mut HashMap<(i64, i64), usize>,
+    ) {
+        let transitions = match entrance.len() {
+            0 => vec![],
+            length if length < MIN_DOUBLE_TRANSITION_ENTRANCE => vec![entrance[length / 2]],
+            length => vec![entrance[0], entrance[length - 1]],
+        };
+
+        for (cell, other_cell) in transitions {
+            self.add_transition(cell, other_cell, node_by_cell);
+        }
+    }
+
+    fn add_transition(
+        &mut self,
+        cell: (i64, i64),
+        other_cell: (i64, i64),
+        node_by_cell: &mut HashMap<(i64, i64), usize>,
+    ) {
+        let node = self.node_at(cell, node_by_cell);
+        let other_node = self.node_at(other_cell, node_by_cell);
+
+        self.nodes[node].edges.push(AbstractEdge {
+            to: other_node,
+            cost: 1.0,
+        });
+        self.nodes[other_node].edges.push(AbstractEdge {
+            to: node,
+            cost: 1.0,
+        });
+    }
+
+    fn node_at(
+        &mut self,
+        cell: (i64, i64),
+        node_by_cell: &mut HashMap<(i64, i64), usize>,
+    ) -> usize {
+        *node_by_cell.entry(cell).or_insert_with(|| {
+            self.nodes.push(AbstractNode {
+                cell,
+                edges: Vec::new(),
+            });
+            self.cluster_nodes[cluster_of(cell)].push(self.nodes.len() - 1);
+            self.nodes.len() - 1
+        })
+    }
+
+    // Adds an edge between every pair of transition nodes of the cluster that can reach each other
+    fn connect_cluster_nodes(&mut self, cluster: usize, grid: &Grid) {
+        for &node in &self.cluster_nodes[cluster] {
+            let search = ClusterSearch::run(self.nodes[node].cell, cluster, grid);
+
+            for &other_node in &self.cluster_nodes[cluster] {
+                let cost = search.cost(self.nodes[other_node].cell);
+                if other_node != node && cost.is_finite() {
+                    self.nodes[node].edges.push(AbstractEdge {
+                        to: other_node,
+                        cost,
+                    });
+                }
+            }
+        }
+    }
+
+    // Copies the edges inside the cluster from a graph with the same transition nodes in it
+    fn copy_cluster_edges(
+        &mut self,
+        cluster: usize,
+        graph: &ClusterGraph,
+        node_by_cell: &HashMap<(i64, i64), usize>,
+    ) {
+        for &old_node in &graph.cluster_nodes[cluster] {
+            let node = node_by_cell[&graph.nodes[old_node].cell];
+
+            for edge in &graph.nodes[old_node].edges {
+                let other_cell = graph.nodes[edge.to].cell;
+                if cluster_of(other_cell) == cluster {
+                    self.nodes[node].edges.push(AbstractEdge {
+                        to: node_by_cell[&other_cell],
+                        cost: edge.cost,
+                    });
+                }
+            }
+        }
+    }
+
+    fn transition_cells(&self, cluster: usize) -> Vec<(i64, i64)> {
+        let mut cells: Vec<(i64, i64)> = self.cluster_nodes[cluster]
+            .iter()
+            .map(|&node| self.nodes[node].cell)
+            .collect();
+        cells.sort();
+        cells
+    }
+}
+
+// Consecutive cells of the abstract path are either in the same cluster or right next to each other
+fn refine_segment(from: (i64, i64), to: (i64, i64), grid: &Grid) -> Vec<(i64, i64)> {
+    let cluster = cluster_of(from);
+
+    if cluster == cluster_of(to) {
+        ClusterSearch::run(from, cluster, grid)
+            .path_to(to)
+            .expect("Abstract edges inside a cluster should have a path")
+    } else {
+        vec![from, to]
+    }
+}
+
+fn cluster_of(cell: (i64, i64)) -> usize {
+    ((cell.0 / CLUSTER_SIZE) * CLUSTER_COLS + cell.1 / CLUSTER_SIZE) as usize
+}
+
+/*
+ * Dijkstra from one cell to every other cell of its cluster without leaving it.
+ * Clusters are small so this is cheaper than running A* towards each target.
+ */
+struct ClusterSearch {
+    // Top left cell of the cluster
+    corner: (i64, i64),
+    costs: Vec<f32>,
+    parents: Vec<usize>,
+}
+
+impl ClusterSearch {
+    fn run(origin: (i64, i64), cluster: usize, grid: &Grid) -> ClusterSearch {
+        let mut search = ClusterSearch {
+            corner: (
+                (cluster as i64 / CLUSTER_COLS) * CLUSTER_SIZE,
+                (cluster as i64 % CLUSTER_COLS) * CLUSTER_SIZE,
+            ),
+            costs: vec![f32::INFINITY; (CLUSTER_SIZE * CLUSTER_SIZE) as usize],
+            parents: vec![usize::MAX; (CLUSTER_SIZE * CLUSTER_SIZE) as usize],
+        };
+        let mut expanded = vec![false; search.costs.len()];
+
+        let origin_index = search
+            .index(origin)
+            .expect("The origin should be inside the cluster");
+        search.costs[origin_index] = 0.0;
+        search.parents[origin_index] = origin_index;
+
+        let mut priority_queue = BinaryHeap::new();
+        priority_queue.push(NodeEntry {
+            node: origin_index,
+            estimate_reach_cost: 0.0,
+        });
+
+        while let Some(NodeEntry { node, .. }) = priority_queue.pop() {
+            if expanded[node] {
+                continue;
+            }
+            expanded[node] = true;
+
+            let cell = search.cell(node);
+            for direction in NEIGHBOR_OFFSETS {
+                let neighbor = (cell.0 + direction.0, cell.1 + direction.1);
+                let Some(neighbor_index) = search.index(neighbor) else {
+                    continue;
+                };
+                if !grid.can_move(cell, direction) {
+                    continue;
+                }
+
+                let move_cost = if direction.0 != 0 && direction.1 != 0 {
+                    SQRT_2
+                } else {
+                    1.0
+                };
+                let neighbor_cost = search.costs[node] + move_cost;
+                if neighbor_cost < search.costs[neighbor_index] {
+                    search.costs[neighbor_index] = neighbor_cost;
+                    search.parents[neighbor_index] = node;
+                    priority_queue.push(NodeEntry {
+                        node: neighbor_index,
+                        estimate_reach_cost: neighbor_cost,
+                    });
+                }
+            }
+        }
+
+        search
+    }
+
+    fn cost(&self, cell: (i64, i64)) -> f32 {
+        self.index(cell)
+            .map_or(f32::INFINITY, |index| self.costs[index])
+    }
+
+    fn path_to(&self, cell: (i64, i64)) -> Option<Vec<(i64, i64)>> {
+        let mut current = self.index(cell)?;
+        if self.costs[current].is_infinite() {
+            return None;
+        }
+
+        let mut path = vec![cell];
+        while self.parents[current] != current {
+            current = self.parents[current];
+            path.push(self.cell(current));
+        }
+
+        path.reverse();
+
+        Some(path)
+    }
+
+    // Index of the cell among the cluster cells, None for cells of other clusters
+    fn index(&self, cell: (i64, i64)) -> Option<usize> {
+        let row = cell.0 - self.corner.0;
+        let col = cell.1 - self.corner.1;
+
+        ((0..CLUSTER_SIZE).contains(&row) && (0..CLUSTER_SIZE).contains(&col))
+            .then_some((row * CLUSTER_SIZE + col) as usize)
+    }
+
+    fn cell(&self, index: usize) -> (i64, i64) {
+        (
+            self.corner.0 + index as i64 / CLUSTER_SIZE,
+            self.corner.1 + index as i64 % CLUSTER_SIZE,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cells joined by an edge and its cost
+    type CellEdge = ((i64, i64), (i64, i64), f32);
+
+    // Every edge as the cells it joins, in the same order whatever the node indexes are
+    fn edges(graph: &ClusterGraph) -> Vec<CellEdge> {
+        let mut edges: Vec<_> = graph
+            .nodes
+            .iter()
+            .flat_map(|node| {
+                node.edges
+                    .iter()
+                    .map(|edge| (node.cell, graph.nodes[edge.to].cell, edge.cost))
+            })
+            .collect();
+        edges.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)).then(a.2.total_cmp(&b.2)));
+        edges
+    }
+
+    #[test]
+    fn updates_match_building_again() {
+        let mut grid = Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize]);
+        let mut graph = ClusterGraph::build(&grid);
+
+        // Wall crossing a cluster border and the middle of the clusters next to it
+        let wall: Vec<(i64, i64)> = (25..38).map(|row| (row, 45)).collect();
+        for cell in &wall {
+            grid.set_blocked(*cell, true);
+        }
+        graph = graph.update(&grid, &wall);
+        assert_eq!(edges(&graph), edges(&ClusterGraph::build(&grid)));
+
+        // Blocking a border cell moves the transitions of the entrance
+        let border_cell = [(29, 50)];
+        grid.set_blocked(border_cell[0], true);
+        graph = graph.update(&grid, &border_cell);
+        assert_eq!(edges(&graph), edges(&ClusterGraph::build(&grid)));
+
+        let freed_cells = &wall[3..6];
+        for cell in freed_cells {
+            grid.set_blocked(*cell, false);
+        }
+        graph = graph.update(&grid, freed_cells);
+        assert_eq!(edges(&graph), edges(&ClusterGraph::build(&grid)));
+    }
+}
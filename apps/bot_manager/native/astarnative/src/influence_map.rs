@@ -0,0 +1,191 @@
+use std::sync::Mutex;
+
+use arena_geometry::Position;
+use rustler::{NifMap, NifUnitEnum};
+
+use crate::grid::{self, CollisionGrid, GRID_CELL_SIZE, NUM_COLS, NUM_ROWS};
+
+/*
+Influence map
+
+A value per grid cell that summarizes the situation around it: sources (players, projectiles...)
+splat positive or negative influence on the cells around them, fading with the distance according
+to their falloff. Callers choose the sign, e.g. allies and healing spots positive and enemies or
+incoming projectiles negative, so the cell with the highest influence is the best one to be in.
+
+Influence fades over time with the half life of the map, so recent events weigh more than old ones.
+The expected usage is decaying the map by the elapsed time on every tick and then adding the
+sources of that tick.
+*/
+
+// Resource holding an influence map between ticks, every call gets exclusive access to it
+pub struct InfluenceMap {
+    pub(crate) state: Mutex<InfluenceGrid>,
+}
+
+pub(crate) struct InfluenceGrid {
+    values: Vec<f32>,
+    // Milliseconds for the influence to fade to half of its value
+    half_life: f32,
+}
+
+#[derive(NifUnitEnum, Clone, Copy)]
+pub enum Falloff {
+    // Same influence over the whole radius
+    Constant,
+    // Fades linearly until the edge of the radius
+    Linear,
+    // Fades quickly close to the source and slowly towards the edge of the radius
+    Quadratic,
+}
+
+// Influence splatted around a position, `strength` is the influence right on the position
+#[derive(NifMap, Clone, Copy)]
+pub struct InfluenceSource {
+    pub(crate) position: Position,
+    pub(crate) strength: f32,
+    pub(crate) radius: f32,
+    pub(crate) falloff: Falloff,
+}
+
+impl InfluenceGrid {
+    pub(crate) fn new(half_life: f32) -> InfluenceGrid {
+        InfluenceGrid {
+            values: vec![0.0; (NUM_ROWS * NUM_COLS) as usize],
+            half_life,
+        }
+    }
+
+    pub(crate) fn decay(&mut self, elapsed_time: f32) {
+        if self.half_life <= 0.0 {
+            self.values.fill(0.0);
+            return;
+        }
+
+        let factor = 0.5_f32.powf(elapsed_time / self.half_life);
+        for value in self.values.iter_mut() {
+            *value *= factor;
+        }
+    }
+
+    // Sources without a radius have nowhere to splat their influence, so they're skipped
+    pub(crate) fn add_source(&mut self, source: &InfluenceSource) {
+        if source.radius <= 0.0 {
+            return;
+        }
+
+        for cell in cells_within(&source.position, source.radius) {
+            let distance = grid::grid_to_world_center(&cell).distance_to_position(&source.position);
+            if distance > source.radius {
+                continue;
+            }
+
+            let remaining = 1.0 - distance / source.radius;
+            let influence = match source.falloff {
+                Falloff::Constant => source.strength,
+                Falloff::Linear => source.strength * remaining,
+                Falloff::Quadratic => source.strength * remaining * remaining,
+            };
+
+            self.values[grid::cell_index(cell)] += influence;
+        }
+    }
+
+    // Adds the strength to every cell outside of the zone circle
+    pub(crate) fn add_zone(&mut self, center: &Position, radius: f32, strength: f32) {
+        for (index, value) in self.values.iter_mut().enumerate() {
+            let cell = grid::cell_at(index);
+            if grid::grid_to_world_center(&cell).distance_to_position(center) > radius {
+                *value += strength;
+            }
+        }
+    }
+
+    pub(crate) fn influence_at(&self, position: &Position) -> f32 {
+        let cell = grid::world_to_grid(position);
+        if !grid::is_inside(cell) {
+            return 0.0;
+        }
+
+        self.values[grid::cell_index(cell)]
+    }
+
+    // Free cell with the highest influence within the radius that can be reached from `around`,
+    // ties are broken by the distance to `around`
+    pub(crate) fn best_position(
+        &self,
+        collision_grid: &CollisionGrid,
+        around: &Position,
+        radius: f32,
+    ) -> Option<Position> {
+        let grid = &collision_grid.grid;
+        let around_cell = grid.nearest_free_cell(grid::world_to_grid(around))?;
+        let around_region = collision_grid
+            .connected_regions
+            .region(grid.index(around_cell));
+
+        let mut best: Option<(f32, f32, Position)> = None;
+
+        for cell in cells_within(around, radius) {
+            if grid.is_blocked(cell)
+                || collision_grid.connected_regions.region(grid.index(cell)) != around_region
+            {
+                continue;
+            }
+
+            let position = grid::grid_to_world_center(&cell);
+            let distance = position.distance_to_position(around);
+            if distance > radius {
+                continue;
+            }
+
+            let influence = self.values[grid.index(cell)];
+            let is_better = match best {
+                None => true,
+                Some((best_influence, best_distance, _)) => {
+                    influence > best_influence
+                        || (influence == best_influence && distance < best_distance)
+                }
+            };
+
+            if is_better {
+                best = Some((influence, distance, position));
+            }
+        }
+
+        best.map(|(_, _, position)| position)
+    }
+}
+
+// Cells of the grid inside the bounding box of the circle
+fn cells_within(center: &Position, radius: f32) -> impl Iterator<Item = (i64, i64)> {
+    let center_cell = grid::world_to_grid(center);
+    let radius_in_cells = (radius.max(0.0) / GRID_CELL_SIZE).ceil() as i64;
+
+    let rows = (center_cell.0 - radius_in_cells).max(0)
+        ..=(center_cell.0 + radius_in_cells).min(NUM_ROWS - 1);
+    let cols = (center_cell.1 - radius_in_cells).max(0)
+        ..=(center_cell.1 + radius_in_cells).min(NUM_COLS - 1);
+
+    rows.flat_map(move |row| cols.clone().map(move |col| (row, col)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_without_radius_leave_the_map_untouched() {
+        let position = grid::grid_to_world_center(&(50, 50));
+        let mut influence_grid = InfluenceGrid::new(1000.0);
+
+        influence_grid.add_source(&InfluenceSource {
+            position,
+            strength: -10.0,
+            radius: 0.0,
+            falloff: Falloff::Linear,
+        });
+
+        assert_eq!(influence_grid.influence_at(&position), 0.0);
+    }
+}
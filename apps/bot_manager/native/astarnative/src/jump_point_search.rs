@@ -0,0 +1,374 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::grid::{octile_distance, Grid, NEIGHBOR_OFFSETS};
+use crate::AStarPathResult;
+
+/*
+Jump point search
+
+On grids where every move costs the same there are lots of equivalent shortest paths and A*
+ends up expanding most of them. Jump point search prunes those symmetric paths: instead of
+adding every neighbor to the open list, from each node we keep moving ("jumping") in a straight
+line and only stop at the nodes where something interesting happens:
+- The goal
+- A node with a forced neighbor, a neighbor that can only be reached optimally through it
+  because an obstacle next to us blocks the other ways
+- When moving diagonally, a node from which a straight jump finds one of the above
+
+Only those jump points are added to the open list, so the amount of expanded nodes is a tiny
+fraction of the A* ones on open maps.
+
+Diagonal moves are only allowed when both straight cells next to them are free (no corner
+cutting), moving straight costs 1 and moving diagonally costs sqrt(2).
+
+The resulting path only has the jump points, consecutive jump points are always connected
+by a straight or diagonal line.
+
+- [Jump point search paper](https://users.cecs.anu.edu.au/~dharabor/data/papers/harabor-grastien-aaai11.pdf)
+*/
+
+#[derive(Clone, Copy, PartialEq)]
+struct NodeEntry {
+    node: usize,
+    estimate_reach_cost: f32,
+}
+
+impl Eq for NodeEntry {}
+
+impl Ord for NodeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate_reach_cost
+            .total_cmp(&self.estimate_reach_cost)
+    }
+}
+
+impl PartialOrd for NodeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub(crate) fn jump_point_search_find_path(
+    start: (i64, i64),
+    goal: (i64, i64),
+    grid: &Grid,
+    max_expansions: usize,
+) -> AStarPathResult {
+    // Bookkeeping for every cell of the grid: the cost to reach it, its parent and
+    // whether it was already expanded
+    let mut costs: Vec<f32> = vec![f32::INFINITY; grid.len()];
+    let mut parents: Vec<usize> = vec![usize::MAX; grid.len()];
+    let mut expanded: Vec<bool> = vec![false; grid.len()];
+
+    let start_index = grid.index(start);
+    costs[start_index] = 0.0;
+    parents[start_index] = start_index;
+    let mut closest_node = start_index;
+    let mut expansions = 0;
+
+    let mut priority_queue = BinaryHeap::new();
+    priority_queue.push(NodeEntry {
+        node: start_index,
+        estimate_reach_cost: octile_distance(start, goal),
+    });
+
+    while let Some(NodeEntry { node, .. }) = priority_queue.pop() {
+        if expanded[node] {
+            continue;
+        }
+        expanded[node] = true;
+
+        let cell = grid.cell(node);
+        if cell == goal {
+            return AStarPathResult::Found(build_path(start, goal, grid, &parents));
+        }
+
+        // Remember the expanded node closest to the goal in case the goal can't be reached
+        if octile_distance(cell, goal) < octile_distance(grid.cell(closest_node), goal) {
+            closest_node = node;
+        }
+
+        expansions += 1;
+        if expansions >= max_expansions {
+            break;
+        }
+
+        let parent = (parents[node] != node).then(|| grid.cell(parents[node]));
+
+        for direction in pruned_directions(cell, parent, grid) {
+            let Some(jump_point) = jump(cell, direction, goal, grid) else {
+                continue;
+            };
+
+            let jump_point_index = grid.index(jump_point);
+            if expanded[jump_point_index] {
+                continue;
+            }
+
+            let jump_point_cost = costs[node] + octile_distance(cell, jump_point);
+            if jump_point_cost < costs[jump_point_index] {
+                costs[jump_point_index] = jump_point_cost;
+                parents[jump_point_index] = node;
+                priority_queue.push(NodeEntry {
+                    node: jump_point_index,
+                    estimate_reach_cost: jump_point_cost + octile_distance(jump_point, goal),
+                });
+            }
+        }
+    }
+
+    AStarPathResult::Partial(build_path(start, grid.cell(closest_node), grid, &parents))
+}
+
+// Directions worth exploring from a node given the direction we arrived from, the rest
+// of the neighbors can be reached at the same cost without going through this node
+fn pruned_directions(cell: (i64, i64), parent: Option<(i64, i64)>, grid: &Grid) -> Vec<(i64, i64)> {
+    let is_free = |row_offset: i64, col_offset: i64| {
+        !grid.is_blocked((cell.0 + row_offset, cell.1 + col_offset))
+    };
+
+    let Some(parent) = parent else {
+        // The start node explores every direction
+        return NEIGHBOR_OFFSETS
+            .iter()
+            .copied()
+            .filter(|(row_offset, col_offset)| grid.can_move(cell, (*row_offset, *col_offset)))
+            .collect();
+    };
+
+    let row_step = (cell.0 - parent.0).signum();
+    let col_step = (cell.1 - parent.1).signum();
+    let mut directions = Vec::new();
+
+    if row_step != 0 && col_step != 0 {
+        if is_free(row_step, 0) {
+            directions.push((row_step, 0));
+        }
+        if is_free(0, col_step) {
+            directions.push((0, col_step));
+        }
+        if is_free(row_step, 0) && is_free(0, col_step) {
+            directions.push((row_step, col_step));
+        }
+    } else if row_step != 0 {
+        // A side cell is only forced when the cell behind it is blocked, otherwise the parent
+        // reaches it diagonally without going through this node
+        let is_next_free = is_free(row_step, 0);
+        for col_offset in [-1, 1] {
+            if is_free(0, col_offset) && !is_free(-row_step, col_offset) {
+                directions.push((0, col_offset));
+                if is_next_free {
+                    directions.push((row_step, col_offset));
+                }
+            }
+        }
+        if is_next_free {
+            directions.push((row_step, 0));
+        }
+    } else {
+        let is_next_free = is_free(0, col_step);
+        for row_offset in [-1, 1] {
+            if is_free(row_offset, 0) && !is_free(row_offset, -col_step) {
+                directions.push((row_offset, 0));
+                if is_next_free {
+                    directions.push((row_offset, col_step));
+                }
+            }
+        }
+        if is_next_free {
+            directions.push((0, col_step));
+        }
+    }
+
+    directions
+}
+
+// Moves from the cell in the given direction until finding a jump point,
+// returns None if an obstacle or the grid border is reached first
+fn jump(
+    from: (i64, i64),
+    direction: (i64, i64),
+    goal: (i64, i64),
+    grid: &Grid,
+) -> Option<(i64, i64)> {
+    let (row_step, col_step) = direction;
+    let mut current = from;
+
+    loop {
+        if !grid.can_move(current, direction) {
+            return None;
+        }
+        current = (current.0 + row_step, current.1 + col_step);
+
+        if current == goal {
+            return Some(current);
+        }
+
+        let is_free = |row_offset: i64, col_offset: i64| {
+            !grid.is_blocked((current.0 + row_offset, current.1 + col_offset))
+        };
+
+        if row_step != 0 && col_step != 0 {
+            if jump(current, (row_step, 0), goal, grid).is_some()
+                || jump(current, (0, col_step), goal, grid).is_some()
+            {
+                return Some(current);
+            }
+        } else if row_step != 0 {
+            // A side cell that is free now but was blocked next to the previous cell is a forced neighbor
+            if (is_free(0, -1) && !is_free(-row_step, -1))
+                || (is_free(0, 1) && !is_free(-row_step, 1))
+            {
+                return Some(current);
+            }
+        } else if (is_free(-1, 0) && !is_free(-1, -col_step))
+            || (is_free(1, 0) && !is_free(1, -col_step))
+        {
+            return Some(current);
+        }
+    }
+}
+
+fn build_path(
+    start: (i64, i64),
+    goal: (i64, i64),
+    grid: &Grid,
+    parents: &[usize],
+) -> Vec<(i64, i64)> {
+    let mut current = grid.index(goal);
+    let mut path = vec![goal];
+
+    while current != grid.index(start) {
+        current = parents[current];
+        path.push(grid.cell(current));
+    }
+
+    path.reverse();
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star::a_star_find_path;
+    use crate::cost_layers::CellCosts;
+    use crate::grid::{NUM_COLS, NUM_ROWS};
+
+    const START: (i64, i64) = (60, 60);
+    const GOAL: (i64, i64) = (140, 130);
+
+    fn open_grid() -> Grid {
+        Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize])
+    }
+
+    // Scattered walls of different lengths, placed with a fixed pseudo random sequence
+    fn grid_with_obstacles() -> Grid {
+        let mut grid = open_grid();
+        let mut seed: u64 = 7;
+        let mut next = |max: i64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % max as u64) as i64
+        };
+
+        for _ in 0..300 {
+            let (row, col, length) = (40 + next(120), 40 + next(120), 1 + next(12));
+            let vertical = next(2) == 0;
+            for step in 0..length {
+                let cell = if vertical {
+                    (row + step, col)
+                } else {
+                    (row, col + step)
+                };
+                if cell != START && cell != GOAL {
+                    grid.set_blocked(cell, true);
+                }
+            }
+        }
+
+        grid
+    }
+
+    fn path_cost(path: &[(i64, i64)]) -> f32 {
+        path.windows(2)
+            .map(|pair| octile_distance(pair[0], pair[1]))
+            .sum()
+    }
+
+    fn found_path(path_result: AStarPathResult) -> Vec<(i64, i64)> {
+        match path_result {
+            AStarPathResult::Found(path) => path,
+            _ => panic!("The goal should be reachable"),
+        }
+    }
+
+    // Same cost as A*, and the path never jumps through blocked cells or cuts their corners
+    fn assert_matches_a_star(grid: &Grid) {
+        let a_star_path = found_path(a_star_find_path(
+            START,
+            GOAL,
+            grid,
+            &CellCosts::default(),
+            usize::MAX,
+        ));
+        let path = found_path(jump_point_search_find_path(START, GOAL, grid, usize::MAX));
+
+        assert!((path_cost(&path) - path_cost(&a_star_path)).abs() < 1e-3);
+        for pair in path.windows(2) {
+            let direction = (
+                (pair[1].0 - pair[0].0).signum(),
+                (pair[1].1 - pair[0].1).signum(),
+            );
+            let mut current = pair[0];
+            while current != pair[1] {
+                assert!(grid.can_move(current, direction));
+                current = (current.0 + direction.0, current.1 + direction.1);
+            }
+        }
+    }
+
+    // With the amount of expansions A* runs out at, jump point search still gets to the goal
+    fn assert_expands_less_than_a_star(grid: &Grid, max_expansions: usize) {
+        let a_star_result =
+            a_star_find_path(START, GOAL, grid, &CellCosts::default(), max_expansions);
+        assert!(matches!(a_star_result, AStarPathResult::Partial(_)));
+
+        let result = jump_point_search_find_path(START, GOAL, grid, max_expansions);
+        assert!(matches!(result, AStarPathResult::Found(_)));
+    }
+
+    #[test]
+    fn open_grid_paths_match_a_star() {
+        let grid = open_grid();
+
+        assert_matches_a_star(&grid);
+        assert_expands_less_than_a_star(&grid, 20);
+    }
+
+    #[test]
+    fn paths_around_obstacles_match_a_star() {
+        let grid = grid_with_obstacles();
+
+        assert_matches_a_star(&grid);
+        assert_expands_less_than_a_star(&grid, 250);
+    }
+
+    #[test]
+    fn straight_moves_only_turn_at_forced_neighbors() {
+        let mut grid = open_grid();
+        let cell = (100, 100);
+        let parent = Some((100, 99));
+
+        assert_eq!(pruned_directions(cell, parent, &grid), vec![(0, 1)]);
+
+        // Blocking the cell behind the upper side forces going up and up-right from this cell
+        grid.set_blocked((99, 99), true);
+        let mut directions = pruned_directions(cell, parent, &grid);
+        directions.sort();
+        assert_eq!(directions, vec![(-1, 0), (-1, 1), (0, 1)]);
+    }
+}
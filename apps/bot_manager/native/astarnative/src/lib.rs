@@ -1,178 +1,474 @@
 #![allow(non_snake_case)] // rustler macros generate non snake case names and dont use this allow themselves
 
-mod position;
-mod entity;
-mod collision_detection;
-
-use std::collections::{BinaryHeap, HashMap};
-use std::cmp::Ordering;
-
-use position::Position;
-use entity::Entity;
-use rustler::{Binary, OwnedBinary, Env};
-
-const GRID_CELL_SIZE: f32 = 150.0;
-const WORLD_RADIUS: f32 = 15000.0;
-const NUM_ROWS: i64 = (WORLD_RADIUS * 2.0 / GRID_CELL_SIZE) as i64;
-const NUM_COLS: i64 = (WORLD_RADIUS * 2.0 / GRID_CELL_SIZE) as i64;
+mod connected_regions;
+mod cover_positions;
+mod distance_field;
+mod grid;
+mod a_star;
+mod cost_layers;
+mod d_star_lite;
+mod flow_field;
+mod hierarchical_pathfinding;
+mod influence_map;
+mod jump_point_search;
+mod local_avoidance;
+mod multi_goal_search;
+pub mod navigation;
+mod navigation_mesh;
+mod path_following;
+mod path_smoothing;
+mod pathfinding_options;
+mod projectile_dodge;
+mod theta_star;
+mod worker_pool;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use arena_geometry::{Direction, Entity, Position};
+use cost_layers::CellCosts;
+use d_star_lite::{DStarLite, PathPlanner};
+use flow_field::FlowField;
+use grid::CollisionGrid;
+use influence_map::{InfluenceGrid, InfluenceMap, InfluenceSource};
+use navigation_mesh::NavigationMesh;
+use path_following::PathFollowing;
+use pathfinding_options::{PathfindingMode, PathfindingOptions};
+use projectile_dodge::ProjectileDodge;
+use rustler::{Atom, Env, NifMap, NifTaggedEnum, NifUnitEnum, ResourceArc, Term};
+
+mod atoms {
+    rustler::atoms! {
+        ok,
+    }
+}
 
 enum AStarPathResult {
     Found(Vec<(i64, i64)>),
+    // The goal couldn't be reached, the path goes to the closest cell to it the search got to
+    Partial(Vec<(i64, i64)>),
     NotFound,
 }
 
+// Endpoints inside obstacles are snapped to a free cell, these only happen when there's none close enough
+#[derive(NifUnitEnum)]
+enum ShortestPathError {
+    StartBlocked,
+    GoalBlocked,
+    // The search couldn't leave the start cell, e.g. with no expansions left or a start walled in
+    Unreachable,
+}
+
+#[derive(NifTaggedEnum)]
+enum ShortestPathResult {
+    Ok(Vec<Position>),
+    Partial(Vec<Position>),
+    Error(ShortestPathError),
+}
+
+#[derive(NifMap)]
+struct NearestTarget {
+    // Position of the target in the list of targets of the query
+    index: usize,
+    target: Position,
+    path: Vec<Position>,
+}
+
+#[derive(NifMap)]
+struct Region {
+    id: usize,
+    // Amount of free cells in the region
+    size: usize,
+    // Whether it's the biggest region of the grid, positions outside of it are isolated from most of the map
+    is_main: bool,
+}
+
+#[derive(NifMap)]
+struct PathChunk {
+    path: Vec<Position>,
+    remaining_waypoints: Vec<Position>,
+}
+
 #[rustler::nif()]
-fn a_star_shortest_path<'a>(from: Position, to: Position, collision_grid: Binary<'a>) -> Vec<Position> {
-    let mut grid : Vec<Vec<bool>> = vec![vec![false; NUM_COLS as usize]; NUM_ROWS as usize];
+fn a_star_shortest_path(from: Position, to: Position, collision_grid: ResourceArc<CollisionGrid>, options: PathfindingOptions) -> ShortestPathResult {
+    shortest_path(&from, &to, &collision_grid, &options)
+}
 
-    for j in 0..NUM_COLS {
-        for i in 0..NUM_ROWS {
-            grid[j as usize][i as usize] = collision_grid.as_slice()[j as usize * NUM_COLS as usize + i as usize] == 1;
-        }
+// Answers every query on the same grid, the results are in the same order as the queries.
+// When `parallel` is set the queries are split between the workers of the shared pool (see worker_pool.rs).
+#[rustler::nif(schedule = "DirtyCpu")]
+fn batch_shortest_paths(collision_grid: ResourceArc<CollisionGrid>, queries: Vec<(Position, Position, PathfindingOptions)>, parallel: bool) -> Vec<ShortestPathResult> {
+    if !parallel || queries.len() < 2 {
+        return queries
+            .iter()
+            .map(|(from, to, options)| shortest_path(from, to, &collision_grid, options))
+            .collect();
     }
 
-    let start = world_to_grid(&from);
-    let goal = world_to_grid(&to);
+    worker_pool::map(queries, move |(from, to, options)| shortest_path(&from, &to, &collision_grid, &options))
+}
+
+fn shortest_path(from: &Position, to: &Position, collision_grid: &CollisionGrid, options: &PathfindingOptions) -> ShortestPathResult {
+    let grid = &collision_grid.grid;
 
-    if let AStarPathResult::Found(path_in_grid) = a_star_find_path(start, goal, grid) {
+    // Endpoints inside obstacles are moved to the closest free cell around them, see `Grid::nearest_free_cell`
+    let Some(start) = grid.nearest_free_cell(grid::world_to_grid(from)) else {
+        return ShortestPathResult::Error(ShortestPathError::StartBlocked);
+    };
+    let Some(goal) = grid.nearest_free_cell(grid::world_to_grid(to)) else {
+        return ShortestPathResult::Error(ShortestPathError::GoalBlocked);
+    };
+
+    to_shortest_path_result(find_path(start, goal, collision_grid, options))
+}
+
+fn to_shortest_path_result(path_result: AStarPathResult) -> ShortestPathResult {
+    let to_world = |path_in_grid: Vec<(i64, i64)>| {
         path_in_grid
             .iter()
-            .map(|grid_position| Position::add(&grid_to_world(grid_position), &Position {x: GRID_CELL_SIZE / 2.0, y: GRID_CELL_SIZE / 2.0}))
+            .map(grid::grid_to_world_center)
             .collect::<Vec<Position>>()
-    } else {
-        Vec::new()
+    };
+
+    match path_result {
+        AStarPathResult::Found(path_in_grid) => ShortestPathResult::Ok(to_world(path_in_grid)),
+        AStarPathResult::Partial(path_in_grid) => ShortestPathResult::Partial(to_world(path_in_grid)),
+        AStarPathResult::NotFound => ShortestPathResult::Error(ShortestPathError::Unreachable),
     }
 }
 
+// Runs a single search towards all the targets and returns the one with the shortest path,
+// nil if none of them can be reached
 #[rustler::nif()]
-fn build_collision_grid<'a>(env: Env<'a>, obstacles: HashMap<u64, Entity>) -> Result<Binary<'a>, String>  {
-    let grid : Option<OwnedBinary> = OwnedBinary::new(NUM_COLS as usize * NUM_ROWS as usize);
+fn a_star_nearest_of(from: Position, targets: Vec<Position>, collision_grid: ResourceArc<CollisionGrid>) -> Option<NearestTarget> {
+    let grid = &collision_grid.grid;
+    let start = grid.nearest_free_cell(grid::world_to_grid(&from))?;
+
+    let start_region = collision_grid.connected_regions.region(grid.index(start));
+
+    // Targets inside obstacles are snapped like the endpoints of `a_star_shortest_path`,
+    // the ones without free cells close to them or in another region can't be reached
+    let goals: Vec<(usize, (i64, i64))> = targets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, target)| grid.nearest_free_cell(grid::world_to_grid(target)).map(|cell| (index, cell)))
+        .filter(|(_, cell)| collision_grid.connected_regions.region(grid.index(*cell)) == start_region)
+        .collect();
+    let goal_cells: Vec<(i64, i64)> = goals.iter().map(|(_, cell)| *cell).collect();
+
+    let (goal, path_in_grid) = multi_goal_search::find_path_to_nearest(start, &goal_cells, grid)?;
+    let index = goals[goal].0;
+
+    Some(NearestTarget {
+        index,
+        target: targets[index],
+        path: path_smoothing::smooth_path(&path_in_grid, grid, &CellCosts::default())
+            .iter()
+            .map(grid::grid_to_world_center)
+            .collect(),
+    })
+}
 
-    if grid.is_none() {
-        return Err("Binary allocation failed".to_string())
-    }
+// Whether there's any path between the positions, answered from the regions labeled with the grid
+// so it's meant to discard unreachable targets before searching for a path to them
+#[rustler::nif()]
+fn is_reachable(collision_grid: ResourceArc<CollisionGrid>, from: Position, to: Position) -> bool {
+    let grid = &collision_grid.grid;
 
-    let mut grid = grid.unwrap();
+    // Snapped the same way as the endpoints of `a_star_shortest_path`
+    match (grid.nearest_free_cell(grid::world_to_grid(&from)), grid.nearest_free_cell(grid::world_to_grid(&to))) {
+        (Some(start), Some(goal)) => {
+            collision_grid.connected_regions.region(grid.index(start)) == collision_grid.connected_regions.region(grid.index(goal))
+        }
+        _ => false,
+    }
+}
 
-    let obstacles = obstacles.into_values().collect::<Vec<_>>();
+// The connected region of the position, nil if it's blocked or outside of the grid
+#[rustler::nif()]
+fn region_at(collision_grid: ResourceArc<CollisionGrid>, position: Position) -> Option<Region> {
+    let grid = &collision_grid.grid;
+    let regions = &collision_grid.connected_regions;
 
-    for j in 0..NUM_COLS {
-        for i in 0..NUM_ROWS {
-            grid[j as usize * NUM_COLS as usize + i as usize] = 0;
+    let cell = grid::world_to_grid(&position);
+    if !grid.contains(cell) {
+        return None;
+    }
 
-            let bottom_left = grid_to_world(&(j, i));
-            let bottom_right = Position::add(&bottom_left, &Position {x: GRID_CELL_SIZE, y: 0.0});
-            let top_left = Position::add(&bottom_left, &Position {x: 0.0, y: GRID_CELL_SIZE});
-            let top_right = Position::add(&top_left, &Position {x: GRID_CELL_SIZE, y: 0.0});
+    regions.region(grid.index(cell)).map(|region| Region {
+        id: region,
+        size: regions.size(region),
+        is_main: regions.main_region() == Some(region),
+    })
+}
 
-            let mut line1 = Entity::new_line(0, vec![bottom_left, bottom_right]);
-            let mut line2 = Entity::new_line(0, vec![bottom_left, top_left]);
-            let mut line3 = Entity::new_line(0, vec![top_left, top_right]);
-            let mut line4 = Entity::new_line(0, vec![top_right, bottom_right]);
+// Distance from the position to the closest obstacle, measured between cell centers so it's a multiple
+// of the cell size. Zero if the position is inside an obstacle.
+#[rustler::nif()]
+fn distance_to_obstacle(collision_grid: ResourceArc<CollisionGrid>, position: Position) -> f32 {
+    let grid = &collision_grid.grid;
 
-            grid[j as usize * NUM_COLS as usize + i as usize] |= (!line1.collides_with(&obstacles).is_empty()) as u8;
-            grid[j as usize * NUM_COLS as usize + i as usize] |= (!line2.collides_with(&obstacles).is_empty()) as u8;
-            grid[j as usize * NUM_COLS as usize + i as usize] |= (!line3.collides_with(&obstacles).is_empty()) as u8;
-            grid[j as usize * NUM_COLS as usize + i as usize] |= (!line4.collides_with(&obstacles).is_empty()) as u8;
-        }
+    let cell = grid::world_to_grid(&position);
+    if grid.is_blocked(cell) {
+        return 0.0;
     }
 
-    return Ok(grid.release(env));
+    collision_grid.distance_field.distance(grid.index(cell)) * grid::GRID_CELL_SIZE
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
-struct NodeEntry {
-    node: (i64, i64),
-    parent: (i64, i64),
-    cost: usize,
-    estimate_reach_cost: usize,
+// Positions within `radius` of `around` where an obstacle blocks the line of sight from the threat,
+// sorted from the closest to `around`
+#[rustler::nif()]
+fn find_cover_positions(collision_grid: ResourceArc<CollisionGrid>, threat_position: Position, around: Position, radius: f32) -> Vec<Position> {
+    cover_positions::find_cover_positions(&threat_position, &around, radius, &collision_grid)
 }
 
-impl Ord for NodeEntry {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.estimate_reach_cost.cmp(&self.estimate_reach_cost)
+// Only the chunk of the path inside the start cluster is refined, the rest are coarse waypoints
+// meant to be refined by calling this again once the chunk is walked
+#[rustler::nif()]
+fn a_star_next_path_chunk(from: Position, to: Position, collision_grid: ResourceArc<CollisionGrid>) -> PathChunk {
+    let grid = &collision_grid.grid;
+
+    // Snapped the same way as the endpoints of `a_star_shortest_path`
+    let (Some(start), Some(goal)) = (grid.nearest_free_cell(grid::world_to_grid(&from)), grid.nearest_free_cell(grid::world_to_grid(&to))) else {
+        return PathChunk { path: Vec::new(), remaining_waypoints: Vec::new() };
+    };
+
+    match collision_grid.cluster_graph.find_next_chunk(start, goal, grid) {
+        Some(chunk) => PathChunk {
+            path: path_smoothing::smooth_path(&chunk.refined_path, grid, &CellCosts::default())
+                .iter()
+                .map(grid::grid_to_world_center)
+                .collect(),
+            remaining_waypoints: chunk.remaining_waypoints
+                .iter()
+                .map(grid::grid_to_world_center)
+                .collect(),
+        },
+        None => PathChunk { path: Vec::new(), remaining_waypoints: Vec::new() },
     }
 }
 
-impl PartialOrd for NodeEntry {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+// Steers a bot along a path given its position and how far it moves this tick (`speed * delta_time`).
+// Callers keep the waypoint index between ticks, starting at 0 for a new path
+#[rustler::nif()]
+fn follow_path(collision_grid: ResourceArc<CollisionGrid>, path: Vec<Position>, waypoint_index: usize, position: Position, speed: f32, delta_time: f32) -> PathFollowing {
+    path_following::follow_path(&path, waypoint_index, &position, speed * delta_time, &collision_grid.grid)
+}
+
+// Closest velocity to the desired one that doesn't collide with the neighbors within `time_horizon` milliseconds,
+// assuming they keep moving the same way. Static obstacles are left to the pathfinding, polygons are ignored
+#[rustler::nif()]
+fn avoid_collisions(agent: Entity, desired_velocity: Position, neighbors: Vec<Entity>, time_horizon: f32, delta_time: f32) -> Result<Position, String> {
+    // Both divide the relative positions, so they can't be zero
+    if time_horizon <= 0.0 || delta_time <= 0.0 {
+        return Err("Time horizon and delta time must be positive".to_string());
     }
+
+    Ok(local_avoidance::avoid_collisions(&agent, &desired_velocity, &neighbors, time_horizon, delta_time))
 }
 
-fn a_star_find_path(start: (i64, i64), goal: (i64, i64), grid: Vec<Vec<bool>>) -> AStarPathResult {
-    // for each position on the grid, gets its parent node on the shortest path tree and the cost
-    // to reach there
-    let mut shortest_path_tree : HashMap<(i64, i64), (usize, (i64, i64))> = HashMap::new();
+// Projectiles that will hit the bot within `time_horizon` milliseconds if it stays still
+// and the direction to move in to dodge them
+#[rustler::nif()]
+fn dodge_projectiles(bot: Entity, projectiles: Vec<Entity>, time_horizon: f32, collision_grid: ResourceArc<CollisionGrid>, external_wall: Entity) -> ProjectileDodge {
+    projectile_dodge::dodge_projectiles(&bot, &projectiles, time_horizon, &collision_grid.grid, &external_wall)
+}
 
-    let mut priority_queue = BinaryHeap::new();
-    priority_queue.push(NodeEntry{node: start, parent: start, cost: 0, estimate_reach_cost: 0});
+#[rustler::nif(schedule = "DirtyCpu")]
+fn build_collision_grid(obstacles: HashMap<u64, Entity>) -> Result<ResourceArc<CollisionGrid>, String>  {
+    let obstacles = obstacles.into_values().collect::<Vec<_>>();
 
-    while let Some(NodeEntry { node, parent, cost, estimate_reach_cost: _estimate_reach_cost }) = priority_queue.pop() {
-        if shortest_path_tree.contains_key(&node) { continue; }
+    Ok(ResourceArc::new(CollisionGrid::build(obstacles)))
+}
 
-        shortest_path_tree.insert(node, (cost, parent));
+// Returns a new grid with the cells overlapped by the entity blocked, e.g. when a trap is placed
+#[rustler::nif(schedule = "DirtyCpu")]
+fn block_entity_cells(collision_grid: ResourceArc<CollisionGrid>, entity: Entity) -> Result<ResourceArc<CollisionGrid>, String> {
+    collision_grid.with_obstacle(entity).map(ResourceArc::new)
+}
 
-        if node == goal {
-            return AStarPathResult::Found(build_path(start, goal, &shortest_path_tree));
-        }
+// Returns a new grid without an entity previously blocked, e.g. when a crate is destroyed
+#[rustler::nif(schedule = "DirtyCpu")]
+fn unblock_entity_cells(collision_grid: ResourceArc<CollisionGrid>, entity: Entity) -> Result<ResourceArc<CollisionGrid>, String> {
+    collision_grid.without_obstacle(&entity).map(ResourceArc::new)
+}
 
-        for neigh in get_neighbors(node, &grid) {
-            if !shortest_path_tree.contains_key(&neigh) {
-                priority_queue.push(NodeEntry { node: neigh, parent: node, cost: cost + 1, estimate_reach_cost: cost + 1 + heuristic_distance(neigh, goal) });
-            }
-        }
-    }
+// Returns a new grid with its cost layers built from the entities, e.g. pools, traps and bushes.
+// Each entity marks its cells in the layer of its category, replacing the layers the grid had.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn set_cost_layers(collision_grid: ResourceArc<CollisionGrid>, entities: Vec<Entity>) -> ResourceArc<CollisionGrid> {
+    ResourceArc::new(collision_grid.with_cost_layers(&entities))
+}
 
-    return AStarPathResult::NotFound;
+// Planner that keeps its search state so the path can be repaired after the grid changes
+#[rustler::nif()]
+fn new_path_planner(from: Position, to: Position, collision_grid: ResourceArc<CollisionGrid>) -> Result<ResourceArc<PathPlanner>, String> {
+    let grid = &collision_grid.grid;
+
+    // Snapped the same way as the endpoints of `a_star_shortest_path`
+    let Some(start) = grid.nearest_free_cell(grid::world_to_grid(&from)) else {
+        return Err("There's no free cell close to the path start".to_string());
+    };
+    let Some(goal) = grid.nearest_free_cell(grid::world_to_grid(&to)) else {
+        return Err("There's no free cell close to the path goal".to_string());
+    };
+
+    Ok(ResourceArc::new(PathPlanner { state: Mutex::new(DStarLite::new(start, goal, grid)) }))
 }
 
-fn build_path(start: (i64, i64), goal: (i64, i64), shortest_path_tree: &HashMap<(i64, i64), (usize, (i64, i64))>) -> Vec<(i64, i64)> {
-    let mut current = goal;
-    let mut path = Vec::new();
+// Path from the current position to the planner goal on the given grid. The first call plans from
+// scratch, later ones only repair the cells affected by what changed in the grid since the last call
+#[rustler::nif()]
+fn replan_path(path_planner: ResourceArc<PathPlanner>, from: Position, collision_grid: ResourceArc<CollisionGrid>) -> ShortestPathResult {
+    let grid = &collision_grid.grid;
 
-    path.push(goal);
+    // The bot may have been pushed inside something that appeared, it's snapped like in `a_star_shortest_path`
+    let Some(start) = grid.nearest_free_cell(grid::world_to_grid(&from)) else {
+        return ShortestPathResult::Error(ShortestPathError::StartBlocked);
+    };
 
-    while current != start {
-        current = shortest_path_tree[&current].1;
-        path.push(current);
-    }
+    let mut planner = path_planner.state.lock().unwrap();
 
-    path.reverse();
+    let path_result = match planner.replan(start, grid) {
+        AStarPathResult::Found(path_in_grid) => AStarPathResult::Found(path_smoothing::smooth_path(&path_in_grid, grid, &CellCosts::default())),
+        // D* Lite only tells the goal can't be reached, a regular search finds how close we can get to it
+        _ => find_path(start, planner.goal(), &collision_grid, &PathfindingOptions::default()),
+    };
 
-    return path;
+    to_shortest_path_result(path_result)
 }
 
-fn get_neighbors(pos: (i64, i64), grid: &[Vec<bool>]) -> Vec<(i64, i64)> {
-    let mut neighbors = Vec::new();
+// Directions towards the goal for every cell of the grid, meant to be shared by every bot heading to it
+#[rustler::nif(schedule = "DirtyCpu")]
+fn build_flow_field(collision_grid: ResourceArc<CollisionGrid>, goal: Position) -> Result<ResourceArc<FlowField>, String> {
+    match FlowField::build(goal, &collision_grid.grid) {
+        Some(flow_field) => Ok(ResourceArc::new(flow_field)),
+        None => Err("Flow field goal is outside of the grid or blocked".to_string()),
+    }
+}
 
-    for (dy, dx) in [(-1, 0), (1, 0), (0, 1), (0, -1), (-1, -1), (-1, 1), (1, -1), (-1, -1)] {
-        let neigh_pos = (pos.0 + dy, pos.1 + dx);
+#[rustler::nif()]
+fn flow_field_direction(flow_field: ResourceArc<FlowField>, position: Position) -> Direction {
+    flow_field.direction_at(&position)
+}
 
-        if neigh_pos.0 >= 0 && neigh_pos.0 < NUM_ROWS && neigh_pos.1 >= 0 && neigh_pos.1 < NUM_COLS && !grid[neigh_pos.0 as usize][neigh_pos.1 as usize] {
-            neighbors.push(neigh_pos);
-        }
+// Empty influence map, its influence fades to half every `half_life` milliseconds
+#[rustler::nif()]
+fn new_influence_map(half_life: f32) -> ResourceArc<InfluenceMap> {
+    ResourceArc::new(InfluenceMap { state: Mutex::new(InfluenceGrid::new(half_life)) })
+}
+
+#[rustler::nif()]
+fn decay_influence(influence_map: ResourceArc<InfluenceMap>, elapsed_time: f32) -> Atom {
+    influence_map.state.lock().unwrap().decay(elapsed_time);
+    atoms::ok()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn add_influence(influence_map: ResourceArc<InfluenceMap>, sources: Vec<InfluenceSource>) -> Atom {
+    let mut influence_grid = influence_map.state.lock().unwrap();
+    for source in sources.iter() {
+        influence_grid.add_source(source);
     }
+    atoms::ok()
+}
 
-    return neighbors;
+// Adds `strength` to every cell outside of the safe zone, usually negative so bots stay inside
+#[rustler::nif()]
+fn add_zone_influence(influence_map: ResourceArc<InfluenceMap>, zone_center: Position, zone_radius: f32, strength: f32) -> Atom {
+    influence_map.state.lock().unwrap().add_zone(&zone_center, zone_radius, strength);
+    atoms::ok()
 }
 
-fn heuristic_distance(from: (i64, i64), to: (i64, i64)) -> usize {
-    (from.0.abs_diff(to.0) + from.1.abs_diff(to.1)) as usize
+#[rustler::nif()]
+fn influence_at(influence_map: ResourceArc<InfluenceMap>, position: Position) -> f32 {
+    influence_map.state.lock().unwrap().influence_at(&position)
 }
 
-fn world_to_grid(pos: &Position) -> (i64, i64) {
-    (
-        ((pos.y + WORLD_RADIUS) / GRID_CELL_SIZE) as i64, 
-        ((pos.x + WORLD_RADIUS) / GRID_CELL_SIZE) as i64
-    )
+// Reachable position within `radius` of `around` with the highest influence, nil if there's none
+#[rustler::nif()]
+fn best_influence_position(
+    influence_map: ResourceArc<InfluenceMap>,
+    collision_grid: ResourceArc<CollisionGrid>,
+    around: Position,
+    radius: f32,
+) -> Option<Position> {
+    influence_map.state.lock().unwrap().best_position(&collision_grid, &around, radius)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn build_navigation_mesh(obstacles: HashMap<u64, Entity>, external_wall: Entity, agent_radius: f32) -> Result<ResourceArc<NavigationMesh>, String> {
+    let obstacles = obstacles.into_values().collect::<Vec<_>>();
+
+    NavigationMesh::build(&obstacles, &external_wall, agent_radius).map(ResourceArc::new)
 }
 
-fn grid_to_world(grid_pos: &(i64, i64)) -> Position {
-    Position { 
-        x: (grid_pos.1 - NUM_COLS / 2) as f32 * GRID_CELL_SIZE, 
-        y: (grid_pos.0 - NUM_ROWS / 2) as f32 * GRID_CELL_SIZE 
+#[rustler::nif()]
+fn navigation_mesh_shortest_path(navigation_mesh: ResourceArc<NavigationMesh>, from: Position, to: Position) -> Vec<Position> {
+    navigation_mesh.find_path(from, to)
+}
+
+// SVG with the blocked cells of the grid under the wall, obstacles, entities and paths
+#[rustler::nif(schedule = "DirtyCpu")]
+fn render_debug_svg(collision_grid: ResourceArc<CollisionGrid>, external_wall: Entity, obstacles: Vec<Entity>, entities: Vec<Entity>, paths: Vec<Vec<Position>>) -> String {
+    let scene = arena_geometry::DebugScene {
+        external_wall: Some(external_wall),
+        obstacles,
+        entities,
+        grid: Some(collision_grid.grid.to_debug_grid()),
+        paths,
+    };
+
+    arena_geometry::render_svg(&scene)
+}
+
+fn find_path(start: (i64, i64), goal: (i64, i64), collision_grid: &CollisionGrid, options: &PathfindingOptions) -> AStarPathResult {
+    let grid = &collision_grid.grid;
+
+    if !grid.contains(start) || !grid.contains(goal) {
+        return AStarPathResult::NotFound;
+    }
+
+    // Only A* is able to search with cost layers, the rest of the modes assume every cell costs the same
+    let cell_costs = match options.mode {
+        PathfindingMode::AStar => collision_grid.cost_layers.cell_costs(&options.cost_weights),
+        _ => CellCosts::default(),
+    };
+
+    let max_expansions = options.max_expansions.unwrap_or(usize::MAX);
+
+    let path_result = match options.mode {
+        PathfindingMode::AStar => a_star::a_star_find_path(start, goal, grid, &cell_costs, max_expansions),
+        PathfindingMode::JumpPointSearch => jump_point_search::jump_point_search_find_path(start, goal, grid, max_expansions),
+        // The abstract graph can tell there's no path but not how close we can get, A* is able to
+        PathfindingMode::Hierarchical => match collision_grid.cluster_graph.find_path(start, goal, grid) {
+            AStarPathResult::NotFound => a_star::a_star_find_path(start, goal, grid, &cell_costs, max_expansions),
+            path_result => path_result,
+        },
+        // Theta* paths are already any-angle, there's nothing to smooth
+        PathfindingMode::ThetaStar => return theta_star::theta_star_find_path(start, goal, grid, max_expansions),
+    };
+
+    match path_result {
+        AStarPathResult::Found(path_in_grid) => AStarPathResult::Found(path_smoothing::smooth_path(&path_in_grid, grid, &cell_costs)),
+        AStarPathResult::Partial(path_in_grid) => AStarPathResult::Partial(path_smoothing::smooth_path(&path_in_grid, grid, &cell_costs)),
+        AStarPathResult::NotFound => AStarPathResult::NotFound,
     }
 }
 
-rustler::init!("Elixir.AStarNative", [a_star_shortest_path, build_collision_grid]);
+#[allow(non_local_definitions)] // rustler resource! macro implements a trait inside this function
+fn load(env: Env, _: Term) -> bool {
+    rustler::resource!(CollisionGrid, env);
+    rustler::resource!(NavigationMesh, env);
+    rustler::resource!(PathPlanner, env);
+    rustler::resource!(FlowField, env);
+    rustler::resource!(InfluenceMap, env);
+    true
+}
+
+rustler::init!(
+    "Elixir.AStarNative",
+    [a_star_shortest_path, batch_shortest_paths, a_star_nearest_of, is_reachable, region_at, distance_to_obstacle, find_cover_positions, a_star_next_path_chunk, follow_path, avoid_collisions, dodge_projectiles, build_collision_grid, block_entity_cells, unblock_entity_cells, set_cost_layers, new_path_planner, replan_path, build_flow_field, flow_field_direction, new_influence_map, decay_influence, add_influence, add_zone_influence, influence_at, best_influence_position, build_navigation_mesh, navigation_mesh_shortest_path, render_debug_svg],
+    load = load
+);
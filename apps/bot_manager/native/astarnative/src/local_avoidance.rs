@@ -0,0 +1,393 @@
+use arena_geometry::{Category, Entity, Position, Shape};
+
+/*
+Local avoidance with Optimal Reciprocal Collision Avoidance (ORCA)
+
+Each nearby entity rules out the velocities that would make the agent collide with it within the
+time horizon, assuming it keeps its current velocity. The forbidden region is approximated by a
+half-plane of velocities and the new velocity is the closest one to the desired velocity that's
+inside every half-plane and under the agent speed. When there's no such velocity (too crowded) the
+one that violates the half-planes the least is used.
+
+Other players are expected to avoid the agent as well, so each side only takes half of the
+responsibility of avoiding the collision. Every other entity (projectiles, moving obstacles...)
+won't, so the agent avoids them completely.
+
+Speeds are in units per millisecond like in the game, so time horizon and delta time are in
+milliseconds.
+
+- [ORCA paper](https://gamma.cs.unc.edu/ORCA/publications/ORCA.pdf)
+- [RVO2 library](https://github.com/snape/RVO2), this is a port of its agent avoidance
+*/
+
+const EPSILON: f32 = 0.00001;
+
+// Velocities on the left of the line (looking towards its direction) are allowed
+#[derive(Clone, Copy)]
+struct Line {
+    point: Position,
+    direction: Position,
+}
+
+pub(crate) fn avoid_collisions(
+    agent: &Entity,
+    desired_velocity: &Position,
+    neighbors: &[Entity],
+    time_horizon: f32,
+    delta_time: f32,
+) -> Position {
+    let velocity = velocity(agent);
+
+    let lines: Vec<Line> = neighbors
+        .iter()
+        .filter(|neighbor| neighbor.id != agent.id && neighbor.shape != Shape::Polygon)
+        .map(|neighbor| orca_line(agent, &velocity, neighbor, time_horizon, delta_time))
+        .collect();
+
+    let (failed_line, new_velocity) =
+        linear_program_2(&lines, agent.speed, desired_velocity, false);
+    if failed_line < lines.len() {
+        linear_program_3(&lines, failed_line, agent.speed, new_velocity)
+    } else {
+        new_velocity
+    }
+}
+
+fn velocity(entity: &Entity) -> Position {
+    if entity.is_moving {
+        Position {
+            x: entity.direction.x * entity.speed,
+            y: entity.direction.y * entity.speed,
+        }
+    } else {
+        Position { x: 0.0, y: 0.0 }
+    }
+}
+
+// Half-plane of velocities that avoid colliding with the neighbor within the time horizon
+fn orca_line(
+    agent: &Entity,
+    agent_velocity: &Position,
+    neighbor: &Entity,
+    time_horizon: f32,
+    delta_time: f32,
+) -> Line {
+    let relative_position = Position::sub(&neighbor.position, &agent.position);
+    let relative_velocity = Position::sub(agent_velocity, &velocity(neighbor));
+    let distance_squared = length_squared(&relative_position);
+    let combined_radius = agent.radius + neighbor.radius;
+    let combined_radius_squared = combined_radius * combined_radius;
+
+    let (direction, u) = if distance_squared > combined_radius_squared {
+        // Vector from the center of the truncated velocity obstacle to the relative velocity
+        let w = Position::sub(
+            &relative_velocity,
+            &Position::mult(&relative_position, 1.0 / time_horizon),
+        );
+        let w_length_squared = length_squared(&w);
+        let dot_product = dot(&w, &relative_position);
+
+        if dot_product < 0.0
+            && dot_product * dot_product > combined_radius_squared * w_length_squared
+        {
+            // Closest to the cutoff circle of the velocity obstacle
+            let w_length = w_length_squared.sqrt();
+            let unit_w = Position::mult(&w, 1.0 / w_length);
+            (
+                Position {
+                    x: unit_w.y,
+                    y: -unit_w.x,
+                },
+                Position::mult(&unit_w, combined_radius / time_horizon - w_length),
+            )
+        } else {
+            // Closest to one of the legs of the velocity obstacle
+            let leg = (distance_squared - combined_radius_squared).sqrt();
+            let direction = if determinant(&relative_position, &w) > 0.0 {
+                Position {
+                    x: relative_position.x * leg - relative_position.y * combined_radius,
+                    y: relative_position.x * combined_radius + relative_position.y * leg,
+                }
+            } else {
+                Position {
+                    x: -(relative_position.x * leg + relative_position.y * combined_radius),
+                    y: -(-relative_position.x * combined_radius + relative_position.y * leg),
+                }
+            };
+            let direction = Position::mult(&direction, 1.0 / distance_squared);
+            let u = Position::sub(
+                &Position::mult(&direction, dot(&relative_velocity, &direction)),
+                &relative_velocity,
+            );
+            (direction, u)
+        }
+    } else {
+        // Already colliding, the velocity obstacle is cut off by the next step instead
+        let w = Position::sub(
+            &relative_velocity,
+            &Position::mult(&relative_position, 1.0 / delta_time),
+        );
+        let w_length = length_squared(&w).sqrt().max(EPSILON);
+        let unit_w = Position::mult(&w, 1.0 / w_length);
+        (
+            Position {
+                x: unit_w.y,
+                y: -unit_w.x,
+            },
+            Position::mult(&unit_w, combined_radius / delta_time - w_length),
+        )
+    };
+
+    let responsibility = if neighbor.category == Category::Player {
+        0.5
+    } else {
+        1.0
+    };
+
+    Line {
+        point: Position::add(agent_velocity, &Position::mult(&u, responsibility)),
+        direction,
+    }
+}
+
+// Closest velocity to the optimal one on the line that respects the previous lines and the speed,
+// None if there's none
+fn linear_program_1(
+    lines: &[Line],
+    line_index: usize,
+    speed: f32,
+    optimal_velocity: &Position,
+    optimize_direction: bool,
+) -> Option<Position> {
+    let line = &lines[line_index];
+    let dot_product = dot(&line.point, &line.direction);
+    let discriminant = dot_product * dot_product + speed * speed - length_squared(&line.point);
+    if discriminant < 0.0 {
+        // The max speed circle doesn't reach the line
+        return None;
+    }
+
+    let discriminant_root = discriminant.sqrt();
+    let mut t_left = -dot_product - discriminant_root;
+    let mut t_right = -dot_product + discriminant_root;
+
+    for previous_line in &lines[..line_index] {
+        let denominator = determinant(&line.direction, &previous_line.direction);
+        let numerator = determinant(
+            &previous_line.direction,
+            &Position::sub(&line.point, &previous_line.point),
+        );
+
+        if denominator.abs() <= EPSILON {
+            // Parallel lines
+            if numerator < 0.0 {
+                return None;
+            }
+            continue;
+        }
+
+        let t = numerator / denominator;
+        if denominator >= 0.0 {
+            t_right = t_right.min(t);
+        } else {
+            t_left = t_left.max(t);
+        }
+
+        if t_left > t_right {
+            return None;
+        }
+    }
+
+    let t = if optimize_direction {
+        if dot(optimal_velocity, &line.direction) > 0.0 {
+            t_right
+        } else {
+            t_left
+        }
+    } else {
+        dot(
+            &line.direction,
+            &Position::sub(optimal_velocity, &line.point),
+        )
+        .clamp(t_left, t_right)
+    };
+
+    Some(Position::add(
+        &line.point,
+        &Position::mult(&line.direction, t),
+    ))
+}
+
+// Closest velocity to the optimal one that respects every line and the speed. Returns the index
+// of the first line that couldn't be satisfied (the amount of lines if all were) and the velocity
+// that satisfies the ones before it
+fn linear_program_2(
+    lines: &[Line],
+    speed: f32,
+    optimal_velocity: &Position,
+    optimize_direction: bool,
+) -> (usize, Position) {
+    let mut result = if optimize_direction {
+        // The optimal velocity is a unit direction in this case
+        Position::mult(optimal_velocity, speed)
+    } else if length_squared(optimal_velocity) > speed * speed {
+        let mut direction = *optimal_velocity;
+        direction.normalize();
+        Position::mult(&direction, speed)
+    } else {
+        *optimal_velocity
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        if determinant(&line.direction, &Position::sub(&line.point, &result)) > 0.0 {
+            match linear_program_1(lines, index, speed, optimal_velocity, optimize_direction) {
+                Some(velocity) => result = velocity,
+                None => return (index, result),
+            }
+        }
+    }
+
+    (lines.len(), result)
+}
+
+// Velocity that minimizes the biggest violation of the lines from `failed_line` on, used when
+// there's no velocity that satisfies all of them
+fn linear_program_3(
+    lines: &[Line],
+    failed_line: usize,
+    speed: f32,
+    velocity: Position,
+) -> Position {
+    let mut result = velocity;
+    let mut distance = 0.0;
+
+    for (index, line) in lines.iter().enumerate().skip(failed_line) {
+        if determinant(&line.direction, &Position::sub(&line.point, &result)) <= distance {
+            continue;
+        }
+
+        let mut projected_lines: Vec<Line> = Vec::new();
+        for previous_line in &lines[..index] {
+            let line_determinant = determinant(&line.direction, &previous_line.direction);
+            let point = if line_determinant.abs() <= EPSILON {
+                if dot(&line.direction, &previous_line.direction) > 0.0 {
+                    // Lines pointing the same way
+                    continue;
+                }
+                Position::mult(&Position::add(&line.point, &previous_line.point), 0.5)
+            } else {
+                let t = determinant(
+                    &previous_line.direction,
+                    &Position::sub(&line.point, &previous_line.point),
+                ) / line_determinant;
+                Position::add(&line.point, &Position::mult(&line.direction, t))
+            };
+
+            let mut direction = Position::sub(&previous_line.direction, &line.direction);
+            direction.normalize();
+            projected_lines.push(Line { point, direction });
+        }
+
+        let optimal_direction = Position {
+            x: -line.direction.y,
+            y: line.direction.x,
+        };
+        let (failed_projected_line, projected_result) =
+            linear_program_2(&projected_lines, speed, &optimal_direction, true);
+        // Failing here can only come from floating point errors, the result is kept as is then
+        if failed_projected_line == projected_lines.len() {
+            result = projected_result;
+        }
+
+        distance = determinant(&line.direction, &Position::sub(&line.point, &result));
+    }
+
+    result
+}
+
+fn dot(a: &Position, b: &Position) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn determinant(a: &Position, b: &Position) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn length_squared(a: &Position) -> f32 {
+    dot(a, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arena_geometry::Direction;
+
+    const RADIUS: f32 = 50.0;
+    const SPEED: f32 = 0.5;
+    const TIME_HORIZON: f32 = 2000.0;
+    const DELTA_TIME: f32 = 30.0;
+
+    fn player(id: u64, x: f32, y: f32) -> Entity {
+        Entity {
+            shape: Shape::Circle,
+            radius: RADIUS,
+            speed: SPEED,
+            category: Category::Player,
+            ..Entity::new_point(id, Position { x, y })
+        }
+    }
+
+    // Velocity that gets to the goal in one tick, avoidance caps it to the agent speed
+    fn desired_velocity(agent: &Entity, goal: &Position) -> Position {
+        Position::mult(&Position::sub(goal, &agent.position), 1.0 / DELTA_TIME)
+    }
+
+    fn move_agent(agent: &mut Entity, velocity: &Position) {
+        agent.position = Position::add(&agent.position, &Position::mult(velocity, DELTA_TIME));
+        agent.direction = Direction {
+            x: velocity.x / SPEED,
+            y: velocity.y / SPEED,
+        };
+        agent.is_moving = length_squared(velocity) > 0.0;
+    }
+
+    #[test]
+    fn players_moving_head_on_pass_each_other_without_colliding() {
+        // Slightly off the same line, perfectly mirrored agents can't pick a side and stop in
+        // front of each other
+        let mut first = player(1, -1000.0, 0.0);
+        let mut second = player(2, 1000.0, 10.0);
+        let first_goal = second.position;
+        let second_goal = first.position;
+
+        for _ in 0..300 {
+            let neighbors = [first.clone(), second.clone()];
+            let first_velocity = avoid_collisions(
+                &first,
+                &desired_velocity(&first, &first_goal),
+                &neighbors,
+                TIME_HORIZON,
+                DELTA_TIME,
+            );
+            let second_velocity = avoid_collisions(
+                &second,
+                &desired_velocity(&second, &second_goal),
+                &neighbors,
+                TIME_HORIZON,
+                DELTA_TIME,
+            );
+
+            assert!(length_squared(&first_velocity) <= SPEED * SPEED + EPSILON);
+            assert!(length_squared(&second_velocity) <= SPEED * SPEED + EPSILON);
+
+            move_agent(&mut first, &first_velocity);
+            move_agent(&mut second, &second_velocity);
+
+            let distance = first.position.distance_to_position(&second.position);
+            assert!(distance >= 2.0 * RADIUS - 1.0, "distance {}", distance);
+        }
+
+        assert!(first.position.distance_to_position(&first_goal) < 1.0);
+        assert!(second.position.distance_to_position(&second_goal) < 1.0);
+    }
+}
@@ -0,0 +1,118 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+
+use crate::grid::{octile_distance, Grid};
+
+/*
+ * A* towards several goals at once, it stops at the first goal it expands, which is the one with
+ * the shortest path from the start. The heuristic is the distance to the closest goal, that never
+ * overestimates the cost to reach whichever goal ends up being the nearest.
+ *
+ * Moves can't cut blocked corners, straight moves cost 1 and diagonal ones sqrt(2).
+ */
+
+#[derive(Clone, Copy, PartialEq)]
+struct NodeEntry {
+    node: usize,
+    estimate_reach_cost: f32,
+}
+
+impl Eq for NodeEntry {}
+
+impl Ord for NodeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate_reach_cost
+            .total_cmp(&self.estimate_reach_cost)
+    }
+}
+
+impl PartialOrd for NodeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Returns the index of the nearest goal by path distance and the path to it
+pub(crate) fn find_path_to_nearest(
+    start: (i64, i64),
+    goals: &[(i64, i64)],
+    grid: &Grid,
+) -> Option<(usize, Vec<(i64, i64)>)> {
+    if goals.is_empty() {
+        return None;
+    }
+
+    let mut costs: Vec<f32> = vec![f32::INFINITY; grid.len()];
+    let mut parents: Vec<usize> = vec![usize::MAX; grid.len()];
+    let mut expanded: Vec<bool> = vec![false; grid.len()];
+
+    let heuristic = |cell: (i64, i64)| {
+        goals
+            .iter()
+            .map(|goal| octile_distance(cell, *goal))
+            .fold(f32::INFINITY, f32::min)
+    };
+
+    let start_index = grid.index(start);
+    costs[start_index] = 0.0;
+    parents[start_index] = start_index;
+
+    let mut priority_queue = BinaryHeap::new();
+    priority_queue.push(NodeEntry {
+        node: start_index,
+        estimate_reach_cost: heuristic(start),
+    });
+
+    while let Some(NodeEntry { node, .. }) = priority_queue.pop() {
+        if expanded[node] {
+            continue;
+        }
+        expanded[node] = true;
+
+        let cell = grid.cell(node);
+        if let Some(goal_index) = goals.iter().position(|goal| *goal == cell) {
+            return Some((goal_index, build_path(start_index, node, grid, &parents)));
+        }
+
+        for neigh in grid.neighbors(cell) {
+            let neigh_index = grid.index(neigh);
+            if expanded[neigh_index] {
+                continue;
+            }
+
+            let move_cost = if neigh.0 != cell.0 && neigh.1 != cell.1 {
+                SQRT_2
+            } else {
+                1.0
+            };
+            let neigh_cost = costs[node] + move_cost;
+
+            if neigh_cost < costs[neigh_index] {
+                costs[neigh_index] = neigh_cost;
+                parents[neigh_index] = node;
+                priority_queue.push(NodeEntry {
+                    node: neigh_index,
+                    estimate_reach_cost: neigh_cost + heuristic(neigh),
+                });
+            }
+        }
+    }
+
+    None
+}
+
+fn build_path(start: usize, goal: usize, grid: &Grid, parents: &[usize]) -> Vec<(i64, i64)> {
+    let mut current = goal;
+    let mut path = vec![grid.cell(goal)];
+
+    while current != start {
+        current = parents[current];
+        path.push(grid.cell(current));
+    }
+
+    path.reverse();
+
+    path
+}
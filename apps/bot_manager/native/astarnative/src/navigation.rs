@@ -0,0 +1,79 @@
+use arena_geometry::{Direction, Entity, Position};
+
+use crate::grid::CollisionGrid;
+use crate::pathfinding_options::PathfindingOptions;
+use crate::{path_following, shortest_path, ShortestPathResult};
+
+/*
+ * Bots navigation for Rust programs running outside of the BEAM, like the arena simulator.
+ *
+ * It answers the same as the NIFs do with the default pathfinding options, without the
+ * resources and terms the NIFs pass around.
+ */
+pub struct Navigation {
+    collision_grid: CollisionGrid,
+}
+
+pub enum PathResult {
+    Found(Vec<Position>),
+    // The goal couldn't be reached, the path goes as close to it as possible
+    Partial(Vec<Position>),
+    // The start or the goal have no free cell around them
+    Blocked,
+}
+
+pub struct PathStep {
+    // Unit direction to move in, zero once the end of the path is reached
+    pub direction: Direction,
+    pub waypoint_index: usize,
+    pub needs_replan: bool,
+}
+
+impl Navigation {
+    pub fn new(obstacles: Vec<Entity>) -> Navigation {
+        Navigation {
+            collision_grid: CollisionGrid::build(obstacles),
+        }
+    }
+
+    pub fn shortest_path(&self, from: &Position, to: &Position) -> PathResult {
+        match shortest_path(
+            from,
+            to,
+            &self.collision_grid,
+            &PathfindingOptions::default(),
+        ) {
+            ShortestPathResult::Ok(path) => PathResult::Found(path),
+            ShortestPathResult::Partial(path) => PathResult::Partial(path),
+            ShortestPathResult::Error(_) => PathResult::Blocked,
+        }
+    }
+
+    // Same as the `follow_path` NIF, `step` is the distance the bot moves this tick
+    pub fn follow_path(
+        &self,
+        path: &[Position],
+        waypoint_index: usize,
+        position: &Position,
+        step: f32,
+    ) -> PathStep {
+        let following = path_following::follow_path(
+            path,
+            waypoint_index,
+            position,
+            step,
+            &self.collision_grid.grid,
+        );
+
+        PathStep {
+            direction: following.direction,
+            waypoint_index: following.waypoint_index,
+            needs_replan: following.needs_replan,
+        }
+    }
+
+    // Blocked cells of the collision grid, to draw them with `arena_geometry::render_svg`
+    pub fn debug_grid(&self) -> arena_geometry::DebugGrid {
+        self.collision_grid.grid.to_debug_grid()
+    }
+}
@@ -0,0 +1,869 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::f32::consts::PI;
+
+use arena_geometry::collision_detection::ear_clipping::is_clockwise;
+use arena_geometry::collision_detection::oriented_box::OrientedBox;
+use arena_geometry::spatial_query::{closest_point, point_in_polygon};
+use arena_geometry::{Entity, Position, Shape};
+
+mod funnel;
+mod triangulation;
+
+/*
+Navigation mesh
+
+Instead of splitting the map in uniform cells, the walkable area is split in triangles that follow
+the obstacles borders, so paths can go right next to the obstacles no matter how big the
+cells of the collision grid are, and big open areas are covered by just a few nodes.
+
+To build it:
+1. The external wall and the obstacles are turned into polygons inflated (or deflated, in the wall
+   case) by the agent radius, so walking on the mesh border keeps the agent from colliding
+2. Points are sampled along every border and on a coarse lattice over the open areas
+3. The points are triangulated with the borders as constraints (see triangulation.rs), so every
+   triangle is either fully walkable or fully blocked, and we only keep the walkable ones
+4. Triangles sharing an edge are linked as neighbors, and indexed in buckets to find the
+   triangle under a position without going through all of them
+
+To find a path we run A* over the triangles to get the corridor from the start triangle to the
+goal one, and then the funnel algorithm (see funnel.rs) pulls the shortest path inside that corridor.
+*/
+
+// Max distance between the points sampled along obstacles and wall borders
+const BORDER_SAMPLE_SPACING: f32 = 300.0;
+// Distance between the points sampled over the open areas
+const INTERIOR_SAMPLE_SPACING: f32 = 1500.0;
+// Amount of segments used to approximate circle obstacles
+const CIRCLE_OBSTACLE_SEGMENTS: usize = 16;
+// Amount of segments used to approximate the external wall
+const EXTERNAL_WALL_SEGMENTS: usize = 64;
+// When inflating polygons, corners so sharp that the moved vertex would end farther than this
+// ratio times the inflate amount are beveled with two vertices instead
+const MAX_MITER_RATIO: f32 = 2.0;
+// Triangles smaller than this are leftovers of almost aligned points and are discarded
+const MIN_TRIANGLE_AREA: f32 = 1.0;
+// Side of the square buckets used to look up the triangles around a position
+const BUCKET_SIZE: f32 = 1000.0;
+
+pub struct NavigationMesh {
+    vertices: Vec<Position>,
+    triangles: Vec<NavigationTriangle>,
+    buckets: TriangleBuckets,
+}
+
+struct NavigationTriangle {
+    // Vertex indices in counter clockwise order
+    vertices: [usize; 3],
+    // Triangle on the other side of each edge, edge i goes from vertex i to vertex i + 1
+    neighbors: [Option<usize>; 3],
+}
+
+impl NavigationMesh {
+    pub(crate) fn build(
+        obstacles: &[Entity],
+        external_wall: &Entity,
+        agent_radius: f32,
+    ) -> Result<NavigationMesh, String> {
+        let walkable_radius = external_wall.radius - agent_radius;
+        let wall_polygon = circle_to_polygon(
+            &external_wall.position,
+            walkable_radius,
+            EXTERNAL_WALL_SEGMENTS,
+        );
+
+        let mut inflated_obstacles: Vec<Vec<Position>> = Vec::new();
+        for obstacle in obstacles {
+            inflated_obstacles.extend(inflate_obstacle(obstacle, agent_radius)?);
+        }
+
+        let is_walkable = |position: &Position| {
+            point_in_polygon(position, &wall_polygon)
+                && !inflated_obstacles
+                    .iter()
+                    .any(|obstacle| point_in_polygon(position, obstacle))
+        };
+
+        let mut points = MeshPoints::default();
+        for polygon in std::iter::once(&wall_polygon).chain(inflated_obstacles.iter()) {
+            points.add_border(&sample_polygon_border(polygon));
+        }
+        for point in sample_interior(&external_wall.position, walkable_radius) {
+            if is_walkable(&point) {
+                points.add(point);
+            }
+        }
+
+        let MeshPoints {
+            positions: mut vertices,
+            constraints,
+            ..
+        } = points;
+        let triangles: Vec<[usize; 3]> = triangulation::triangulate(&mut vertices, &constraints)?
+            .into_iter()
+            .filter(|triangle| {
+                let [a, b, c] = triangle.map(|vertex| vertices[vertex]);
+                let centroid = Position {
+                    x: (a.x + b.x + c.x) / 3.0,
+                    y: (a.y + b.y + c.y) / 3.0,
+                };
+
+                // No triangle crosses a border, so its centroid tells on which side it is
+                Position::cross_product(&a, &b, &c) / 2.0 > MIN_TRIANGLE_AREA
+                    && is_walkable(&centroid)
+            })
+            .collect();
+
+        Ok(NavigationMesh {
+            buckets: TriangleBuckets::build(&triangles, &vertices),
+            vertices,
+            triangles: link_neighbor_triangles(triangles),
+        })
+    }
+
+    /*
+     * Returns the shortest path on the mesh going from `from` to `to`, both included,
+     * or an empty vector if there's no way to get there
+     * Positions outside of the mesh (e.g. inside an inflated obstacle) are moved to the
+     * closest position on the mesh
+     */
+    pub(crate) fn find_path(&self, from: Position, to: Position) -> Vec<Position> {
+        let (Some((start_triangle, from)), Some((goal_triangle, to))) =
+            (self.locate(&from), self.locate(&to))
+        else {
+            return Vec::new();
+        };
+
+        match self.find_corridor(start_triangle, goal_triangle, &from, &to) {
+            Some(corridor) => {
+                let portals: Vec<(Position, Position)> = corridor
+                    .windows(2)
+                    .map(|pair| self.portal_between(pair[0], pair[1]))
+                    .collect();
+
+                funnel::find_path_through_portals(from, to, &portals)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    // A* over the triangles, moving from one triangle to its neighbors through the
+    // middle point of their shared edge
+    fn find_corridor(
+        &self,
+        start_triangle: usize,
+        goal_triangle: usize,
+        from: &Position,
+        to: &Position,
+    ) -> Option<Vec<usize>> {
+        // For each reached triangle, the cost to reach it, where we entered it and its parent
+        let mut reached: HashMap<usize, (f32, Position, usize)> = HashMap::new();
+        let mut expanded: HashSet<usize> = HashSet::new();
+        let mut priority_queue = BinaryHeap::new();
+
+        reached.insert(start_triangle, (0.0, *from, start_triangle));
+        priority_queue.push(TriangleEntry {
+            triangle: start_triangle,
+            estimate_reach_cost: from.distance_to_position(to),
+        });
+
+        while let Some(TriangleEntry { triangle, .. }) = priority_queue.pop() {
+            if !expanded.insert(triangle) {
+                continue;
+            }
+
+            if triangle == goal_triangle {
+                let mut corridor = vec![goal_triangle];
+                let mut current = goal_triangle;
+                while current != start_triangle {
+                    current = reached[&current].2;
+                    corridor.push(current);
+                }
+                corridor.reverse();
+                return Some(corridor);
+            }
+
+            let (cost, entry_point, _) = reached[&triangle];
+
+            for (edge, neighbor) in self.triangles[triangle].neighbors.iter().enumerate() {
+                let Some(neighbor) = *neighbor else { continue };
+                if expanded.contains(&neighbor) {
+                    continue;
+                }
+
+                let (left, right) = self.edge_vertices(triangle, edge);
+                let neighbor_entry_point = Position::mult(&Position::add(&left, &right), 0.5);
+                let neighbor_cost = cost + entry_point.distance_to_position(&neighbor_entry_point);
+
+                let improves_cost = match reached.get(&neighbor) {
+                    Some((current_cost, _, _)) => neighbor_cost < *current_cost,
+                    None => true,
+                };
+
+                if improves_cost {
+                    reached.insert(neighbor, (neighbor_cost, neighbor_entry_point, triangle));
+                    priority_queue.push(TriangleEntry {
+                        triangle: neighbor,
+                        estimate_reach_cost: neighbor_cost
+                            + neighbor_entry_point.distance_to_position(to),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    // The shared edge between two neighbor triangles as (left, right) when walking from
+    // the first one into the second one
+    fn portal_between(&self, from_triangle: usize, to_triangle: usize) -> (Position, Position) {
+        let edge = self.triangles[from_triangle]
+            .neighbors
+            .iter()
+            .position(|neighbor| *neighbor == Some(to_triangle))
+            .expect("Corridor triangles should be neighbors");
+
+        self.edge_vertices(from_triangle, edge)
+    }
+
+    // Since triangles are counter clockwise, when leaving a triangle through an edge
+    // its end vertex is on the left and its start vertex is on the right
+    fn edge_vertices(&self, triangle: usize, edge: usize) -> (Position, Position) {
+        let vertices = self.triangles[triangle].vertices;
+        (
+            self.vertices[vertices[(edge + 1) % 3]],
+            self.vertices[vertices[edge]],
+        )
+    }
+
+    /*
+     * Finds the triangle containing the position, if no triangle contains it the
+     * closest position on the mesh is used instead.
+     * Buckets are checked in rings around the position's one, until the ring is farther away
+     * than the closest position found so far
+     */
+    fn locate(&self, position: &Position) -> Option<(usize, Position)> {
+        let (row, col) = self.buckets.bucket_of(position);
+
+        for triangle in self.buckets.triangles_at(row, col) {
+            let [a, b, c] = self.triangles[*triangle]
+                .vertices
+                .map(|vertex| self.vertices[vertex]);
+
+            if Position::cross_product(&a, &b, position) >= 0.0
+                && Position::cross_product(&b, &c, position) >= 0.0
+                && Position::cross_product(&c, &a, position) >= 0.0
+            {
+                return Some((*triangle, *position));
+            }
+        }
+
+        let mut closest: Option<(usize, Position, f32)> = None;
+
+        for ring in 0..=self.buckets.rows.max(self.buckets.cols) {
+            if closest.is_some_and(|(_, _, distance)| distance <= (ring as f32 - 1.0) * BUCKET_SIZE)
+            {
+                break;
+            }
+
+            for (ring_row, ring_col) in ring_buckets(row, col, ring) {
+                for triangle in self.buckets.triangles_at(ring_row, ring_col) {
+                    let [a, b, c] = self.triangles[*triangle]
+                        .vertices
+                        .map(|vertex| self.vertices[vertex]);
+
+                    for (start, end) in [(a, b), (b, c), (c, a)] {
+                        let candidate = closest_point(position, &start, &end);
+                        let distance = candidate.distance_to_position(position);
+                        if closest
+                            .is_none_or(|(_, _, closest_distance)| distance < closest_distance)
+                        {
+                            closest = Some((*triangle, candidate, distance));
+                        }
+                    }
+                }
+            }
+        }
+
+        closest.map(|(index, closest_position, _)| (index, closest_position))
+    }
+}
+
+// Mesh points without duplicates, and the border segments between them
+#[derive(Default)]
+struct MeshPoints {
+    positions: Vec<Position>,
+    index_by_position: HashMap<(u32, u32), usize>,
+    constraints: Vec<(usize, usize)>,
+}
+
+impl MeshPoints {
+    fn add(&mut self, position: Position) -> usize {
+        *self
+            .index_by_position
+            .entry((position.x.to_bits(), position.y.to_bits()))
+            .or_insert_with(|| {
+                self.positions.push(position);
+                self.positions.len() - 1
+            })
+    }
+
+    // Adds the points of a closed border, every segment between them is a constraint
+    fn add_border(&mut self, border: &[Position]) {
+        let indices: Vec<usize> = border.iter().map(|point| self.add(*point)).collect();
+
+        for current_index in 0..indices.len() {
+            let start = indices[current_index];
+            let end = indices[(current_index + 1) % indices.len()];
+            if start != end {
+                self.constraints.push((start, end));
+            }
+        }
+    }
+}
+
+// Uniform grid over the mesh, each bucket lists the triangles whose bounding box overlaps it
+struct TriangleBuckets {
+    origin: Position,
+    rows: usize,
+    cols: usize,
+    buckets: Vec<Vec<usize>>,
+}
+
+impl TriangleBuckets {
+    fn build(triangles: &[[usize; 3]], vertices: &[Position]) -> TriangleBuckets {
+        let bounding_box = |triangle: &[usize; 3]| {
+            let [a, b, c] = triangle.map(|vertex| vertices[vertex]);
+            (
+                Position {
+                    x: a.x.min(b.x).min(c.x),
+                    y: a.y.min(b.y).min(c.y),
+                },
+                Position {
+                    x: a.x.max(b.x).max(c.x),
+                    y: a.y.max(b.y).max(c.y),
+                },
+            )
+        };
+
+        let (mut min, mut max) = (
+            Position {
+                x: f32::MAX,
+                y: f32::MAX,
+            },
+            Position {
+                x: f32::MIN,
+                y: f32::MIN,
+            },
+        );
+        for (triangle_min, triangle_max) in triangles.iter().map(bounding_box) {
+            min = Position {
+                x: min.x.min(triangle_min.x),
+                y: min.y.min(triangle_min.y),
+            };
+            max = Position {
+                x: max.x.max(triangle_max.x),
+                y: max.y.max(triangle_max.y),
+            };
+        }
+
+        let mut triangle_buckets = TriangleBuckets {
+            origin: min,
+            rows: (((max.y - min.y) / BUCKET_SIZE).floor().max(0.0) as usize) + 1,
+            cols: (((max.x - min.x) / BUCKET_SIZE).floor().max(0.0) as usize) + 1,
+            buckets: Vec::new(),
+        };
+        if triangles.is_empty() {
+            triangle_buckets.rows = 0;
+            triangle_buckets.cols = 0;
+            return triangle_buckets;
+        }
+        triangle_buckets.buckets = vec![Vec::new(); triangle_buckets.rows * triangle_buckets.cols];
+
+        for (index, triangle) in triangles.iter().enumerate() {
+            let (triangle_min, triangle_max) = bounding_box(triangle);
+            let (min_row, min_col) = triangle_buckets.bucket_of(&triangle_min);
+            let (max_row, max_col) = triangle_buckets.bucket_of(&triangle_max);
+
+            for row in min_row..=max_row {
+                for col in min_col..=max_col {
+                    triangle_buckets.buckets[row as usize * triangle_buckets.cols + col as usize]
+                        .push(index);
+                }
+            }
+        }
+
+        triangle_buckets
+    }
+
+    // Bucket containing the position, positions outside of the mesh get the closest bucket
+    fn bucket_of(&self, position: &Position) -> (i64, i64) {
+        let row = ((position.y - self.origin.y) / BUCKET_SIZE).floor() as i64;
+        let col = ((position.x - self.origin.x) / BUCKET_SIZE).floor() as i64;
+        (
+            row.clamp(0, self.rows as i64 - 1),
+            col.clamp(0, self.cols as i64 - 1),
+        )
+    }
+
+    fn triangles_at(&self, row: i64, col: i64) -> &[usize] {
+        if row < 0 || col < 0 || row >= self.rows as i64 || col >= self.cols as i64 {
+            return &[];
+        }
+        &self.buckets[row as usize * self.cols + col as usize]
+    }
+}
+
+// Buckets at exactly `ring` buckets away (moving diagonally too) from the given one
+fn ring_buckets(row: i64, col: i64, ring: usize) -> Vec<(i64, i64)> {
+    let ring = ring as i64;
+    if ring == 0 {
+        return vec![(row, col)];
+    }
+
+    (-ring..=ring)
+        .flat_map(|row_offset| (-ring..=ring).map(move |col_offset| (row_offset, col_offset)))
+        .filter(|(row_offset, col_offset)| row_offset.abs() == ring || col_offset.abs() == ring)
+        .map(|(row_offset, col_offset)| (row + row_offset, col + col_offset))
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct TriangleEntry {
+    triangle: usize,
+    estimate_reach_cost: f32,
+}
+
+impl Eq for TriangleEntry {}
+
+impl Ord for TriangleEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate_reach_cost
+            .total_cmp(&self.estimate_reach_cost)
+    }
+}
+
+impl PartialOrd for TriangleEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn link_neighbor_triangles(triangles: Vec<[usize; 3]>) -> Vec<NavigationTriangle> {
+    // For each edge, the triangle and edge index that has it
+    let mut edges: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+    let mut navigation_triangles: Vec<NavigationTriangle> = triangles
+        .into_iter()
+        .map(|vertices| NavigationTriangle {
+            vertices,
+            neighbors: [None; 3],
+        })
+        .collect();
+
+    for triangle_index in 0..navigation_triangles.len() {
+        for edge_index in 0..3 {
+            let vertices = navigation_triangles[triangle_index].vertices;
+            let start = vertices[edge_index];
+            let end = vertices[(edge_index + 1) % 3];
+
+            // The neighbor triangle has the same edge going in the opposite direction
+            if let Some((neighbor_index, neighbor_edge_index)) = edges.remove(&(end, start)) {
+                navigation_triangles[triangle_index].neighbors[edge_index] = Some(neighbor_index);
+                navigation_triangles[neighbor_index].neighbors[neighbor_edge_index] =
+                    Some(triangle_index);
+            } else {
+                edges.insert((start, end), (triangle_index, edge_index));
+            }
+        }
+    }
+
+    navigation_triangles
+}
+
+/*
+ * Polygons covering the obstacle grown by the agent radius. Most shapes give a single polygon,
+ * lines give one per segment. Points and lines have no area but still block the agent body.
+ * Fails on shapes without enough vertices to know where they are.
+ */
+fn inflate_obstacle(obstacle: &Entity, agent_radius: f32) -> Result<Vec<Vec<Position>>, String> {
+    match obstacle.shape {
+        Shape::Circle => Ok(vec![inflate_circle(
+            &obstacle.position,
+            obstacle.radius + agent_radius,
+        )]),
+        Shape::Point => Ok(vec![inflate_circle(&obstacle.position, agent_radius)]),
+        Shape::Polygon if obstacle.vertices.len() >= 3 => {
+            Ok(vec![inflate_polygon(&obstacle.vertices, agent_radius)])
+        }
+        Shape::Line if obstacle.vertices.len() >= 2 => Ok(obstacle
+            .vertices
+            .windows(2)
+            .map(|segment| inflate_segment(&segment[0], &segment[1], agent_radius))
+            .collect()),
+        Shape::Capsule { a, b, radius } => Ok(vec![inflate_segment(&a, &b, radius + agent_radius)]),
+        Shape::Sector { radius, half_angle } => Ok(vec![inflate_polygon(
+            &sector_to_polygon(obstacle, radius, half_angle),
+            agent_radius,
+        )]),
+        Shape::OrientedBox { .. } => {
+            let oriented_box = OrientedBox::of(obstacle).expect("The shape is an oriented box");
+            Ok(vec![inflate_polygon(&oriented_box.corners(), agent_radius)])
+        }
+        Shape::Polygon | Shape::Line => Err(format!(
+            "Obstacle {} doesn't have enough vertices for its shape",
+            obstacle.id
+        )),
+    }
+}
+
+// The polygon vertices are placed so the polygon edges are tangent to the inflated circle,
+// that way the polygon fully contains it
+fn inflate_circle(center: &Position, radius: f32) -> Vec<Position> {
+    circle_to_polygon(
+        center,
+        radius / (PI / CIRCLE_OBSTACLE_SEGMENTS as f32).cos(),
+        CIRCLE_OBSTACLE_SEGMENTS,
+    )
+}
+
+// Every point within `radius` of the segment: two half circles joined by the segment sides,
+// with the half circles built like `inflate_circle` so the polygon contains them
+fn inflate_segment(a: &Position, b: &Position, radius: f32) -> Vec<Position> {
+    if a == b {
+        return inflate_circle(a, radius);
+    }
+
+    let segment = Position::sub(b, a);
+    let facing = segment.y.atan2(segment.x);
+    let half_circle_segments = CIRCLE_OBSTACLE_SEGMENTS / 2;
+    let step = PI / half_circle_segments as f32;
+    let radius = radius / (step / 2.0).cos();
+
+    [(b, facing - PI / 2.0), (a, facing + PI / 2.0)]
+        .into_iter()
+        .flat_map(|(center, start_angle)| {
+            (0..=half_circle_segments).map(move |segment| {
+                let angle = start_angle + step * segment as f32;
+                Position {
+                    x: center.x + radius * angle.cos(),
+                    y: center.y + radius * angle.sin(),
+                }
+            })
+        })
+        .collect()
+}
+
+// The sector corners (its apex and the ends of the arc) with the arc sampled between them, the
+// arc vertices are pushed out like in `inflate_circle` so the polygon contains the sector
+fn sector_to_polygon(sector: &Entity, radius: f32, half_angle: f32) -> Vec<Position> {
+    if half_angle >= 180.0 {
+        return inflate_circle(&sector.position, radius);
+    }
+
+    // Like the physics, no direction means facing right
+    let facing = if sector.direction.x == 0.0 && sector.direction.y == 0.0 {
+        0.0
+    } else {
+        sector.direction.y.atan2(sector.direction.x)
+    };
+    let half_angle = half_angle.to_radians();
+    let arc_segments = ((CIRCLE_OBSTACLE_SEGMENTS as f32 * half_angle / PI).ceil() as usize).max(1);
+    let step = 2.0 * half_angle / arc_segments as f32;
+    let arc_radius = radius / (step / 2.0).cos();
+
+    std::iter::once(sector.position)
+        .chain((0..=arc_segments).map(|segment| {
+            let angle = facing - half_angle + step * segment as f32;
+            Position {
+                x: sector.position.x + arc_radius * angle.cos(),
+                y: sector.position.y + arc_radius * angle.sin(),
+            }
+        }))
+        .collect()
+}
+
+fn circle_to_polygon(center: &Position, radius: f32, segments: usize) -> Vec<Position> {
+    (0..segments)
+        .map(|segment| {
+            let angle = 2.0 * PI * segment as f32 / segments as f32;
+            Position {
+                x: center.x + radius * angle.cos(),
+                y: center.y + radius * angle.sin(),
+            }
+        })
+        .collect()
+}
+
+/*
+ * Moves every edge of the polygon outwards by the given amount, each vertex is placed where
+ * the moved edges next to it meet. Convex corners that are too sharp would send that vertex
+ * too far away, so they get cut with two vertices instead (one for each moved edge)
+ */
+fn inflate_polygon(vertices: &[Position], amount: f32) -> Vec<Position> {
+    let mut vertices = vertices.to_vec();
+    vertices.dedup();
+    if vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+
+    // Outwards is to the right of the edges on counter clockwise polygons and to the left otherwise
+    let orientation = if !is_clockwise(&vertices) { 1.0 } else { -1.0 };
+    let outward_normal = |from: &Position, to: &Position| {
+        let mut edge = Position::sub(to, from);
+        edge.normalize();
+        Position {
+            x: edge.y * orientation,
+            y: -edge.x * orientation,
+        }
+    };
+
+    let mut inflated_vertices = Vec::new();
+
+    for current_index in 0..vertices.len() {
+        let previous = vertices[(current_index + vertices.len() - 1) % vertices.len()];
+        let current = vertices[current_index];
+        let next = vertices[(current_index + 1) % vertices.len()];
+
+        let previous_normal = outward_normal(&previous, &current);
+        let next_normal = outward_normal(&current, &next);
+
+        let mut bisector = Position::add(&previous_normal, &next_normal);
+        let bisector_length = bisector.distance_to_position(&Position { x: 0.0, y: 0.0 });
+        let is_convex = Position::cross_product(&previous, &current, &next) * orientation > 0.0;
+
+        let miter_ratio = if bisector_length > f32::EPSILON {
+            bisector.normalize();
+            1.0 / (bisector.x * previous_normal.x + bisector.y * previous_normal.y)
+        } else {
+            f32::INFINITY
+        };
+
+        // Spikes where the polygon goes back over the same line also need to be cut
+        if miter_ratio > MAX_MITER_RATIO && (is_convex || !miter_ratio.is_finite()) {
+            inflated_vertices.push(Position::add(
+                &current,
+                &Position::mult(&previous_normal, amount),
+            ));
+            inflated_vertices.push(Position::add(
+                &current,
+                &Position::mult(&next_normal, amount),
+            ));
+        } else {
+            inflated_vertices.push(Position::add(
+                &current,
+                &Position::mult(&bisector, amount * miter_ratio),
+            ));
+        }
+    }
+
+    inflated_vertices
+}
+
+fn sample_polygon_border(vertices: &[Position]) -> Vec<Position> {
+    let mut samples = Vec::new();
+
+    for current_index in 0..vertices.len() {
+        let current = vertices[current_index];
+        let next = vertices[(current_index + 1) % vertices.len()];
+        let edge = Position::sub(&next, &current);
+        let segments = (current.distance_to_position(&next) / BORDER_SAMPLE_SPACING)
+            .ceil()
+            .max(1.0) as usize;
+
+        for segment in 0..segments {
+            samples.push(Position::add(
+                &current,
+                &Position::mult(&edge, segment as f32 / segments as f32),
+            ));
+        }
+    }
+
+    samples
+}
+
+fn sample_interior(center: &Position, radius: f32) -> Vec<Position> {
+    let steps = (radius / INTERIOR_SAMPLE_SPACING) as i64;
+
+    (-steps..=steps)
+        .flat_map(|row| (-steps..=steps).map(move |col| (row, col)))
+        .map(|(row, col)| Position {
+            x: center.x + col as f32 * INTERIOR_SAMPLE_SPACING,
+            y: center.y + row as f32 * INTERIOR_SAMPLE_SPACING,
+        })
+        // Keep lattice points away from the wall border, where it was already sampled
+        .filter(|point| point.distance_to_position(center) < radius - INTERIOR_SAMPLE_SPACING / 2.0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arena_geometry::{Category, Direction};
+
+    const AGENT_RADIUS: f32 = 100.0;
+
+    fn external_wall() -> Entity {
+        Entity {
+            id: 0,
+            shape: Shape::Circle,
+            position: Position { x: 0.0, y: 0.0 },
+            radius: 15000.0,
+            vertices: Vec::new(),
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction: Direction { x: 0.0, y: 0.0 },
+            is_moving: false,
+            name: "Wall".to_string(),
+        }
+    }
+
+    fn square(id: u64, min: f32, max: f32) -> Entity {
+        Entity::new_polygon(
+            id,
+            vec![
+                Position { x: min, y: min },
+                Position { x: max, y: min },
+                Position { x: max, y: max },
+                Position { x: min, y: max },
+            ],
+        )
+    }
+
+    // Two overlapping squares, so their borders cross each other
+    fn overlapping_squares() -> Vec<Entity> {
+        vec![square(1, -1000.0, 1000.0), square(2, 500.0, 2500.0)]
+    }
+
+    fn is_inside_squares(position: &Position, margin: f32) -> bool {
+        let inside = |min: f32, max: f32| {
+            position.x > min - margin
+                && position.x < max + margin
+                && position.y > min - margin
+                && position.y < max + margin
+        };
+        inside(-1000.0, 1000.0) || inside(500.0, 2500.0)
+    }
+
+    #[test]
+    fn triangles_cover_the_walkable_area() {
+        let mesh =
+            NavigationMesh::build(&overlapping_squares(), &external_wall(), AGENT_RADIUS).unwrap();
+
+        let mesh_area: f32 = mesh
+            .triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.vertices.map(|vertex| mesh.vertices[vertex]);
+                Position::cross_product(&a, &b, &c) / 2.0
+            })
+            .sum();
+
+        let segments = EXTERNAL_WALL_SEGMENTS as f32;
+        let walkable_radius = 15000.0 - AGENT_RADIUS;
+        let wall_area = segments / 2.0 * walkable_radius.powi(2) * (2.0 * PI / segments).sin();
+        // Both squares grow by the agent radius on each side, and overlap in a 700 x 700 square
+        let obstacles_area = 2.0 * 2200.0_f32.powi(2) - 700.0_f32.powi(2);
+
+        let expected_area = wall_area - obstacles_area;
+        assert!((mesh_area - expected_area).abs() / expected_area < 1e-3);
+    }
+
+    #[test]
+    fn paths_go_around_the_obstacles() {
+        let mesh =
+            NavigationMesh::build(&overlapping_squares(), &external_wall(), AGENT_RADIUS).unwrap();
+        let from = Position {
+            x: -3000.0,
+            y: 3000.0,
+        };
+        let to = Position {
+            x: 3000.0,
+            y: -3000.0,
+        };
+
+        let path = mesh.find_path(from, to);
+
+        assert_eq!(path.first(), Some(&from));
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.len() > 2);
+        for pair in path.windows(2) {
+            for step in 0..=20 {
+                let along = Position::add(
+                    &pair[0],
+                    &Position::mult(&Position::sub(&pair[1], &pair[0]), step as f32 / 20.0),
+                );
+                assert!(!is_inside_squares(&along, AGENT_RADIUS - 1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn positions_inside_obstacles_are_moved_to_their_border() {
+        let mesh =
+            NavigationMesh::build(&overlapping_squares(), &external_wall(), AGENT_RADIUS).unwrap();
+
+        let path = mesh.find_path(
+            Position { x: -500.0, y: 0.0 },
+            Position { x: -5000.0, y: 0.0 },
+        );
+
+        let start = path.first().unwrap();
+        assert!((start.x + 1100.0).abs() < 1.0);
+        assert!(start.y.abs() < 1.0);
+    }
+
+    #[test]
+    fn every_obstacle_shape_blocks_the_mesh() {
+        let position = Position { x: 0.0, y: 0.0 };
+        let obstacles = [
+            Entity {
+                shape: Shape::Capsule {
+                    a: Position { x: -500.0, y: 0.0 },
+                    b: Position { x: 500.0, y: 0.0 },
+                    radius: 200.0,
+                },
+                ..Entity::new_point(1, position)
+            },
+            Entity {
+                shape: Shape::Sector {
+                    radius: 800.0,
+                    half_angle: 45.0,
+                },
+                direction: Direction { x: 0.0, y: 1.0 },
+                ..Entity::new_point(2, position)
+            },
+            Entity::new_line(
+                3,
+                vec![
+                    Position { x: -500.0, y: 0.0 },
+                    Position { x: 500.0, y: 0.0 },
+                ],
+            ),
+            Entity::new_point(4, position),
+        ];
+
+        for obstacle in obstacles {
+            let mesh = NavigationMesh::build(
+                std::slice::from_ref(&obstacle),
+                &external_wall(),
+                AGENT_RADIUS,
+            )
+            .unwrap();
+            let inside = match obstacle.shape {
+                Shape::Sector { .. } => Position { x: 0.0, y: 500.0 },
+                _ => Position { x: 0.0, y: 50.0 },
+            };
+
+            let located = mesh.locate(&inside).unwrap().1;
+            assert!(located.distance_to_position(&inside) > 40.0);
+        }
+    }
+
+    #[test]
+    fn obstacles_without_enough_vertices_are_rejected() {
+        let line = Entity::new_line(1, vec![Position { x: 0.0, y: 0.0 }]);
+
+        assert!(NavigationMesh::build(&[line], &external_wall(), AGENT_RADIUS).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use arena_geometry::Position;
+
+/*
+Funnel algorithm (also known as simple stupid funnel)
+
+Given the corridor of triangles found by the search, described by the portals (shared edges)
+we have to go through, it finds the shortest path inside the corridor.
+
+We keep a funnel formed by an apex and two legs, one to the left side of the portals and one to
+the right side. For each portal we try to narrow the funnel moving its legs to the portal sides:
+- If the new side keeps the funnel open we narrow it
+- If the new side crosses over the other leg, the path has to turn around the corner at the tip
+  of that other leg, which becomes a waypoint and the apex of a new funnel
+
+- [Simple stupid funnel algorithm](https://digestingduck.blogspot.com/2010/03/simple-stupid-funnel-algorithm.html)
+*/
+
+// Each portal is a pair of (left, right) positions as seen when walking through it
+pub(crate) fn find_path_through_portals(
+    from: Position,
+    to: Position,
+    portals: &[(Position, Position)],
+) -> Vec<Position> {
+    let mut all_portals = vec![(from, from)];
+    all_portals.extend_from_slice(portals);
+    all_portals.push((to, to));
+
+    let mut path = vec![from];
+
+    let mut apex = from;
+    let mut funnel_left = from;
+    let mut funnel_right = from;
+    let mut left_index = 0;
+    let mut right_index = 0;
+
+    let mut portal_index = 1;
+    while portal_index < all_portals.len() {
+        let (left, right) = all_portals[portal_index];
+
+        // Try to narrow the right leg of the funnel
+        if Position::cross_product(&apex, &funnel_right, &right) >= 0.0 {
+            if apex == funnel_right || Position::cross_product(&apex, &funnel_left, &right) < 0.0 {
+                funnel_right = right;
+                right_index = portal_index;
+            } else {
+                // The right side crossed over the left leg, turn around the left corner
+                apex = funnel_left;
+                push_waypoint(&mut path, apex);
+
+                funnel_left = apex;
+                funnel_right = apex;
+                right_index = left_index;
+                portal_index = left_index + 1;
+                continue;
+            }
+        }
+
+        // Try to narrow the left leg of the funnel
+        if Position::cross_product(&apex, &funnel_left, &left) <= 0.0 {
+            if apex == funnel_left || Position::cross_product(&apex, &funnel_right, &left) > 0.0 {
+                funnel_left = left;
+                left_index = portal_index;
+            } else {
+                // The left side crossed over the right leg, turn around the right corner
+                apex = funnel_right;
+                push_waypoint(&mut path, apex);
+
+                funnel_left = apex;
+                funnel_right = apex;
+                left_index = right_index;
+                portal_index = right_index + 1;
+                continue;
+            }
+        }
+
+        portal_index += 1;
+    }
+
+    push_waypoint(&mut path, to);
+
+    path
+}
+
+fn push_waypoint(path: &mut Vec<Position>, waypoint: Position) {
+    if path.last() != Some(&waypoint) {
+        path.push(waypoint);
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::{HashMap, VecDeque};
+
+use arena_geometry::Position;
+
+/*
+Constrained Delaunay triangulation
+
+The result has every constraint segment (the obstacles and wall borders) as an edge of its
+triangles, so no triangle is partly walkable and partly inside an obstacle.
+
+1. Constraints are split where they cross each other (overlapping obstacles) and where they
+   go over another point, new points are added at the crossings
+2. Every point is triangulated with the Bowyer-Watson algorithm: we start with a triangle big
+   enough to contain every point and insert the points one by one, removing the triangles whose
+   circumcircle contains the new point and connecting the border of the hole they leave to it
+3. Constraints missing from the triangulation are recovered by flipping the edges crossing them
+   (Sloan's algorithm): the diagonal of the two triangles around a crossing edge is flipped when
+   they form a convex quad, until no edge crosses the constraint
+4. The triangles that use a vertex of the initial triangle are removed
+
+Triangles far from the constraints keep the Delaunay shape, the ones next to them may be thinner.
+
+Computations are done in f64 since circumcircle tests square the coordinates of a map
+that is tens of thousands of units wide.
+
+- [Bowyer-Watson algorithm](https://en.wikipedia.org/wiki/Bowyer%E2%80%93Watson_algorithm)
+- [Sloan, A fast algorithm for generating constrained Delaunay triangulations](https://doi.org/10.1016/0045-7949(93)90239-A)
+*/
+
+// Crossings and points closer than this to a constraint are snapped to it
+const ON_SEGMENT_DISTANCE: f64 = 1e-3;
+// Recovering a constraint gives up after this many flips, which only happens on degenerate input
+// since every flip removes one of the edges crossing it
+const MAX_FLIPS_PER_CONSTRAINT: usize = 10_000;
+
+struct Triangle {
+    vertices: [usize; 3],
+    circumcenter: (f64, f64),
+    circumradius_squared: f64,
+}
+
+/*
+ * Returns the triangles as indices of the points, in counter clockwise order. Constraints are
+ * pairs of point indices, the points added where constraints cross are pushed to `points`.
+ * Fails if some constraint can't be made an edge, a mesh without it would go through obstacles
+ */
+pub(crate) fn triangulate(
+    points: &mut Vec<Position>,
+    constraints: &[(usize, usize)],
+) -> Result<Vec<[usize; 3]>, String> {
+    triangulate_with_flip_limit(points, constraints, MAX_FLIPS_PER_CONSTRAINT)
+}
+
+fn triangulate_with_flip_limit(
+    points: &mut Vec<Position>,
+    constraints: &[(usize, usize)],
+    max_flips: usize,
+) -> Result<Vec<[usize; 3]>, String> {
+    if points.len() < 3 {
+        return Ok(Vec::new());
+    }
+
+    let mut vertices: Vec<(f64, f64)> = points
+        .iter()
+        .map(|point| (point.x as f64, point.y as f64))
+        .collect();
+    let constraints = split_constraints(&mut vertices, constraints);
+    let vertices_count = vertices.len();
+
+    let super_triangle = add_super_triangle_vertices(&mut vertices);
+    let mut triangles = vec![new_triangle(super_triangle, &vertices)];
+
+    for point_index in 0..vertices_count {
+        let point = vertices[point_index];
+
+        let (invalid_triangles, valid_triangles): (Vec<Triangle>, Vec<Triangle>) =
+            triangles.into_iter().partition(|triangle| {
+                squared_distance(triangle.circumcenter, point) < triangle.circumradius_squared
+            });
+        triangles = valid_triangles;
+
+        for edge in hole_border_edges(&invalid_triangles) {
+            let triangle = new_triangle([edge.0, edge.1, point_index], &vertices);
+            if triangle.circumradius_squared.is_finite() {
+                triangles.push(triangle);
+            }
+        }
+    }
+
+    let mut triangles: Vec<[usize; 3]> = triangles
+        .into_iter()
+        .map(|triangle| triangle.vertices)
+        .collect();
+    let missing_constraints =
+        recover_constraints(&mut triangles, &constraints, &vertices, max_flips);
+    if let Some((start, end)) = missing_constraints.first() {
+        return Err(format!(
+            "{} border segments couldn't be made mesh edges, the first one goes from \
+             ({:.1}, {:.1}) to ({:.1}, {:.1})",
+            missing_constraints.len(),
+            vertices[*start].0,
+            vertices[*start].1,
+            vertices[*end].0,
+            vertices[*end].1
+        ));
+    }
+
+    points.extend(
+        vertices[points.len()..vertices_count]
+            .iter()
+            .map(|(x, y)| Position {
+                x: *x as f32,
+                y: *y as f32,
+            }),
+    );
+
+    Ok(triangles
+        .into_iter()
+        .filter(|triangle| triangle.iter().all(|vertex| *vertex < vertices_count))
+        .collect())
+}
+
+// Splits the constraints in pieces that don't cross each other nor go over other vertices
+fn split_constraints(
+    vertices: &mut Vec<(f64, f64)>,
+    constraints: &[(usize, usize)],
+) -> Vec<(usize, usize)> {
+    // For each constraint, the vertices splitting it and how far along the constraint they are
+    let mut splits: Vec<Vec<(f64, usize)>> = vec![Vec::new(); constraints.len()];
+
+    for first in 0..constraints.len() {
+        for second in (first + 1)..constraints.len() {
+            let (a, b) = constraints[first];
+            let (c, d) = constraints[second];
+            if a == c || a == d || b == c || b == d {
+                continue;
+            }
+
+            let Some(crossing) =
+                segments_crossing(vertices[a], vertices[b], vertices[c], vertices[d])
+            else {
+                continue;
+            };
+
+            // Crossings right next to an endpoint only split the other constraint at that endpoint
+            let endpoint = [a, b, c, d].into_iter().find(|vertex| {
+                squared_distance(vertices[*vertex], crossing) <= ON_SEGMENT_DISTANCE.powi(2)
+            });
+            let crossing_vertex = endpoint.unwrap_or_else(|| {
+                vertices.push(crossing);
+                vertices.len() - 1
+            });
+
+            for constraint in [first, second] {
+                let (start, end) = constraints[constraint];
+                if crossing_vertex != start && crossing_vertex != end {
+                    let along = segment_projection(vertices[start], vertices[end], crossing);
+                    splits[constraint].push((along, crossing_vertex));
+                }
+            }
+        }
+    }
+
+    for (constraint, (start, end)) in constraints.iter().enumerate() {
+        let (start_position, end_position) = (vertices[*start], vertices[*end]);
+
+        for (vertex, position) in vertices.iter().enumerate() {
+            if vertex == *start || vertex == *end {
+                continue;
+            }
+
+            let along = segment_projection(start_position, end_position, *position);
+            if along <= 0.0 || along >= 1.0 {
+                continue;
+            }
+
+            let closest = (
+                start_position.0 + (end_position.0 - start_position.0) * along,
+                start_position.1 + (end_position.1 - start_position.1) * along,
+            );
+            if squared_distance(closest, *position) <= ON_SEGMENT_DISTANCE.powi(2) {
+                splits[constraint].push((along, vertex));
+            }
+        }
+    }
+
+    let mut split_constraints = Vec::new();
+    for (constraint, (start, end)) in constraints.iter().enumerate() {
+        let constraint_splits = &mut splits[constraint];
+        constraint_splits.sort_by(|(first, _), (second, _)| first.total_cmp(second));
+
+        let mut previous = *start;
+        for vertex in constraint_splits
+            .iter()
+            .map(|(_, vertex)| *vertex)
+            .chain([*end])
+        {
+            if vertex != previous {
+                split_constraints.push((previous, vertex));
+                previous = vertex;
+            }
+        }
+    }
+
+    split_constraints
+}
+
+/*
+ * Flips the edges crossing each constraint until the constraint is an edge of the triangulation.
+ * Returns the constraints that are still missing after `max_flips` flips
+ */
+fn recover_constraints(
+    triangles: &mut [[usize; 3]],
+    constraints: &[(usize, usize)],
+    vertices: &[(f64, f64)],
+    max_flips: usize,
+) -> Vec<(usize, usize)> {
+    // Every directed edge and the triangle having it, triangles are counter clockwise so
+    // each edge shared by two triangles appears once in each direction
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    for (triangle_index, [a, b, c]) in triangles.iter().enumerate() {
+        for edge in [(*a, *b), (*b, *c), (*c, *a)] {
+            edges.insert(edge, triangle_index);
+        }
+    }
+
+    let crosses_constraint = |(a, b): (usize, usize), (start, end): (usize, usize)| {
+        a != start
+            && a != end
+            && b != start
+            && b != end
+            && segments_crossing(vertices[a], vertices[b], vertices[start], vertices[end]).is_some()
+    };
+
+    let is_edge = |edges: &HashMap<(usize, usize), usize>, (start, end): (usize, usize)| {
+        edges.contains_key(&(start, end)) || edges.contains_key(&(end, start))
+    };
+    let mut missing_constraints = Vec::new();
+
+    for constraint in constraints.iter().copied() {
+        if is_edge(&edges, constraint) {
+            continue;
+        }
+
+        let mut crossing_edges: VecDeque<(usize, usize)> = triangles
+            .iter()
+            .flat_map(|[a, b, c]| [(*a, *b), (*b, *c), (*c, *a)])
+            .filter(|(a, b)| a < b && crosses_constraint((*a, *b), constraint))
+            .collect();
+
+        let mut flips = 0;
+        while let Some((a, b)) = crossing_edges.pop_front() {
+            if flips >= max_flips {
+                break;
+            }
+            let (Some(first), Some(second)) =
+                (edges.get(&(a, b)).copied(), edges.get(&(b, a)).copied())
+            else {
+                continue;
+            };
+
+            // Going counter clockwise, p is the vertex after a -> b in the first triangle and q
+            // the one after b -> a in the second one
+            let p = third_vertex(&triangles[first], a, b);
+            let q = third_vertex(&triangles[second], b, a);
+
+            // The quad a, q, b, p is convex when its other diagonal also crosses a -> b
+            let (p_position, q_position) = (vertices[p], vertices[q]);
+            if cross_product(p_position, q_position, vertices[a])
+                * cross_product(p_position, q_position, vertices[b])
+                >= 0.0
+            {
+                crossing_edges.push_back((a, b));
+                flips += 1;
+                continue;
+            }
+
+            triangles[first] = [a, q, p];
+            triangles[second] = [q, b, p];
+            edges.remove(&(a, b));
+            edges.remove(&(b, a));
+            for (edge, triangle) in [
+                ((a, q), first),
+                ((q, p), first),
+                ((p, a), first),
+                ((q, b), second),
+                ((b, p), second),
+                ((p, q), second),
+            ] {
+                edges.insert(edge, triangle);
+            }
+
+            if crosses_constraint((p, q), constraint) {
+                crossing_edges.push_back((p.min(q), p.max(q)));
+            }
+            flips += 1;
+        }
+
+        if !is_edge(&edges, constraint) {
+            missing_constraints.push(constraint);
+        }
+    }
+
+    missing_constraints
+}
+
+fn third_vertex(triangle: &[usize; 3], a: usize, b: usize) -> usize {
+    let edge = (0..3)
+        .find(|edge| triangle[*edge] == a && triangle[(edge + 1) % 3] == b)
+        .expect("The triangle should have the edge");
+    triangle[(edge + 2) % 3]
+}
+
+// Point where the segments a -> b and c -> d cross, if they cross away from their endpoints
+fn segments_crossing(
+    a: (f64, f64),
+    b: (f64, f64),
+    c: (f64, f64),
+    d: (f64, f64),
+) -> Option<(f64, f64)> {
+    let cross_c = cross_product(a, b, c);
+    let cross_d = cross_product(a, b, d);
+    let cross_a = cross_product(c, d, a);
+    let cross_b = cross_product(c, d, b);
+
+    if cross_c * cross_d >= 0.0 || cross_a * cross_b >= 0.0 {
+        return None;
+    }
+
+    let along = cross_a / (cross_a - cross_b);
+    Some((a.0 + (b.0 - a.0) * along, a.1 + (b.1 - a.1) * along))
+}
+
+// How far along the segment the projection of the position is, 0 at its start and 1 at its end
+fn segment_projection(start: (f64, f64), end: (f64, f64), position: (f64, f64)) -> f64 {
+    let segment = (end.0 - start.0, end.1 - start.1);
+    let length_squared = segment.0.powi(2) + segment.1.powi(2);
+    if length_squared == 0.0 {
+        return 0.0;
+    }
+
+    ((position.0 - start.0) * segment.0 + (position.1 - start.1) * segment.1) / length_squared
+}
+
+// Adds three vertices forming a triangle that contains all the others, returning their indices
+fn add_super_triangle_vertices(vertices: &mut Vec<(f64, f64)>) -> [usize; 3] {
+    let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+    let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+
+    for (x, y) in vertices.iter() {
+        min_x = min_x.min(*x);
+        min_y = min_y.min(*y);
+        max_x = max_x.max(*x);
+        max_y = max_y.max(*y);
+    }
+
+    let center = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    let size = (max_x - min_x).max(max_y - min_y).max(1.0) * 20.0;
+
+    let first_index = vertices.len();
+    vertices.push((center.0 - size, center.1 - size));
+    vertices.push((center.0 + size, center.1 - size));
+    vertices.push((center.0, center.1 + size));
+
+    [first_index, first_index + 1, first_index + 2]
+}
+
+// The edges of the hole are the ones that belong to a single invalid triangle,
+// edges shared by two invalid triangles are inside the hole
+fn hole_border_edges(invalid_triangles: &[Triangle]) -> Vec<(usize, usize)> {
+    let edges: Vec<(usize, usize)> = invalid_triangles
+        .iter()
+        .flat_map(|triangle| {
+            let [a, b, c] = triangle.vertices;
+            [(a, b), (b, c), (c, a)]
+        })
+        .collect();
+
+    edges
+        .iter()
+        .filter(|(a, b)| {
+            edges
+                .iter()
+                .filter(|(other_a, other_b)| {
+                    (other_a == a && other_b == b) || (other_a == b && other_b == a)
+                })
+                .count()
+                == 1
+        })
+        .copied()
+        .collect()
+}
+
+fn new_triangle(vertices_indices: [usize; 3], vertices: &[(f64, f64)]) -> Triangle {
+    let [a, b, c] = vertices_indices;
+    let (a_position, b_position, c_position) = (vertices[a], vertices[b], vertices[c]);
+
+    // Keep every triangle counter clockwise
+    let vertices_indices = if cross_product(a_position, b_position, c_position) < 0.0 {
+        [a, c, b]
+    } else {
+        [a, b, c]
+    };
+
+    let (circumcenter, circumradius_squared) = circumcircle(a_position, b_position, c_position);
+
+    Triangle {
+        vertices: vertices_indices,
+        circumcenter,
+        circumradius_squared,
+    }
+}
+
+// Degenerated triangles (the three vertices are aligned) get an infinite circumcircle
+fn circumcircle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> ((f64, f64), f64) {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    if d == 0.0 {
+        return ((0.0, 0.0), f64::INFINITY);
+    }
+
+    let a_squared = a.0.powi(2) + a.1.powi(2);
+    let b_squared = b.0.powi(2) + b.1.powi(2);
+    let c_squared = c.0.powi(2) + c.1.powi(2);
+
+    let center = (
+        (a_squared * (b.1 - c.1) + b_squared * (c.1 - a.1) + c_squared * (a.1 - b.1)) / d,
+        (a_squared * (c.0 - b.0) + b_squared * (a.0 - c.0) + c_squared * (b.0 - a.0)) / d,
+    );
+
+    (center, squared_distance(center, a))
+}
+
+// Same as `Position::cross_product` in f64
+fn cross_product(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn squared_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+     * Lots of small squares on a lattice with a long thin rectangle going between two of their
+     * rows, the lattice edges going from one row to the other cross the rectangle long sides
+     */
+    fn dense_obstacles() -> (Vec<Position>, Vec<(usize, usize)>) {
+        let mut points = Vec::new();
+        let mut constraints = Vec::new();
+        let mut add_square = |corners: [(f32, f32); 4]| {
+            let first = points.len();
+            points.extend(corners.map(|(x, y)| Position { x, y }));
+            constraints.extend((0..4).map(|corner| (first + corner, first + (corner + 1) % 4)));
+        };
+
+        for row in -5..5 {
+            for col in -10..10 {
+                let (x, y) = (col as f32 * 100.0, row as f32 * 100.0);
+                add_square([(x, y), (x + 10.0, y), (x + 10.0, y + 10.0), (x, y + 10.0)]);
+            }
+        }
+        add_square([(-950.0, 45.0), (950.0, 45.0), (950.0, 55.0), (-950.0, 55.0)]);
+
+        (points, constraints)
+    }
+
+    fn is_edge(triangles: &[[usize; 3]], (start, end): (usize, usize)) -> bool {
+        triangles.iter().any(|triangle| {
+            (0..3).any(|edge| {
+                let (a, b) = (triangle[edge], triangle[(edge + 1) % 3]);
+                (a, b) == (start, end) || (a, b) == (end, start)
+            })
+        })
+    }
+
+    #[test]
+    fn every_constraint_is_an_edge() {
+        let (mut points, constraints) = dense_obstacles();
+
+        let triangles = triangulate(&mut points, &constraints).unwrap();
+
+        assert!(constraints
+            .iter()
+            .all(|constraint| is_edge(&triangles, *constraint)));
+    }
+
+    #[test]
+    fn constraints_that_can_not_be_recovered_fail() {
+        let (mut points, constraints) = dense_obstacles();
+
+        let result = triangulate_with_flip_limit(&mut points, &constraints, 1);
+
+        assert!(result.is_err());
+    }
+}
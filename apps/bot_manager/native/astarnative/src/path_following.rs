@@ -0,0 +1,237 @@
+use arena_geometry::{Direction, Position};
+use rustler::NifMap;
+
+use crate::grid::{self, Grid, GRID_CELL_SIZE};
+
+/*
+ * Path following for bots walking a path returned by the pathfinding NIFs.
+ *
+ * Waypoints are considered reached once the bot is within a step of them, so it doesn't overshoot
+ * and walk back. Steering looks ahead along the path and heads straight to the furthest of the
+ * next waypoints it can cut the corners to, as long as there's room around the cut and it doesn't
+ * stray far from the path.
+ *
+ * The follower is stateless, callers keep the path and the waypoint index between ticks.
+ */
+
+// Waypoints closer than this count as reached even if the bot moves less per tick
+const MIN_ARRIVAL_DISTANCE: f32 = GRID_CELL_SIZE / 2.0;
+// How many waypoints ahead of the current one are checked to cut corners
+const LOOKAHEAD_WAYPOINTS: usize = 3;
+// Bots further away than this from the path need a new one
+const MAX_DEVIATION: f32 = GRID_CELL_SIZE * 2.0;
+// Minimum distance between a corner cut and any obstacle
+const CORNER_CUT_CLEARANCE: f32 = GRID_CELL_SIZE / 10.0;
+
+#[derive(NifMap)]
+pub struct PathFollowing {
+    // Unit direction to move in, zero once the end of the path is reached
+    pub(crate) direction: Direction,
+    // Waypoint the bot is heading to, equal to the path length once it's finished
+    pub(crate) waypoint_index: usize,
+    // The bot strayed too far from the path or something blocks the way to the waypoint
+    pub(crate) needs_replan: bool,
+}
+
+pub(crate) fn follow_path(
+    path: &[Position],
+    waypoint_index: usize,
+    position: &Position,
+    step: f32,
+    grid: &Grid,
+) -> PathFollowing {
+    let arrival_distance = step.max(MIN_ARRIVAL_DISTANCE);
+
+    let mut waypoint_index = waypoint_index.min(path.len());
+    while waypoint_index < path.len()
+        && position.distance_to_position(&path[waypoint_index]) <= arrival_distance
+    {
+        waypoint_index += 1;
+    }
+
+    if waypoint_index == path.len() {
+        return PathFollowing {
+            direction: Direction { x: 0.0, y: 0.0 },
+            waypoint_index,
+            needs_replan: false,
+        };
+    }
+
+    // Bots pushed into an obstacle cell look from the center of the closest free one
+    let cell = grid::world_to_grid(position);
+    let origin = if grid.is_blocked(cell) {
+        match grid.nearest_free_cell(cell) {
+            Some(free_cell) => grid::grid_to_world_center(&free_cell),
+            None => return replan(waypoint_index),
+        }
+    } else {
+        *position
+    };
+    // Paths can go diagonally past an obstacle corner, so their segments are allowed to touch one
+    let segment_start = if waypoint_index > 0 {
+        path[waypoint_index - 1]
+    } else {
+        origin
+    };
+    if !grid::is_segment_free(&segment_start, &path[waypoint_index], grid, true) {
+        return replan(waypoint_index);
+    }
+
+    // The bot may be cutting the corners of the last few waypoints, so it only strayed if it's far
+    // from all of the segments it could be skipping
+    if waypoint_index > 0 {
+        let deviation = (waypoint_index.saturating_sub(LOOKAHEAD_WAYPOINTS).max(1)
+            ..=waypoint_index)
+            .map(|index| distance_to_segment(position, &path[index - 1], &path[index]))
+            .fold(f32::INFINITY, f32::min);
+        if deviation > MAX_DEVIATION {
+            return replan(waypoint_index);
+        }
+    }
+
+    // Corners are only cut when the skipped waypoints stay close to the cut, so the bot doesn't
+    // take a shortcut far from the path that would count as straying from it
+    let first_skipped_index = waypoint_index;
+    let last_lookahead_index = (waypoint_index + LOOKAHEAD_WAYPOINTS).min(path.len() - 1);
+    while waypoint_index < last_lookahead_index {
+        let next_waypoint = &path[waypoint_index + 1];
+        let skips_far_waypoint =
+            path[first_skipped_index..=waypoint_index]
+                .iter()
+                .any(|waypoint| {
+                    distance_to_segment(waypoint, &origin, next_waypoint) > MAX_DEVIATION / 2.0
+                });
+        if skips_far_waypoint || !is_corridor_free(&origin, next_waypoint, grid) {
+            break;
+        }
+
+        waypoint_index += 1;
+    }
+
+    let mut direction = Position::sub(&path[waypoint_index], position);
+    direction.normalize();
+
+    PathFollowing {
+        direction: Direction {
+            x: direction.x,
+            y: direction.y,
+        },
+        waypoint_index,
+        needs_replan: false,
+    }
+}
+
+fn replan(waypoint_index: usize) -> PathFollowing {
+    PathFollowing {
+        direction: Direction { x: 0.0, y: 0.0 },
+        waypoint_index,
+        needs_replan: true,
+    }
+}
+
+// Corner cuts need room on both sides of the segment, otherwise the bot could drift into the corner
+fn is_corridor_free(from: &Position, to: &Position, grid: &Grid) -> bool {
+    let mut normal = Position::sub(to, from);
+    if normal.x == 0.0 && normal.y == 0.0 {
+        return grid::is_segment_free(from, to, grid, false);
+    }
+    normal.normalize();
+    let offset = Position {
+        x: -normal.y * CORNER_CUT_CLEARANCE,
+        y: normal.x * CORNER_CUT_CLEARANCE,
+    };
+
+    grid::is_segment_free(from, to, grid, false)
+        && grid::is_segment_free(
+            &Position::add(from, &offset),
+            &Position::add(to, &offset),
+            grid,
+            false,
+        )
+        && grid::is_segment_free(
+            &Position::sub(from, &offset),
+            &Position::sub(to, &offset),
+            grid,
+            false,
+        )
+}
+
+fn distance_to_segment(position: &Position, from: &Position, to: &Position) -> f32 {
+    let segment = Position::sub(to, from);
+    let length_squared = segment.x * segment.x + segment.y * segment.y;
+    if length_squared == 0.0 {
+        return position.distance_to_position(from);
+    }
+
+    let to_position = Position::sub(position, from);
+    let t =
+        ((to_position.x * segment.x + to_position.y * segment.y) / length_squared).clamp(0.0, 1.0);
+
+    position.distance_to_position(&Position::add(from, &Position::mult(&segment, t)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{NUM_COLS, NUM_ROWS};
+
+    // Two straight legs with a turn in the middle
+    fn l_shaped_path() -> Vec<Position> {
+        [(100, 100), (100, 110), (110, 110)]
+            .iter()
+            .map(grid::grid_to_world_center)
+            .collect()
+    }
+
+    fn open_grid() -> Grid {
+        Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize])
+    }
+
+    fn assert_heading_to(following: &PathFollowing, position: &Position, waypoint: &Position) {
+        let mut expected = Position::sub(waypoint, position);
+        expected.normalize();
+
+        assert!((following.direction.x - expected.x).abs() < 1e-4);
+        assert!((following.direction.y - expected.y).abs() < 1e-4);
+        assert!(!following.needs_replan);
+    }
+
+    #[test]
+    fn reached_waypoints_are_advanced_past() {
+        let path = l_shaped_path();
+        let grid = open_grid();
+
+        let following = follow_path(&path, 0, &path[0], 10.0, &grid);
+        assert_eq!(following.waypoint_index, 1);
+        assert_heading_to(&following, &path[0], &path[1]);
+
+        // Within a step of the turn it already heads down the second leg
+        let mut near_turn = Position::sub(&path[1], &path[0]);
+        near_turn.normalize();
+        let near_turn = Position::sub(&path[1], &Position::mult(&near_turn, 40.0));
+        let following = follow_path(&path, 1, &near_turn, 50.0, &grid);
+        assert_eq!(following.waypoint_index, 2);
+        assert_heading_to(&following, &near_turn, &path[2]);
+    }
+
+    #[test]
+    fn overshot_waypoints_are_not_walked_back_to() {
+        let path = l_shaped_path();
+        let grid = open_grid();
+
+        // A long step carried the bot past the turn and into the second leg
+        let mut second_leg = Position::sub(&path[2], &path[1]);
+        second_leg.normalize();
+        let past_turn = Position::add(&path[1], &Position::mult(&second_leg, 150.0));
+        let following = follow_path(&path, 1, &past_turn, 200.0, &grid);
+        assert_eq!(following.waypoint_index, 2);
+        assert_heading_to(&following, &past_turn, &path[2]);
+
+        // Overshooting the last waypoint finishes the path instead of turning around
+        let past_end = Position::add(&path[2], &Position::mult(&second_leg, 60.0));
+        let following = follow_path(&path, 2, &past_end, 10.0, &grid);
+        assert_eq!(following.waypoint_index, path.len());
+        assert_eq!((following.direction.x, following.direction.y), (0.0, 0.0));
+        assert!(!following.needs_replan);
+    }
+}
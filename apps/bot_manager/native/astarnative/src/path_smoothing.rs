@@ -0,0 +1,148 @@
+use crate::cost_layers::CellCosts;
+use crate::grid::Grid;
+
+// Shortcuts that cost the same as the path they replace are still taken despite float rounding
+const COST_TOLERANCE: f32 = 1e-3;
+
+/*
+ * Path smoothing by string pulling
+ *
+ * A* over the grid returns the center of every cell it goes through, which draws a staircase
+ * with a waypoint per cell. To get rid of it we walk the path keeping an anchor waypoint and
+ * jump to the farthest waypoint that can be reached from it in a straight line without going
+ * through a blocked cell. The result only keeps the waypoints where the path actually turns.
+ *
+ * With cost layers a straight line isn't always better, cutting a corner through a pool may be
+ * shorter but more expensive than the path that went around it. So a shortcut is only taken if
+ * walking it doesn't cost more than walking the waypoints it skips.
+ */
+
+/*
+ * Removes every waypoint that can be skipped by walking in a straight line from a previous one.
+ * The first and last waypoints of the path are always kept.
+ */
+pub(crate) fn smooth_path(
+    path: &[(i64, i64)],
+    grid: &Grid,
+    cell_costs: &CellCosts,
+) -> Vec<(i64, i64)> {
+    if path.len() <= 2 {
+        return path.to_vec();
+    }
+
+    // Cost of walking the path from its start up to each waypoint
+    let mut path_costs = vec![0.0; path.len()];
+    for index in 1..path.len() {
+        path_costs[index] = path_costs[index - 1]
+            + distance(path[index - 1], path[index]) * cell_costs.cost(grid.index(path[index]));
+    }
+
+    let mut smoothed_path = vec![path[0]];
+    let mut anchor = 0;
+
+    while anchor < path.len() - 1 {
+        // Adjacent waypoints are always reachable since A* moved between them, so we
+        // fall back to the next one if no farther waypoint is visible
+        let next = (anchor + 2..path.len())
+            .rev()
+            .find(|&candidate| {
+                shortcut_cost(path[anchor], path[candidate], grid, cell_costs).is_some_and(|cost| {
+                    cost <= path_costs[candidate] - path_costs[anchor] + COST_TOLERANCE
+                })
+            })
+            .unwrap_or(anchor + 1);
+
+        smoothed_path.push(path[next]);
+        anchor = next;
+    }
+
+    smoothed_path
+}
+
+// Cost of walking straight between two cells, None if there's no line of sight between them
+fn shortcut_cost(
+    from: (i64, i64),
+    to: (i64, i64),
+    grid: &Grid,
+    cell_costs: &CellCosts,
+) -> Option<f32> {
+    let mut crossed_cells_cost = 0.0;
+    let mut crossed_cells = 0;
+
+    let is_visible = traverse_line(from, to, |cell| {
+        if cell != from {
+            crossed_cells_cost += cell_costs.cost(grid.index(cell));
+            crossed_cells += 1;
+        }
+        !grid.is_blocked(cell)
+    });
+
+    (is_visible && crossed_cells > 0)
+        .then(|| distance(from, to) * crossed_cells_cost / crossed_cells as f32)
+}
+
+fn distance(from: (i64, i64), to: (i64, i64)) -> f32 {
+    ((from.0 - to.0) as f32).hypot((from.1 - to.1) as f32)
+}
+
+/*
+ * Determines if the segment between the centers of two cells only goes through free cells
+ * We traverse every cell touched by the segment (supercover traversal), when the segment
+ * goes exactly through a corner both cells sharing that corner have to be free, so we don't
+ * let bots squeeze diagonally between two obstacles
+ */
+pub(crate) fn has_line_of_sight(from: (i64, i64), to: (i64, i64), grid: &Grid) -> bool {
+    traverse_line(from, to, |cell| !grid.is_blocked(cell))
+}
+
+// Visits every cell touched by the segment in order while `visit` returns true,
+// returns false if the traversal was stopped before reaching the end
+fn traverse_line(
+    from: (i64, i64),
+    to: (i64, i64),
+    mut visit: impl FnMut((i64, i64)) -> bool,
+) -> bool {
+    let (mut row, mut col) = from;
+
+    let rows_to_cross = to.0.abs_diff(from.0) as i64;
+    let cols_to_cross = to.1.abs_diff(from.1) as i64;
+    let row_step = (to.0 - from.0).signum();
+    let col_step = (to.1 - from.1).signum();
+
+    let mut crossed_rows = 0;
+    let mut crossed_cols = 0;
+
+    if !visit((row, col)) {
+        return false;
+    }
+
+    while crossed_rows < rows_to_cross || crossed_cols < cols_to_cross {
+        // Starting from a cell center, the segment reaches the next row border at
+        // (0.5 + crossed_rows) / rows_to_cross of its length and the next column border at
+        // (0.5 + crossed_cols) / cols_to_cross, we compare both multiplied out to stay on integers
+        let next_border =
+            (1 + 2 * crossed_rows) * cols_to_cross - (1 + 2 * crossed_cols) * rows_to_cross;
+
+        if next_border == 0 {
+            if !visit((row + row_step, col)) || !visit((row, col + col_step)) {
+                return false;
+            }
+            row += row_step;
+            col += col_step;
+            crossed_rows += 1;
+            crossed_cols += 1;
+        } else if next_border < 0 {
+            row += row_step;
+            crossed_rows += 1;
+        } else {
+            col += col_step;
+            crossed_cols += 1;
+        }
+
+        if !visit((row, col)) {
+            return false;
+        }
+    }
+
+    true
+}
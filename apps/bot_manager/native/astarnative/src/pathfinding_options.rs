@@ -0,0 +1,84 @@
+use rustler::{Decoder, MapIterator, NifResult, NifUnitEnum, Term};
+
+use arena_geometry::Category;
+
+mod atoms {
+    rustler::atoms! {
+        mode,
+        cost_weights,
+        max_expansions,
+    }
+}
+
+#[derive(NifUnitEnum, Clone, Copy, PartialEq)]
+pub enum PathfindingMode {
+    // Moves between neighbor cells, paths are smoothed afterwards
+    AStar,
+    // Any-angle search, a node's parent can be any visible ancestor
+    ThetaStar,
+    // Jump point search, much faster than A* on open grids, paths are smoothed afterwards
+    JumpPointSearch,
+    // Searches the precomputed cluster graph of the grid and refines it, the fastest on long paths
+    Hierarchical,
+}
+
+/*
+ * Options accepted by the pathfinding NIFs as an Elixir map, e.g. `%{mode: :theta_star}`
+ * Every key is optional so callers only need to send the ones they want to change
+ *
+ * `cost_weights` maps categories to the extra cost of walking through cells of their cost layer,
+ * e.g. `%{pool: 4.0, bush: -0.5}`. Only the `:a_star` mode takes them into account.
+ *
+ * `max_expansions` bounds how many nodes a search can expand, once spent the path to the
+ * closest node to the goal is returned as a partial path. There's no limit by default.
+ */
+#[derive(Clone)]
+pub struct PathfindingOptions {
+    pub mode: PathfindingMode,
+    pub cost_weights: Vec<(Category, f32)>,
+    pub max_expansions: Option<usize>,
+}
+
+impl Default for PathfindingOptions {
+    fn default() -> Self {
+        PathfindingOptions {
+            mode: PathfindingMode::AStar,
+            cost_weights: Vec::new(),
+            max_expansions: None,
+        }
+    }
+}
+
+impl<'a> Decoder<'a> for PathfindingOptions {
+    fn decode(term: Term<'a>) -> NifResult<Self> {
+        let mut options = PathfindingOptions::default();
+
+        if let Ok(mode) = term.map_get(atoms::mode()) {
+            options.mode = mode.decode()?;
+        }
+
+        if let Ok(cost_weights) = term.map_get(atoms::cost_weights()) {
+            for (category, weight) in
+                MapIterator::new(cost_weights).ok_or(rustler::Error::BadArg)?
+            {
+                options
+                    .cost_weights
+                    .push((category.decode()?, decode_number(weight)?));
+            }
+        }
+
+        if let Ok(max_expansions) = term.map_get(atoms::max_expansions()) {
+            options.max_expansions = Some(max_expansions.decode()?);
+        }
+
+        Ok(options)
+    }
+}
+
+// Elixir callers shouldn't need to care about writing 4 or 4.0
+fn decode_number(term: Term) -> NifResult<f32> {
+    match term.decode::<f64>() {
+        Ok(number) => Ok(number as f32),
+        Err(_) => Ok(term.decode::<i64>()? as f32),
+    }
+}
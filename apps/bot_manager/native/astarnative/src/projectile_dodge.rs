@@ -0,0 +1,248 @@
+use std::f32::consts::TAU;
+
+use arena_geometry::collision_detection::line_circle_collision;
+use arena_geometry::{Direction, Entity, Position, Shape};
+use rustler::NifMap;
+
+use crate::grid::{self, Grid};
+
+/*
+ * Projectiles are assumed to keep moving in a straight line. The ones whose trajectory within the
+ * time horizon goes through the bot (grown by the projectile radius) will hit it if it stays still.
+ *
+ * To dodge them, the bot tries moving sideways to each incoming projectile and then in evenly
+ * spread directions, and picks the one that keeps it furthest away from every projectile during
+ * the time horizon. Directions that run into an obstacle or leave the map are discarded.
+ */
+
+// Directions tried when moving sideways to the projectiles isn't enough
+const ESCAPE_DIRECTIONS: usize = 16;
+
+#[derive(NifMap)]
+pub struct ProjectileDodge {
+    // Ids of the projectiles that will hit the bot if it doesn't move
+    pub(crate) threats: Vec<u64>,
+    // Unit direction to move in, nil if there's nothing to dodge or no way to move
+    pub(crate) direction: Option<Direction>,
+}
+
+pub(crate) fn dodge_projectiles(
+    bot: &Entity,
+    projectiles: &[Entity],
+    time_horizon: f32,
+    grid: &Grid,
+    external_wall: &Entity,
+) -> ProjectileDodge {
+    let threats: Vec<&Entity> = projectiles
+        .iter()
+        .filter(|projectile| will_hit(bot, projectile, time_horizon))
+        .collect();
+
+    if threats.is_empty() {
+        return ProjectileDodge {
+            threats: Vec::new(),
+            direction: None,
+        };
+    }
+
+    let lateral_directions = threats.iter().flat_map(|threat| {
+        let mut direction = Position {
+            x: -threat.direction.y,
+            y: threat.direction.x,
+        };
+        direction.normalize();
+        [direction, Position::mult(&direction, -1.0)]
+    });
+    let spread_directions = (0..ESCAPE_DIRECTIONS).map(|index| {
+        let angle = index as f32 * TAU / ESCAPE_DIRECTIONS as f32;
+        Position {
+            x: angle.cos(),
+            y: angle.sin(),
+        }
+    });
+
+    // Lateral directions go first so they win ties
+    let mut best: Option<(f32, Position)> = None;
+    for direction in lateral_directions.chain(spread_directions) {
+        if direction.x.is_nan() || direction.y.is_nan() {
+            continue;
+        }
+
+        let destination = Position::add(
+            &bot.position,
+            &Position::mult(&direction, bot.speed * time_horizon),
+        );
+        if !is_inside_map(&destination, bot.radius, external_wall)
+            || !grid::is_segment_free(&bot.position, &destination, grid, false)
+        {
+            continue;
+        }
+
+        let clearance = projectiles
+            .iter()
+            .map(|projectile| clearance(bot, &direction, projectile, time_horizon))
+            .fold(f32::INFINITY, f32::min);
+
+        if best.is_none_or(|(best_clearance, _)| clearance > best_clearance) {
+            best = Some((clearance, direction));
+        }
+    }
+
+    ProjectileDodge {
+        threats: threats.iter().map(|threat| threat.id).collect(),
+        direction: best.map(|(_, direction)| Direction {
+            x: direction.x,
+            y: direction.y,
+        }),
+    }
+}
+
+fn will_hit(bot: &Entity, projectile: &Entity, time_horizon: f32) -> bool {
+    let trajectory = Entity::new_line(
+        projectile.id,
+        vec![projectile.position, projectile.next_position(time_horizon)],
+    );
+
+    let mut hit_area = bot.clone();
+    hit_area.shape = Shape::Circle;
+    hit_area.radius += projectile.radius;
+
+    line_circle_collision(&trajectory, &hit_area)
+}
+
+// Smallest gap between the bot and the projectile during the time horizon if the bot moves in the
+// direction at full speed, negative if they overlap
+fn clearance(bot: &Entity, direction: &Position, projectile: &Entity, time_horizon: f32) -> f32 {
+    let relative_position = Position::sub(&projectile.position, &bot.position);
+    let relative_velocity = Position {
+        x: projectile.direction.x * projectile.speed - direction.x * bot.speed,
+        y: projectile.direction.y * projectile.speed - direction.y * bot.speed,
+    };
+
+    let speed_squared = relative_velocity.x.powi(2) + relative_velocity.y.powi(2);
+    let closest_time = if speed_squared > 0.0 {
+        (-(relative_position.x * relative_velocity.x + relative_position.y * relative_velocity.y)
+            / speed_squared)
+            .clamp(0.0, time_horizon)
+    } else {
+        0.0
+    };
+
+    let closest_position = Position::add(
+        &relative_position,
+        &Position::mult(&relative_velocity, closest_time),
+    );
+
+    closest_position.distance_to_position(&Position { x: 0.0, y: 0.0 })
+        - bot.radius
+        - projectile.radius
+}
+
+fn is_inside_map(position: &Position, radius: f32, external_wall: &Entity) -> bool {
+    position.distance_to_position(&external_wall.position) + radius < external_wall.radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arena_geometry::Category;
+
+    use crate::grid::{NUM_COLS, NUM_ROWS, WORLD_RADIUS};
+
+    const TIME_HORIZON: f32 = 1000.0;
+
+    fn circle(id: u64, position: Position, radius: f32) -> Entity {
+        Entity {
+            shape: Shape::Circle,
+            radius,
+            ..Entity::new_point(id, position)
+        }
+    }
+
+    fn bot(position: Position) -> Entity {
+        Entity {
+            speed: 0.5,
+            category: Category::Player,
+            ..circle(1, position, 50.0)
+        }
+    }
+
+    // Projectile moving right that starts `distance` units to the left of the target
+    fn projectile_towards(target: &Position, distance: f32, lateral_offset: f32) -> Entity {
+        let position = Position {
+            x: target.x - distance,
+            y: target.y + lateral_offset,
+        };
+        Entity {
+            speed: 3.0,
+            direction: Direction { x: 1.0, y: 0.0 },
+            is_moving: true,
+            category: Category::Projectile,
+            ..circle(2, position, 20.0)
+        }
+    }
+
+    fn external_wall() -> Entity {
+        circle(0, Position { x: 0.0, y: 0.0 }, WORLD_RADIUS)
+    }
+
+    fn dodge(bot: &Entity, projectiles: &[Entity]) -> ProjectileDodge {
+        let grid = Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize]);
+        dodge_projectiles(bot, projectiles, TIME_HORIZON, &grid, &external_wall())
+    }
+
+    #[test]
+    fn projectiles_reaching_the_bot_within_the_horizon_are_dodged() {
+        let bot = bot(Position { x: 0.0, y: 0.0 });
+        let projectile = projectile_towards(&bot.position, 2000.0, 30.0);
+
+        let dodge = dodge(&bot, std::slice::from_ref(&projectile));
+        assert_eq!(dodge.threats, vec![projectile.id]);
+
+        let direction = dodge.direction.expect("There's room to dodge");
+        let direction = Position {
+            x: direction.x,
+            y: direction.y,
+        };
+        assert!(clearance(&bot, &direction, &projectile, TIME_HORIZON) > 0.0);
+    }
+
+    #[test]
+    fn projectiles_missing_the_bot_within_the_horizon_are_ignored() {
+        let bot = bot(Position { x: 0.0, y: 0.0 });
+        let passing_by = projectile_towards(&bot.position, 2000.0, 100.0);
+        // Heading straight to the bot, but it's still on its way when the horizon ends
+        let too_far = projectile_towards(&bot.position, 4000.0, 0.0);
+
+        let dodge = dodge(&bot, &[passing_by, too_far]);
+        assert!(dodge.threats.is_empty());
+        assert!(dodge.direction.is_none());
+    }
+
+    #[test]
+    fn escape_directions_leaving_the_map_are_discarded() {
+        // Right under the top of the external wall, the dodge can't go up
+        let position = Position {
+            x: 0.0,
+            y: WORLD_RADIUS - 300.0,
+        };
+        let bot = bot(position);
+        let projectile = projectile_towards(&position, 2000.0, 0.0);
+
+        let dodge = dodge(&bot, &[projectile]);
+        let direction = dodge.direction.expect("There's room to dodge downwards");
+        let destination = Position::add(
+            &position,
+            &Position::mult(
+                &Position {
+                    x: direction.x,
+                    y: direction.y,
+                },
+                bot.speed * TIME_HORIZON,
+            ),
+        );
+
+        assert!(direction.y < 0.0);
+        assert!(is_inside_map(&destination, bot.radius, &external_wall()));
+    }
+}
@@ -0,0 +1,254 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::grid::Grid;
+use crate::path_smoothing::has_line_of_sight;
+use crate::AStarPathResult;
+
+/*
+ * Lazy Theta* any-angle search
+ *
+ * It works like A* but a node's parent isn't restricted to be one of its neighbors, it can be
+ * any ancestor it can see in a straight line. When we reach a new node we optimistically assume
+ * it can see the parent of the node we came from and only verify that line of sight once the node
+ * is expanded (that's the lazy part, we avoid most of the line of sight checks).
+ * If the assumption doesn't hold we fall back to the best already expanded neighbor as parent.
+ *
+ * Moves between neighbors can't cut blocked corners, same as the line of sight checks.
+ *
+ * The resulting path only has the nodes where it turns, so it doesn't need smoothing.
+ *
+ * - [Lazy Theta* paper](http://idm-lab.org/bib/abstracts/papers/aaai10b.pdf)
+ */
+
+// For each cell of the grid, the cost to reach it and its parent
+struct ReachedNodes {
+    costs: Vec<f32>,
+    parents: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct NodeEntry {
+    node: usize,
+    estimate_reach_cost: f32,
+}
+
+impl Eq for NodeEntry {}
+
+impl Ord for NodeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate_reach_cost
+            .total_cmp(&self.estimate_reach_cost)
+    }
+}
+
+impl PartialOrd for NodeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub(crate) fn theta_star_find_path(
+    start: (i64, i64),
+    goal: (i64, i64),
+    grid: &Grid,
+    max_expansions: usize,
+) -> AStarPathResult {
+    let mut reached = ReachedNodes {
+        costs: vec![f32::INFINITY; grid.len()],
+        parents: vec![usize::MAX; grid.len()],
+    };
+    let mut expanded: Vec<bool> = vec![false; grid.len()];
+
+    let start_index = grid.index(start);
+    reached.costs[start_index] = 0.0;
+    reached.parents[start_index] = start_index;
+    let mut closest_node = start_index;
+    let mut expansions = 0;
+
+    let mut priority_queue = BinaryHeap::new();
+    priority_queue.push(NodeEntry {
+        node: start_index,
+        estimate_reach_cost: distance(start, goal),
+    });
+
+    while let Some(NodeEntry { node, .. }) = priority_queue.pop() {
+        if expanded[node] {
+            continue;
+        }
+        expanded[node] = true;
+
+        let cell = grid.cell(node);
+        if !has_line_of_sight(grid.cell(reached.parents[node]), cell, grid) {
+            update_parent_from_expanded_neighbors(node, grid, &expanded, &mut reached);
+        }
+
+        if cell == goal {
+            return AStarPathResult::Found(build_path(start, goal, grid, &reached));
+        }
+
+        // Remember the expanded node closest to the goal in case the goal can't be reached
+        if distance(cell, goal) < distance(grid.cell(closest_node), goal) {
+            closest_node = node;
+        }
+
+        expansions += 1;
+        if expansions >= max_expansions {
+            break;
+        }
+
+        let parent = reached.parents[node];
+        let parent_cell = grid.cell(parent);
+        let parent_cost = reached.costs[parent];
+
+        for neigh in grid.neighbors(cell) {
+            let neigh_index = grid.index(neigh);
+            if expanded[neigh_index] {
+                continue;
+            }
+
+            // Assume the neighbor can see our parent, it will be checked once it is expanded
+            let neigh_cost = parent_cost + distance(parent_cell, neigh);
+
+            if neigh_cost < reached.costs[neigh_index] {
+                reached.costs[neigh_index] = neigh_cost;
+                reached.parents[neigh_index] = parent;
+                priority_queue.push(NodeEntry {
+                    node: neigh_index,
+                    estimate_reach_cost: neigh_cost + distance(neigh, goal),
+                });
+            }
+        }
+    }
+
+    AStarPathResult::Partial(build_path(start, grid.cell(closest_node), grid, &reached))
+}
+
+// The lazy assumption failed, so the node parent becomes the expanded neighbor
+// that gives the cheapest path to it
+fn update_parent_from_expanded_neighbors(
+    node: usize,
+    grid: &Grid,
+    expanded: &[bool],
+    reached: &mut ReachedNodes,
+) {
+    let cell = grid.cell(node);
+    let best_neighbor = grid
+        .neighbors(cell)
+        .into_iter()
+        .map(|neigh| grid.index(neigh))
+        .filter(|neigh_index| expanded[*neigh_index])
+        .map(|neigh_index| {
+            (
+                reached.costs[neigh_index] + distance(grid.cell(neigh_index), cell),
+                neigh_index,
+            )
+        })
+        .min_by(|(cost_a, _), (cost_b, _)| cost_a.total_cmp(cost_b));
+
+    if let Some((cost, neigh_index)) = best_neighbor {
+        reached.costs[node] = cost;
+        reached.parents[node] = neigh_index;
+    }
+}
+
+fn build_path(
+    start: (i64, i64),
+    goal: (i64, i64),
+    grid: &Grid,
+    reached: &ReachedNodes,
+) -> Vec<(i64, i64)> {
+    let mut current = grid.index(goal);
+    let mut path = vec![goal];
+
+    while current != grid.index(start) {
+        current = reached.parents[current];
+        path.push(grid.cell(current));
+    }
+
+    path.reverse();
+
+    path
+}
+
+fn distance(from: (i64, i64), to: (i64, i64)) -> f32 {
+    let rows = (from.0 - to.0) as f32;
+    let cols = (from.1 - to.1) as f32;
+    (rows.powi(2) + cols.powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star::a_star_find_path;
+    use crate::cost_layers::CellCosts;
+    use crate::grid::{NUM_COLS, NUM_ROWS};
+    use crate::path_smoothing::smooth_path;
+
+    const START: (i64, i64) = (60, 60);
+    const GOAL: (i64, i64) = (140, 150);
+
+    // Two walls to go around and a block in the middle, so the path has to turn a few times
+    fn grid_with_walls() -> Grid {
+        let mut grid = Grid::new(vec![false; (NUM_ROWS * NUM_COLS) as usize]);
+        for row in 40..110 {
+            grid.set_blocked((row, 90), true);
+        }
+        for row in 90..170 {
+            grid.set_blocked((row, 120), true);
+        }
+        for row in 100..106 {
+            for col in 60..80 {
+                grid.set_blocked((row, col), true);
+            }
+        }
+        grid
+    }
+
+    fn path_length(path: &[(i64, i64)]) -> f32 {
+        path.windows(2)
+            .map(|pair| {
+                (((pair[1].0 - pair[0].0).pow(2) + (pair[1].1 - pair[0].1).pow(2)) as f32).sqrt()
+            })
+            .sum()
+    }
+
+    fn found_path(path_result: AStarPathResult) -> Vec<(i64, i64)> {
+        match path_result {
+            AStarPathResult::Found(path) => path,
+            _ => panic!("The goal should be reachable"),
+        }
+    }
+
+    #[test]
+    fn any_angle_path_is_not_longer_than_smoothed_a_star() {
+        let grid = grid_with_walls();
+        let a_star_path = found_path(a_star_find_path(
+            START,
+            GOAL,
+            &grid,
+            &CellCosts::default(),
+            usize::MAX,
+        ));
+        let smoothed_path = smooth_path(&a_star_path, &grid, &CellCosts::default());
+
+        let path = found_path(theta_star_find_path(START, GOAL, &grid, usize::MAX));
+
+        assert_eq!(path.first(), Some(&START));
+        assert_eq!(path.last(), Some(&GOAL));
+        assert!(path_length(&path) <= path_length(&smoothed_path) + 1e-3);
+    }
+
+    #[test]
+    fn any_angle_path_never_crosses_a_blocked_cell() {
+        let grid = grid_with_walls();
+
+        let path = found_path(theta_star_find_path(START, GOAL, &grid, usize::MAX));
+
+        assert!(path.len() > 2);
+        for pair in path.windows(2) {
+            assert!(has_line_of_sight(pair[0], pair[1], &grid));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+/*
+Worker pool
+
+Threads shared by every batch of path queries. They're started on the first parallel batch and live
+as long as the NIF library, so batches don't pay for spawning threads and concurrent batches queue
+their work instead of starting more threads.
+
+The BEAM already runs a scheduler per core, the pool only takes half of them (up to MAX_WORKERS)
+so the searches don't starve the rest of the VM.
+*/
+
+const MAX_WORKERS: usize = 8;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+struct WorkerPool {
+    jobs: Mutex<Sender<Job>>,
+    size: usize,
+}
+
+static POOL: OnceLock<WorkerPool> = OnceLock::new();
+
+fn pool() -> &'static WorkerPool {
+    POOL.get_or_init(|| {
+        let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+        let size = (cores / 2).clamp(1, MAX_WORKERS);
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for worker in 0..size {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("astar_worker_{}", worker))
+                .spawn(move || work(&receiver))
+                .expect("Couldn't start a path query worker");
+        }
+
+        WorkerPool {
+            jobs: Mutex::new(sender),
+            size,
+        }
+    })
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is released as soon as a job is taken, so the rest of workers can take more
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        // A panicking job drops its results sender, which its caller notices. The worker stays
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+// Applies the function to every item in the pool, the results are in the same order as the items
+pub(crate) fn map<T, R, F>(items: Vec<T>, function: F) -> Vec<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let pool = pool();
+    let chunk_size = items.len().div_ceil(pool.size).max(1);
+    let function = Arc::new(function);
+    let (results_sender, results_receiver) = mpsc::channel();
+
+    let mut items = items.into_iter();
+    let mut chunks = 0;
+    loop {
+        let chunk: Vec<T> = items.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            break;
+        }
+
+        let function = Arc::clone(&function);
+        let results_sender = results_sender.clone();
+        let chunk_index = chunks;
+        let job: Job = Box::new(move || {
+            let results: Vec<R> = chunk.into_iter().map(|item| function(item)).collect();
+            let _ = results_sender.send((chunk_index, results));
+        });
+        pool.jobs
+            .lock()
+            .unwrap()
+            .send(job)
+            .expect("Path query workers stopped");
+        chunks += 1;
+    }
+    drop(results_sender);
+
+    let mut chunk_results: Vec<(usize, Vec<R>)> = results_receiver.iter().collect();
+    assert_eq!(chunk_results.len(), chunks, "Path query worker panicked");
+
+    chunk_results.sort_by_key(|(chunk_index, _)| *chunk_index);
+    chunk_results
+        .into_iter()
+        .flat_map(|(_, results)| results)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_keep_the_order_of_the_items() {
+        let items: Vec<u32> = (0..1000).collect();
+        let results = map(items, |item| item * 2);
+
+        assert_eq!(
+            results,
+            (0..1000).map(|item| item * 2).collect::<Vec<u32>>()
+        );
+    }
+
+    #[test]
+    fn workers_survive_a_panicking_batch() {
+        let panicked = panic::catch_unwind(|| map(vec![1, 2, 3], |_: u32| -> u32 { panic!() }));
+        assert!(panicked.is_err());
+
+        assert_eq!(map(vec![1, 2, 3], |item: u32| item + 1), vec![2, 3, 4]);
+    }
+}
@@ -1,16 +1,24 @@
-use crate::map::{Entity, Position};
+use crate::entity::Entity;
+use crate::position::Position;
+
 pub mod ear_clipping;
+pub mod oriented_box;
 pub mod sat;
 /*
  * Determines if a collision has occured between a point and a circle
  * If the distance between the point and the center of the circle is less
  * than the radius of the circle, a collision has occured
  */
-pub(crate) fn point_circle_collision(point: &Entity, circle: &Entity) -> bool {
+pub fn point_circle_collision(point: &Entity, circle: &Entity) -> bool {
     let distance = calculate_distance(&point.position, &circle.position);
     distance <= circle.radius
 }
-pub(crate) fn point_polygon_collision(point: &Entity, polygon: &Entity) -> bool {
+
+/*
+ * Determines if a point is inside a polygon by casting a horizontal ray from the point
+ * and counting how many polygon edges it crosses, an odd count means it's inside
+ */
+pub fn point_polygon_collision(point: &Entity, polygon: &Entity) -> bool {
     let mut collision = false;
     for current in 0..polygon.vertices.len() {
         let mut next = current + 1;
@@ -41,7 +49,7 @@ pub(crate) fn point_polygon_collision(point: &Entity, polygon: &Entity) -> bool
  * of the line is less than the radius of the circle, a collision has occured
  * Also that closest point should be on the segment
  */
-pub(crate) fn line_circle_collision(line: &Entity, circle: &Entity) -> bool {
+pub fn line_circle_collision(line: &Entity, circle: &Entity) -> bool {
     // Check if the vertices are inside the circle
     let point_1 = Entity::new_point(0, line.vertices[0]);
     let inside_1 = point_circle_collision(&point_1, circle);
@@ -70,7 +78,7 @@ pub(crate) fn line_circle_collision(line: &Entity, circle: &Entity) -> bool {
     );
 
     // Check if the closest point is on the line
-    let on_line = line_point_colision(line, &closest_point);
+    let on_line = line_point_collision(line, &closest_point);
     if !on_line {
         return false;
     };
@@ -84,7 +92,7 @@ pub(crate) fn line_circle_collision(line: &Entity, circle: &Entity) -> bool {
  * If the distance between the centers of the circles is less than
  * the sum of the radius, a collision has occured
  */
-pub(crate) fn circle_circle_collision(circle_1: &Entity, circle_2: &Entity) -> bool {
+pub fn circle_circle_collision(circle_1: &Entity, circle_2: &Entity) -> bool {
     let distance = calculate_distance(&circle_1.position, &circle_2.position);
     distance <= circle_1.radius + circle_2.radius
 }
@@ -93,7 +101,7 @@ pub(crate) fn circle_circle_collision(circle_1: &Entity, circle_2: &Entity) -> b
  * Determines if a collision has occured between a circle and a polygon
  *
  */
-pub(crate) fn circle_polygon_collision(circle: &Entity, polygon: &Entity) -> bool {
+pub fn circle_polygon_collision(circle: &Entity, polygon: &Entity) -> bool {
     // For each line in the polygon, check if there is a collision between the line and the circle
     // If there is a collision, return true
     for current in 0..polygon.vertices.len() {
@@ -113,8 +121,8 @@ pub(crate) fn circle_polygon_collision(circle: &Entity, polygon: &Entity) -> boo
 
     // Check if the center of the circle is inside the polygon
     // If you doesn't want to check if the circle is inside the polygon,
-    // return false instead of calling point_polygon_colision
-    point_polygon_colision(circle, polygon)
+    // return false instead of calling point_polygon_collision
+    point_polygon_collision(circle, polygon)
 }
 
 /*
@@ -123,7 +131,7 @@ pub(crate) fn circle_polygon_collision(circle: &Entity, polygon: &Entity) -> boo
  * is equal (with a little bufer) to the distance between vertex 1 and vertex 2,
  * a collision has occured
  */
-pub(crate) fn line_point_colision(line: &Entity, point: &Entity) -> bool {
+pub fn line_point_collision(line: &Entity, point: &Entity) -> bool {
     let d1 = calculate_distance(&point.position, &line.vertices[0]);
     let d2 = calculate_distance(&point.position, &line.vertices[1]);
     let line_length = calculate_distance(&line.vertices[0], &line.vertices[1]);
@@ -133,35 +141,7 @@ pub(crate) fn line_point_colision(line: &Entity, point: &Entity) -> bool {
     d1 + d2 >= line_length - buffer && d1 + d2 <= line_length + buffer
 }
 
-/*
- * Determines if a collision has occured between a point and a polygon
- */
-pub(crate) fn point_polygon_colision(point: &Entity, polygon: &Entity) -> bool {
-    let mut collision = false;
-    for current in 0..polygon.vertices.len() {
-        let mut next = current + 1;
-        if next == polygon.vertices.len() {
-            next = 0
-        };
-
-        let current_vertex = polygon.vertices[current];
-        let next_vertex = polygon.vertices[next];
-
-        if ((current_vertex.y >= point.position.y && next_vertex.y < point.position.y)
-            || (current_vertex.y < point.position.y && next_vertex.y >= point.position.y))
-            && (point.position.x
-                < (next_vertex.x - current_vertex.x) * (point.position.y - current_vertex.y)
-                    / (next_vertex.y - current_vertex.y)
-                    + current_vertex.x)
-        {
-            collision = !collision;
-        }
-    }
-
-    collision
-}
-
-pub(crate) fn line_polygon_collision(line: &Entity, polygon: &Entity) -> bool {
+pub fn line_polygon_collision(line: &Entity, polygon: &Entity) -> bool {
     for current_vertex_index in 0..polygon.vertices.len() {
         let mut next_vertex_index = current_vertex_index + 1;
         if next_vertex_index == polygon.vertices.len() {
@@ -180,13 +160,13 @@ pub(crate) fn line_polygon_collision(line: &Entity, polygon: &Entity) -> bool {
     false
 }
 
-pub(crate) fn line_line_collision(line: &Entity, other_line: &Entity) -> bool {
+pub fn line_line_collision(line: &Entity, other_line: &Entity) -> bool {
     let line_first_vertex = line.vertices[0];
     let line_second_vertex = line.vertices[1];
     let other_line_first_vertex = other_line.vertices[0];
     let other_line_second_vertex = other_line.vertices[1];
 
-    let uA = ((other_line_second_vertex.x - other_line_first_vertex.x)
+    let u_a = ((other_line_second_vertex.x - other_line_first_vertex.x)
         * (line_first_vertex.y - other_line_first_vertex.y)
         - (other_line_second_vertex.y - other_line_first_vertex.y)
             * (line_first_vertex.x - other_line_first_vertex.x))
@@ -195,7 +175,7 @@ pub(crate) fn line_line_collision(line: &Entity, other_line: &Entity) -> bool {
             - (other_line_second_vertex.x - other_line_first_vertex.x)
                 * (line_second_vertex.y - line_first_vertex.y));
 
-    let uB = ((line_second_vertex.x - line_first_vertex.x)
+    let u_b = ((line_second_vertex.x - line_first_vertex.x)
         * (line_first_vertex.y - other_line_first_vertex.y)
         - (line_second_vertex.y - line_first_vertex.y)
             * (line_first_vertex.x - other_line_first_vertex.x))
@@ -204,13 +184,13 @@ pub(crate) fn line_line_collision(line: &Entity, other_line: &Entity) -> bool {
             - (other_line_second_vertex.x - other_line_first_vertex.x)
                 * (line_second_vertex.y - line_first_vertex.y));
 
-    (0.0..=1.0).contains(&uA) && (0.0..=1.0).contains(&uB)
+    (0.0..=1.0).contains(&u_a) && (0.0..=1.0).contains(&u_b)
 }
 
 /*
  * Calculates the distance between two positions
  */
-pub(crate) fn calculate_distance(a: &Position, b: &Position) -> f32 {
+pub fn calculate_distance(a: &Position, b: &Position) -> f32 {
     let x = a.x - b.x;
     let y = a.y - b.y;
     (x.powi(2) + y.powi(2)).sqrt()
@@ -1,4 +1,5 @@
-use crate::map::{Entity, Position};
+use crate::entity::Entity;
+use crate::position::Position;
 
 /*
 Ear clipping triangulation algorithm
@@ -24,10 +25,15 @@ Even though the resolution is different, the logic is the same as the following
 
 */
 
-pub(crate) fn maybe_triangulate_polygon(mut polygon: Entity) -> Vec<Entity> {
+pub fn maybe_triangulate_polygon(polygon: Entity) -> Vec<Entity> {
+    try_triangulate_polygon(polygon).unwrap_or_else(|error| panic!("{}", error))
+}
+
+// Fails on polygons that can't be triangulated, like counter clockwise or self intersecting ones
+pub(crate) fn try_triangulate_polygon(mut polygon: Entity) -> Result<Vec<Entity>, String> {
     remove_redundant_vertices(&mut polygon);
     if is_polygon_convex(&polygon) {
-        return vec![polygon.clone()];
+        return Ok(vec![polygon.clone()]);
     }
     triangulate_polygon(&polygon)
 }
@@ -64,7 +70,7 @@ fn is_vertex_consecutive(
     get_cross_product_value(&current_to_previous_vector, &current_to_next_vector) == 0.0
 }
 
-fn triangulate_polygon(polygon: &Entity) -> Vec<Entity> {
+fn triangulate_polygon(polygon: &Entity) -> Result<Vec<Entity>, String> {
     let mut result: Vec<Entity> = vec![];
     let mut vertices = polygon.vertices.clone();
 
@@ -72,7 +78,7 @@ fn triangulate_polygon(polygon: &Entity) -> Vec<Entity> {
         if let Some(triangle) = find_ear_in_vertices(&mut vertices) {
             result.push(triangle)
         } else {
-            panic!("No ear detected, polygon invalid")
+            return Err("No ear detected, polygon invalid".to_string());
         }
     }
     let previous_vertex = vertices[0];
@@ -86,10 +92,10 @@ fn triangulate_polygon(polygon: &Entity) -> Vec<Entity> {
     // This algorithm should always result n - 2 triangles where n is the amount of vertex
     // in the polygon
     if result.len() > polygon.vertices.len() - 2 {
-        panic!("Wrong triangulation result")
+        return Err("Wrong triangulation result".to_string());
     }
 
-    result
+    Ok(result)
 }
 
 fn find_ear_in_vertices(vertices: &mut Vec<Position>) -> Option<Entity> {
@@ -165,6 +171,19 @@ fn get_cross_product_value(first_vector: &Position, second_vector: &Position) ->
     first_vector.x * second_vector.y - first_vector.y * second_vector.x
 }
 
+// The triangulation only works with the vertices in clockwise order
+pub fn is_clockwise(vertices: &[Position]) -> bool {
+    let doubled_signed_area: f32 = (0..vertices.len())
+        .map(|index| {
+            let current = vertices[index];
+            let next = vertices[get_next_vertex_index(index, vertices)];
+            current.x * next.y - next.x * current.y
+        })
+        .sum();
+
+    doubled_signed_area < 0.0
+}
+
 // A convex polygon means that every inner angle doesn't have more than
 // 180 degrees
 fn is_polygon_convex(polygon: &Entity) -> bool {
@@ -0,0 +1,302 @@
+use crate::entity::{Entity, Shape};
+use crate::position::Position;
+use crate::spatial_query::entities_overlap;
+
+/*
+ * Oriented boxes
+ *
+ * Rectangles centered on the entity position that turn with it: the box is rotated `rotation`
+ * degrees counterclockwise from the entity direction, or from the x axis while the entity has no
+ * direction. Moving hitboxes only need their position and direction updated.
+ *
+ * The tests against circles and points are done in the box's own axes, where the box is axis
+ * aligned. Polygons and other boxes use the separating axis theorem, so like in `sat.rs` the
+ * polygons must be convex.
+ */
+
+pub struct OrientedBox {
+    pub center: Position,
+    // Unit vectors along the width and the height of the box
+    pub axes: [Position; 2],
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+impl OrientedBox {
+    pub fn of(entity: &Entity) -> Option<OrientedBox> {
+        let Shape::OrientedBox {
+            half_width,
+            half_height,
+            rotation,
+        } = entity.shape
+        else {
+            return None;
+        };
+
+        let facing = if entity.direction.x == 0.0 && entity.direction.y == 0.0 {
+            0.0
+        } else {
+            entity.direction.y.atan2(entity.direction.x)
+        };
+        let angle = facing + rotation.to_radians();
+        let width_axis = Position {
+            x: angle.cos(),
+            y: angle.sin(),
+        };
+
+        Some(OrientedBox {
+            center: entity.position,
+            axes: [
+                width_axis,
+                Position {
+                    x: -width_axis.y,
+                    y: width_axis.x,
+                },
+            ],
+            // Negative extents would make the clamping in local coordinates panic
+            half_width: half_width.abs(),
+            half_height: half_height.abs(),
+        })
+    }
+
+    // Clockwise, the order the triangulation and the rest of the polygons use
+    pub fn corners(&self) -> Vec<Position> {
+        [(-1.0, -1.0), (-1.0, 1.0), (1.0, 1.0), (1.0, -1.0)]
+            .into_iter()
+            .map(|(width_sign, height_sign)| {
+                self.to_world(width_sign * self.half_width, height_sign * self.half_height)
+            })
+            .collect()
+    }
+
+    // Inverse of `to_local`
+    fn to_world(&self, x: f32, y: f32) -> Position {
+        Position::add(
+            &self.center,
+            &Position::add(
+                &Position::mult(&self.axes[0], x),
+                &Position::mult(&self.axes[1], y),
+            ),
+        )
+    }
+
+    // Position relative to the center, along the width and the height of the box
+    fn to_local(&self, position: &Position) -> (f32, f32) {
+        let offset = Position::sub(position, &self.center);
+        (
+            offset.x * self.axes[0].x + offset.y * self.axes[0].y,
+            offset.x * self.axes[1].x + offset.y * self.axes[1].y,
+        )
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        let (x, y) = self.to_local(position);
+        x.abs() <= self.half_width && y.abs() <= self.half_height
+    }
+
+    fn project(&self, axis: &Position) -> (f32, f32) {
+        let center = self.center.x * axis.x + self.center.y * axis.y;
+        let extent = self.half_width * (self.axes[0].x * axis.x + self.axes[0].y * axis.y).abs()
+            + self.half_height * (self.axes[1].x * axis.x + self.axes[1].y * axis.y).abs();
+        (center - extent, center + extent)
+    }
+}
+
+pub fn circle_oriented_box_collision(circle: &Entity, oriented_box: &OrientedBox) -> bool {
+    let (x, y) = oriented_box.to_local(&circle.position);
+    let outside_x = (x.abs() - oriented_box.half_width).max(0.0);
+    let outside_y = (y.abs() - oriented_box.half_height).max(0.0);
+    outside_x.powi(2) + outside_y.powi(2) <= circle.radius.powi(2)
+}
+
+// Also works for lines, a segment is a polygon with a single edge
+pub fn polygon_oriented_box_collision(vertices: &[Position], oriented_box: &OrientedBox) -> bool {
+    let polygon_axes = (0..vertices.len()).map(|index| {
+        let edge = Position::sub(&vertices[(index + 1) % vertices.len()], &vertices[index]);
+        Position {
+            x: -edge.y,
+            y: edge.x,
+        }
+    });
+
+    oriented_box
+        .axes
+        .into_iter()
+        .chain(polygon_axes)
+        .filter(|axis| axis.x != 0.0 || axis.y != 0.0)
+        .all(|axis| {
+            let (box_min, box_max) = oriented_box.project(&axis);
+            let (polygon_min, polygon_max) = vertices
+                .iter()
+                .map(|vertex| vertex.x * axis.x + vertex.y * axis.y)
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                });
+            box_min <= polygon_max && polygon_min <= box_max
+        })
+}
+
+// Same return value as `intersect_circle_polygon`: if they collide, the direction to push the
+// circle in and how much
+pub fn intersect_circle_oriented_box(
+    circle: &Entity,
+    oriented_box: &OrientedBox,
+) -> (bool, Position, f32) {
+    let (x, y) = oriented_box.to_local(&circle.position);
+    let closest = oriented_box.to_world(
+        x.clamp(-oriented_box.half_width, oriented_box.half_width),
+        y.clamp(-oriented_box.half_height, oriented_box.half_height),
+    );
+
+    if !oriented_box.contains(&circle.position) {
+        let mut normal = Position::sub(&circle.position, &closest);
+        let distance = circle.position.distance_to_position(&closest);
+        if distance > circle.radius {
+            return (false, normal, f32::MAX);
+        }
+        normal.normalize();
+        return (true, normal, circle.radius - distance);
+    }
+
+    // With the center inside the box it leaves through the closest side
+    let width_depth = oriented_box.half_width - x.abs();
+    let height_depth = oriented_box.half_height - y.abs();
+    let sign = |value: f32| if value < 0.0 { -1.0 } else { 1.0 };
+    if width_depth < height_depth {
+        (
+            true,
+            Position::mult(&oriented_box.axes[0], sign(x)),
+            width_depth + circle.radius,
+        )
+    } else {
+        (
+            true,
+            Position::mult(&oriented_box.axes[1], sign(y)),
+            height_depth + circle.radius,
+        )
+    }
+}
+
+// Whether the oriented box collides with a circle, point, line, polygon or another oriented box,
+// no matter which of the two entities is the box
+pub fn oriented_box_collision(entity: &Entity, other_entity: &Entity) -> bool {
+    let (oriented_box, other_entity) = match OrientedBox::of(entity) {
+        Some(oriented_box) => (oriented_box, other_entity),
+        None => match OrientedBox::of(other_entity) {
+            Some(oriented_box) => (oriented_box, entity),
+            None => return false,
+        },
+    };
+
+    match other_entity.shape {
+        Shape::Circle => circle_oriented_box_collision(other_entity, &oriented_box),
+        Shape::Point => oriented_box.contains(&other_entity.position),
+        Shape::Polygon | Shape::Line => {
+            polygon_oriented_box_collision(&other_entity.vertices, &oriented_box)
+        }
+        Shape::OrientedBox { .. } => match OrientedBox::of(other_entity) {
+            Some(other_box) => polygon_oriented_box_collision(&other_box.corners(), &oriented_box),
+            None => false,
+        },
+        Shape::Sector { .. } | Shape::Capsule { .. } => entities_overlap(entity, other_entity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Direction;
+
+    fn circle(x: f32, y: f32, radius: f32) -> Entity {
+        let mut circle = Entity::new_point(2, Position { x, y });
+        circle.shape = Shape::Circle;
+        circle.radius = radius;
+        circle
+    }
+
+    // A box without height is a segment, like a thin bash area
+    fn segment_box() -> OrientedBox {
+        let entity = Entity::new_oriented_box(
+            1,
+            Position { x: 0.0, y: 0.0 },
+            Direction { x: 1.0, y: 0.0 },
+            100.0,
+            0.0,
+            0.0,
+        );
+        OrientedBox::of(&entity).unwrap()
+    }
+
+    #[test]
+    fn circle_is_pushed_out_of_a_box_without_height() {
+        let (collided, normal, depth) =
+            intersect_circle_oriented_box(&circle(50.0, 5.0, 10.0), &segment_box());
+
+        assert!(collided);
+        assert!(normal.x.abs() < 1e-5 && (normal.y - 1.0).abs() < 1e-5);
+        assert!((depth - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn circle_centered_on_a_box_without_height_gets_a_finite_push() {
+        let (collided, normal, depth) =
+            intersect_circle_oriented_box(&circle(50.0, 0.0, 10.0), &segment_box());
+
+        assert!(collided);
+        assert!(normal.x.is_finite() && normal.y.is_finite() && depth.is_finite());
+        assert!((depth - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn circle_away_from_a_box_without_width_or_height_does_not_collide() {
+        let entity = Entity::new_oriented_box(
+            1,
+            Position { x: 0.0, y: 0.0 },
+            Direction { x: 0.0, y: 0.0 },
+            0.0,
+            0.0,
+            0.0,
+        );
+        let oriented_box = OrientedBox::of(&entity).unwrap();
+
+        let (collided, _, _) =
+            intersect_circle_oriented_box(&circle(20.0, 0.0, 10.0), &oriented_box);
+        assert!(!collided);
+        let (collided, normal, depth) =
+            intersect_circle_oriented_box(&circle(5.0, 0.0, 10.0), &oriented_box);
+        assert!(collided);
+        assert!((normal.x - 1.0).abs() < 1e-5 && (depth - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn boxes_overlap_sectors_and_capsules() {
+        let oriented_box = Entity::new_oriented_box(
+            1,
+            Position { x: 0.0, y: 0.0 },
+            Direction { x: 0.0, y: 1.0 },
+            100.0,
+            20.0,
+            0.0,
+        );
+        let capsule = |y: f32| {
+            Entity::new_capsule(2, Position { x: -500.0, y }, Position { x: 500.0, y }, 30.0)
+        };
+        // Facing the box from below, it only reaches it with its arc
+        let sector = |range: f32| {
+            Entity::new_sector(
+                3,
+                Position { x: 0.0, y: -300.0 },
+                Direction { x: 0.0, y: 1.0 },
+                range,
+                20.0,
+            )
+        };
+
+        assert!(oriented_box_collision(&oriented_box, &capsule(120.0)));
+        assert!(oriented_box_collision(&capsule(120.0), &oriented_box));
+        assert!(!oriented_box_collision(&oriented_box, &capsule(140.0)));
+        assert!(oriented_box_collision(&oriented_box, &sector(210.0)));
+        assert!(oriented_box_collision(&sector(210.0), &oriented_box));
+        assert!(!oriented_box_collision(&oriented_box, &sector(190.0)));
+    }
+}
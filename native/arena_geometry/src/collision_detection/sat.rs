@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::mem::swap;
 
-use crate::map::{Entity, Position};
+use crate::entity::{Entity, Shape};
+use crate::position::Position;
+use crate::spatial_query::{closest_point, Sector};
 /*
     Collision detection using the [SAT theorem](https://dyn4j.org/2010/01/sat/)
     To determine if a pair of shapes are colliding we'll try to draw a line from an axis where the entities
@@ -18,7 +20,7 @@ use crate::map::{Entity, Position};
 // 1: bool = true if the entities are colliding
 // 2: Position = nomalized line of collision
 // 3: f32 = the minimum amount of overlap between the shapes that would solve the collision
-pub(crate) fn intersect_circle_polygon(
+pub fn intersect_circle_polygon(
     circle: &mut Entity,
     polygon: &Entity,
     obstacles: &HashMap<u64, Entity>,
@@ -127,13 +129,104 @@ pub(crate) fn intersect_circle_polygon(
     (true, normal, result_depth)
 }
 
+// Same as `intersect_circle_polygon` for sectors. The axes are the normals of the straight sides,
+// the one from the apex to the circle for the arc and the one from the closest corner.
+// Sectors wider than 180 degrees are resolved as if they were convex.
+pub fn intersect_circle_sector(circle: &Entity, sector: &Entity) -> (bool, Position, f32) {
+    let mut normal = Position { x: 0.0, y: 0.0 };
+    let mut result_depth: f32 = f32::MAX;
+
+    let Some(sector) = Sector::of(sector) else {
+        return (false, normal, result_depth);
+    };
+
+    let corners = vec![sector.apex, sector.edges[0], sector.edges[1]];
+    let mut axes: Vec<Position> = sector
+        .edges
+        .iter()
+        .map(|edge| {
+            let side = Position::sub(edge, &sector.apex);
+            Position {
+                x: side.y,
+                y: -side.x,
+            }
+        })
+        .collect();
+    axes.push(Position::sub(&circle.position, &sector.apex));
+    axes.push(Position::sub(
+        &circle.position,
+        &find_closest_vertex(&circle.position, &corners),
+    ));
+
+    for mut axis in axes {
+        if axis.x == 0.0 && axis.y == 0.0 {
+            continue;
+        }
+        axis.normalize();
+
+        let (min_sector_cast_point, max_sector_cast_point) = sector.project(axis);
+        let (min_circle_cast_point, max_circle_cast_point) = project_circle(circle, axis);
+
+        if min_sector_cast_point >= max_circle_cast_point
+            || min_circle_cast_point >= max_sector_cast_point
+        {
+            return (false, normal, result_depth);
+        }
+
+        // Pushing the circle along the axis or against it
+        let forward_depth = max_sector_cast_point - min_circle_cast_point;
+        let backward_depth = max_circle_cast_point - min_sector_cast_point;
+
+        if forward_depth.min(backward_depth) < result_depth {
+            if forward_depth < backward_depth {
+                normal = axis;
+                result_depth = forward_depth;
+            } else {
+                normal = Position::mult(&axis, -1.0);
+                result_depth = backward_depth;
+            }
+        }
+    }
+
+    (true, normal, result_depth)
+}
+
+// The circle is pushed away from the closest point of the capsule segment
+pub fn intersect_circle_capsule(circle: &Entity, capsule: &Entity) -> (bool, Position, f32) {
+    let Shape::Capsule { a, b, radius } = &capsule.shape else {
+        return (false, Position { x: 0.0, y: 0.0 }, f32::MAX);
+    };
+
+    let closest = closest_point(&circle.position, a, b);
+    let mut normal = Position::sub(&circle.position, &closest);
+    let distance = circle.position.distance_to_position(&closest);
+    if distance > circle.radius + radius {
+        return (false, normal, f32::MAX);
+    }
+
+    if distance == 0.0 {
+        // The center is on the segment, any side is as good
+        let segment = Position::sub(b, a);
+        normal = Position {
+            x: -segment.y,
+            y: segment.x,
+        };
+        if normal.x == 0.0 && normal.y == 0.0 {
+            normal.x = 1.0;
+        }
+    }
+    normal.normalize();
+
+    (true, normal, circle.radius + radius - distance)
+}
+
 // Uncomment this if we need a polygon-polygon collision detection
 
 // Handle the intesection between two polygons, the return value is a tuple of 3 elements
 // a: bool = true if the entities are colliding
 // b: Position = nomalized line of collision
 // c: f32 = the amount of overlap between the shapes
-// pub(crate) fn intersect_polygon_polygon(
+// pub fn intersect_polygon_polygon(
 //     polygonA: &Entity,
 //     polygonB: &Entity,
 // ) -> (bool, Position, f32) {
@@ -378,3 +471,109 @@ fn invalid_axis(
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Direction;
+
+    fn circle(x: f32, y: f32, radius: f32) -> Entity {
+        Entity {
+            shape: Shape::Circle,
+            radius,
+            ..Entity::new_point(1, Position { x, y })
+        }
+    }
+
+    // Facing right, 500 long and 60 degrees wide
+    fn sector() -> Entity {
+        Entity::new_sector(
+            2,
+            Position { x: 0.0, y: 0.0 },
+            Direction { x: 1.0, y: 0.0 },
+            500.0,
+            30.0,
+        )
+    }
+
+    fn capsule() -> Entity {
+        Entity::new_capsule(
+            2,
+            Position { x: 0.0, y: 0.0 },
+            Position { x: 1000.0, y: 0.0 },
+            50.0,
+        )
+    }
+
+    fn assert_push(
+        (collided, normal, depth): (bool, Position, f32),
+        expected_normal: (f32, f32),
+        expected_depth: f32,
+    ) {
+        assert!(collided);
+        assert!((normal.x - expected_normal.0).abs() < 1e-3, "{:?}", normal);
+        assert!((normal.y - expected_normal.1).abs() < 1e-3, "{:?}", normal);
+        assert!((depth - expected_depth).abs() < 1e-2, "depth {}", depth);
+    }
+
+    #[test]
+    fn circles_are_pushed_out_of_sectors_through_the_closest_side() {
+        assert_push(
+            intersect_circle_sector(&circle(520.0, 0.0, 50.0), &sector()),
+            (1.0, 0.0),
+            30.0,
+        );
+
+        // 40 units away from the middle of the upper side, along its normal
+        let (sin, cos) = 30.0_f32.to_radians().sin_cos();
+        let side_point = Position {
+            x: 200.0 * cos,
+            y: 200.0 * sin,
+        };
+        let outer_normal = (-sin, cos);
+        assert_push(
+            intersect_circle_sector(
+                &circle(
+                    side_point.x + outer_normal.0 * 40.0,
+                    side_point.y + outer_normal.1 * 40.0,
+                    50.0,
+                ),
+                &sector(),
+            ),
+            outer_normal,
+            10.0,
+        );
+    }
+
+    #[test]
+    fn circles_outside_of_the_sector_wedge_do_not_collide() {
+        // Beside the wedge within its radius, behind the apex, past the arc and not a sector at all
+        assert!(!intersect_circle_sector(&circle(0.0, 400.0, 50.0), &sector()).0);
+        assert!(!intersect_circle_sector(&circle(-60.0, 0.0, 50.0), &sector()).0);
+        assert!(!intersect_circle_sector(&circle(560.0, 0.0, 50.0), &sector()).0);
+        assert!(!intersect_circle_sector(&circle(520.0, 0.0, 50.0), &capsule()).0);
+    }
+
+    #[test]
+    fn circles_are_pushed_away_from_the_capsule_segment() {
+        assert_push(
+            intersect_circle_capsule(&circle(500.0, 80.0, 50.0), &capsule()),
+            (0.0, 1.0),
+            20.0,
+        );
+        // Round ends push outwards along the segment
+        assert_push(
+            intersect_circle_capsule(&circle(1080.0, 0.0, 50.0), &capsule()),
+            (1.0, 0.0),
+            20.0,
+        );
+        // Centered on the segment it goes out through one of the sides
+        let (collided, normal, depth) =
+            intersect_circle_capsule(&circle(500.0, 0.0, 50.0), &capsule());
+        assert!(collided && normal.x.abs() < 1e-5 && (normal.y.abs() - 1.0).abs() < 1e-5);
+        assert!((depth - 100.0).abs() < 1e-3);
+
+        assert!(!intersect_circle_capsule(&circle(500.0, 200.0, 50.0), &capsule()).0);
+        assert!(!intersect_circle_capsule(&circle(500.0, 0.0, 50.0), &sector()).0);
+    }
+}
@@ -0,0 +1,599 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::collision_detection::circle_polygon_collision;
+use crate::collision_detection::ear_clipping::{is_clockwise, try_triangulate_polygon};
+use crate::collision_detection::oriented_box::OrientedBox;
+use crate::collision_detection::sat::intersect_circle_polygon;
+use crate::entity::{Category, Entity, Shape};
+use crate::position::Position;
+use crate::spatial_query::Sector;
+
+/*
+ * Debug rendering
+ *
+ * Draws what the physics sees into an SVG so collision bugs can be looked at instead of guessed:
+ * the external wall, the obstacles with the triangles concave ones are split in, the entities
+ * colored by category, the normals the physics would push circles out of polygons with, the
+ * blocked cells of a collision grid and paths.
+ *
+ * The world y axis points up, so it's flipped to draw. Hovering anything shows what it is.
+ * Anything with coordinates that aren't finite is left out, a single NaN breaks the whole file.
+ */
+
+#[derive(Default)]
+pub struct DebugScene {
+    pub external_wall: Option<Entity>,
+    pub obstacles: Vec<Entity>,
+    pub entities: Vec<Entity>,
+    pub grid: Option<DebugGrid>,
+    pub paths: Vec<Vec<Position>>,
+}
+
+// Grid of square cells in rows from the bottom, like the bots collision grid
+pub struct DebugGrid {
+    // Bottom left corner of the first cell
+    pub origin: Position,
+    pub cell_size: f32,
+    pub columns: usize,
+    pub blocked: Vec<bool>,
+}
+
+const GRID_COLOR: &str = "#f4a6a6";
+const WALL_COLOR: &str = "#222222";
+const PATH_COLOR: &str = "#ff7f0e";
+const NORMAL_COLOR: &str = "#e41a1c";
+
+pub fn render_svg(scene: &DebugScene) -> String {
+    let (min, max) = bounds(scene);
+    let margin = (max.x - min.x).max(max.y - min.y) * 0.02;
+    let (min, max) = (
+        Position {
+            x: min.x - margin,
+            y: min.y - margin,
+        },
+        Position {
+            x: max.x + margin,
+            y: max.y + margin,
+        },
+    );
+    let stroke = ((max.x - min.x).max(max.y - min.y) / 1000.0).max(f32::EPSILON);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.1} {:.1} {:.1} {:.1}" stroke-width="{:.2}">"#,
+        min.x,
+        flip(max.y),
+        max.x - min.x,
+        max.y - min.y,
+        stroke
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="white"/>"#,
+        min.x,
+        flip(max.y),
+        max.x - min.x,
+        max.y - min.y
+    );
+
+    if let Some(grid) = &scene.grid {
+        draw_grid(&mut svg, grid);
+    }
+
+    let external_wall = scene
+        .external_wall
+        .as_ref()
+        .filter(|wall| is_drawable(wall));
+    if let Some(external_wall) = external_wall {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="none" stroke="{}" stroke-width="{:.2}"><title>{}</title></circle>"#,
+            external_wall.position.x,
+            flip(external_wall.position.y),
+            external_wall.radius,
+            WALL_COLOR,
+            stroke * 3.0,
+            title(external_wall)
+        );
+    }
+
+    for obstacle in scene
+        .obstacles
+        .iter()
+        .filter(|obstacle| is_drawable(obstacle))
+    {
+        draw_entity(&mut svg, obstacle, stroke);
+        // Only clockwise polygons can be triangulated, the rest and the invalid ones are left whole
+        if obstacle.shape == Shape::Polygon && is_clockwise(&obstacle.vertices) {
+            let pieces = try_triangulate_polygon(obstacle.clone()).unwrap_or_default();
+            if pieces.len() > 1 {
+                for piece in pieces {
+                    let _ = writeln!(
+                        svg,
+                        r#"<polygon points="{}" fill="none" stroke="white" stroke-dasharray="{:.1}"/>"#,
+                        points(&piece.vertices),
+                        stroke * 4.0
+                    );
+                }
+            }
+        }
+    }
+
+    for entity in scene.entities.iter().filter(|entity| is_drawable(entity)) {
+        draw_entity(&mut svg, entity, stroke);
+    }
+
+    for path in &scene.paths {
+        let path: Vec<Position> = path.iter().copied().filter(is_finite).collect();
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{:.2}"/>"#,
+            points(&path),
+            PATH_COLOR,
+            stroke * 2.0
+        );
+        for waypoint in &path {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#,
+                waypoint.x,
+                flip(waypoint.y),
+                stroke * 3.0,
+                PATH_COLOR
+            );
+        }
+    }
+
+    if let Some(external_wall) = external_wall {
+        draw_collision_normals(&mut svg, scene, external_wall, stroke);
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn draw_grid(svg: &mut String, grid: &DebugGrid) {
+    if grid.columns == 0 || !is_finite(&grid.origin) || !grid.cell_size.is_finite() {
+        return;
+    }
+
+    // Consecutive blocked cells of a row are merged in a single rectangle
+    for (row, cells) in grid.blocked.chunks(grid.columns).enumerate() {
+        let mut col = 0;
+        while col < cells.len() {
+            if !cells[col] {
+                col += 1;
+                continue;
+            }
+
+            let start = col;
+            while col < cells.len() && cells[col] {
+                col += 1;
+            }
+
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+                grid.origin.x + start as f32 * grid.cell_size,
+                flip(grid.origin.y + (row + 1) as f32 * grid.cell_size),
+                (col - start) as f32 * grid.cell_size,
+                grid.cell_size,
+                GRID_COLOR
+            );
+        }
+    }
+}
+
+fn draw_entity(svg: &mut String, entity: &Entity, stroke: f32) {
+    let color = category_color(&entity.category);
+
+    let _ = match entity.shape {
+        Shape::Circle => writeln!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}" fill-opacity="0.6" stroke="{}"><title>{}</title></circle>"#,
+            entity.position.x,
+            flip(entity.position.y),
+            entity.radius,
+            color,
+            color,
+            title(entity)
+        ),
+        Shape::Polygon => writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}" fill-opacity="0.6" stroke="{}"><title>{}</title></polygon>"#,
+            points(&entity.vertices),
+            color,
+            color,
+            title(entity)
+        ),
+        Shape::Line => writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{:.2}"><title>{}</title></polyline>"#,
+            points(&entity.vertices),
+            color,
+            stroke * 2.0,
+            title(entity)
+        ),
+        Shape::Point => writeln!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"><title>{}</title></circle>"#,
+            entity.position.x,
+            flip(entity.position.y),
+            stroke * 3.0,
+            color,
+            title(entity)
+        ),
+        // A line as thick as the capsule with round ends is the capsule
+        Shape::Capsule { a, b, radius } => writeln!(
+            svg,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-opacity="0.6" stroke-width="{:.1}" stroke-linecap="round"><title>{}</title></line>"#,
+            a.x,
+            flip(a.y),
+            b.x,
+            flip(b.y),
+            color,
+            radius * 2.0,
+            title(entity)
+        ),
+        Shape::OrientedBox { .. } => writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}" fill-opacity="0.6" stroke="{}"><title>{}</title></polygon>"#,
+            points(
+                &OrientedBox::of(entity)
+                    .map(|oriented_box| oriented_box.corners())
+                    .unwrap_or_default()
+            ),
+            color,
+            color,
+            title(entity)
+        ),
+        Shape::Sector { radius, half_angle } => match Sector::of(entity) {
+            Some(sector) if half_angle < 180.0 => writeln!(
+                svg,
+                // From the first edge to the second one is clockwise in the world, which once y
+                // is flipped is the positive angle direction SVG calls sweep
+                r#"<path d="M {:.1} {:.1} L {:.1} {:.1} A {:.1} {:.1} 0 {} 1 {:.1} {:.1} Z" fill="{}" fill-opacity="0.6" stroke="{}"><title>{}</title></path>"#,
+                sector.apex.x,
+                flip(sector.apex.y),
+                sector.edges[0].x,
+                flip(sector.edges[0].y),
+                radius,
+                radius,
+                u8::from(half_angle > 90.0),
+                sector.edges[1].x,
+                flip(sector.edges[1].y),
+                color,
+                color,
+                title(entity)
+            ),
+            _ => writeln!(
+                svg,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}" fill-opacity="0.6" stroke="{}"><title>{}</title></circle>"#,
+                entity.position.x,
+                flip(entity.position.y),
+                radius,
+                color,
+                color,
+                title(entity)
+            ),
+        },
+    };
+
+    // Moving entities point where they are going
+    if entity.is_moving && entity.shape != Shape::Polygon {
+        let length = entity.radius.max(stroke * 10.0) * 1.5;
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}"/>"#,
+            entity.position.x,
+            flip(entity.position.y),
+            entity.position.x + entity.direction.x * length,
+            flip(entity.position.y + entity.direction.y * length),
+            WALL_COLOR
+        );
+    }
+}
+
+// Normals circles overlapping polygon obstacles would be pushed out with, as long as the overlap
+fn draw_collision_normals(
+    svg: &mut String,
+    scene: &DebugScene,
+    external_wall: &Entity,
+    stroke: f32,
+) {
+    let obstacles: HashMap<u64, Entity> = scene
+        .obstacles
+        .iter()
+        .map(|obstacle| (obstacle.id, obstacle.clone()))
+        .collect();
+
+    for entity in scene
+        .entities
+        .iter()
+        .filter(|entity| entity.shape == Shape::Circle && is_drawable(entity))
+    {
+        for obstacle in scene
+            .obstacles
+            .iter()
+            .filter(|obstacle| obstacle.shape == Shape::Polygon && is_drawable(obstacle))
+        {
+            if !circle_polygon_collision(entity, obstacle) {
+                continue;
+            }
+
+            let (collided, normal, depth) =
+                intersect_circle_polygon(&mut entity.clone(), obstacle, &obstacles, external_wall);
+            if !collided || !is_finite(&normal) || !depth.is_finite() {
+                continue;
+            }
+
+            let length = depth.max(stroke * 10.0);
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{:.2}"><title>Entity {} out of obstacle {}, depth {:.1}</title></line>"#,
+                entity.position.x,
+                flip(entity.position.y),
+                entity.position.x + normal.x * length,
+                flip(entity.position.y + normal.y * length),
+                NORMAL_COLOR,
+                stroke * 2.0,
+                entity.id,
+                obstacle.id,
+                depth
+            );
+        }
+    }
+}
+
+fn bounds(scene: &DebugScene) -> (Position, Position) {
+    let mut min = Position {
+        x: f32::INFINITY,
+        y: f32::INFINITY,
+    };
+    let mut max = Position {
+        x: f32::NEG_INFINITY,
+        y: f32::NEG_INFINITY,
+    };
+    let mut include = |position: &Position, radius: f32| {
+        if !is_finite(position) || !radius.is_finite() {
+            return;
+        }
+        min.x = min.x.min(position.x - radius);
+        min.y = min.y.min(position.y - radius);
+        max.x = max.x.max(position.x + radius);
+        max.y = max.y.max(position.y + radius);
+    };
+
+    match scene
+        .external_wall
+        .as_ref()
+        .filter(|wall| is_drawable(wall))
+    {
+        Some(external_wall) => include(&external_wall.position, external_wall.radius),
+        None => {
+            for entity in scene
+                .obstacles
+                .iter()
+                .chain(&scene.entities)
+                .filter(|entity| is_drawable(entity))
+            {
+                let (entity_min, entity_max) = entity.bounding_box();
+                include(&entity_min, 0.0);
+                include(&entity_max, 0.0);
+            }
+            for waypoint in scene.paths.iter().flatten() {
+                include(waypoint, 0.0);
+            }
+        }
+    }
+
+    if min.x > max.x || min.y > max.y {
+        return (Position { x: 0.0, y: 0.0 }, Position { x: 1.0, y: 1.0 });
+    }
+
+    (min, max)
+}
+
+fn is_drawable(entity: &Entity) -> bool {
+    let shape_is_finite = match &entity.shape {
+        Shape::Circle | Shape::Polygon | Shape::Point | Shape::Line => true,
+        Shape::Sector { radius, half_angle } => radius.is_finite() && half_angle.is_finite(),
+        Shape::Capsule { a, b, radius } => is_finite(a) && is_finite(b) && radius.is_finite(),
+        Shape::OrientedBox {
+            half_width,
+            half_height,
+            rotation,
+        } => half_width.is_finite() && half_height.is_finite() && rotation.is_finite(),
+    };
+
+    shape_is_finite
+        && is_finite(&entity.position)
+        && entity.radius.is_finite()
+        && entity.vertices.iter().all(is_finite)
+        && entity.direction.x.is_finite()
+        && entity.direction.y.is_finite()
+}
+
+fn is_finite(position: &Position) -> bool {
+    position.x.is_finite() && position.y.is_finite()
+}
+
+// SVG y axis points down, adding zero avoids drawing -0.0
+fn flip(y: f32) -> f32 {
+    -y + 0.0
+}
+
+fn points(vertices: &[Position]) -> String {
+    vertices
+        .iter()
+        .map(|vertex| format!("{:.1},{:.1}", vertex.x, flip(vertex.y)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn title(entity: &Entity) -> String {
+    let name = entity
+        .name
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!("{:?} {} {}", entity.category, entity.id, name)
+}
+
+fn category_color(category: &Category) -> &'static str {
+    match category {
+        Category::Player => "#1f77b4",
+        Category::Projectile => "#d62728",
+        Category::Obstacle => "#7f7f7f",
+        Category::PowerUp => "#ffbf00",
+        Category::Pool => "#17becf",
+        Category::Item => "#2ca02c",
+        Category::Bush => "#3a7d44",
+        Category::Crate => "#8c564b",
+        Category::Trap => "#9467bd",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Direction;
+
+    fn circle(id: u64, x: f32, y: f32, radius: f32) -> Entity {
+        Entity {
+            shape: Shape::Circle,
+            radius,
+            ..Entity::new_point(id, Position { x, y })
+        }
+    }
+
+    fn position(x: f32, y: f32) -> Position {
+        Position { x, y }
+    }
+
+    // Every tag is closed in order and no number is NaN or infinite
+    fn assert_valid_svg(svg: &str) {
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        assert!(!svg.contains("NaN") && !svg.contains("inf"), "{}", svg);
+
+        let mut open_tags: Vec<&str> = Vec::new();
+        for tag in svg.split('<').skip(1) {
+            let tag = &tag[..tag.find('>').expect("Tags should be closed")];
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open_tags.pop(), Some(name));
+            } else if !tag.ends_with('/') {
+                open_tags.push(tag.split(' ').next().unwrap());
+            }
+        }
+        assert!(open_tags.is_empty());
+    }
+
+    #[test]
+    fn every_element_kind_is_drawn() {
+        let l_shape = Entity::new_polygon(
+            1,
+            vec![
+                position(0.0, 0.0),
+                position(0.0, 300.0),
+                position(100.0, 300.0),
+                position(100.0, 100.0),
+                position(300.0, 100.0),
+                position(300.0, 0.0),
+            ],
+        );
+        let square = Entity::new_polygon(
+            2,
+            vec![
+                position(1000.0, 0.0),
+                position(1000.0, 200.0),
+                position(1200.0, 200.0),
+                position(1200.0, 0.0),
+            ],
+        );
+        let mut player = circle(3, 1100.0, 220.0, 50.0);
+        player.category = Category::Player;
+        player.is_moving = true;
+        player.direction = Direction { x: 1.0, y: 0.0 };
+        let right = Direction { x: 1.0, y: 0.0 };
+
+        let scene = DebugScene {
+            external_wall: Some(circle(0, 0.0, 0.0, 5000.0)),
+            obstacles: vec![l_shape, square],
+            entities: vec![
+                player,
+                Entity::new_point(4, position(-500.0, 0.0)),
+                Entity::new_line(5, vec![position(-1000.0, 0.0), position(-1000.0, 500.0)]),
+                Entity::new_capsule(6, position(0.0, -1000.0), position(500.0, -1000.0), 40.0),
+                Entity::new_oriented_box(7, position(-2000.0, 0.0), right, 100.0, 50.0, 30.0),
+                Entity::new_sector(8, position(2000.0, 0.0), right, 400.0, 45.0),
+            ],
+            grid: Some(DebugGrid {
+                origin: position(-5000.0, -5000.0),
+                cell_size: 150.0,
+                columns: 4,
+                blocked: vec![false, true, true, false, true, false, false, false],
+            }),
+            paths: vec![vec![position(-3000.0, 0.0), position(3000.0, 0.0)]],
+        };
+
+        let svg = render_svg(&scene);
+
+        assert_valid_svg(&svg);
+        // One of each: wall, merged grid cells, concave obstacle pieces, entity shapes, normal, path
+        assert!(svg.contains(WALL_COLOR));
+        assert!(svg.contains("<rect x=\"-4850.0\" y=\"4850.0\" width=\"300.0\""));
+        assert!(svg.contains("stroke-dasharray"));
+        assert!(svg.contains("Player 3"));
+        assert!(svg.contains("<polyline points=\"-1000.0,0.0"));
+        assert!(svg.contains("stroke-linecap=\"round\""));
+        assert!(svg.contains("OrientedBox 7"));
+        assert!(svg.contains("<path d=\"M 2000.0 0.0"));
+        assert!(svg.contains("Entity 3 out of obstacle 2"));
+        assert!(svg.contains(PATH_COLOR));
+    }
+
+    #[test]
+    fn empty_and_non_finite_scenes_are_still_valid() {
+        assert_valid_svg(&render_svg(&DebugScene::default()));
+
+        let scene = DebugScene {
+            external_wall: Some(circle(0, 0.0, 0.0, f32::NAN)),
+            obstacles: vec![Entity::new_polygon(
+                1,
+                vec![
+                    position(0.0, 0.0),
+                    position(f32::NAN, 1.0),
+                    position(1.0, 0.0),
+                ],
+            )],
+            entities: vec![
+                circle(2, f32::INFINITY, 0.0, 10.0),
+                circle(3, 100.0, 100.0, 10.0),
+                Entity::new_sector(
+                    4,
+                    position(0.0, 0.0),
+                    Direction { x: 1.0, y: 0.0 },
+                    10.0,
+                    f32::NAN,
+                ),
+            ],
+            grid: Some(DebugGrid {
+                origin: position(f32::NAN, 0.0),
+                cell_size: 150.0,
+                columns: 1,
+                blocked: vec![true],
+            }),
+            paths: vec![
+                Vec::new(),
+                vec![position(0.0, f32::NAN), position(50.0, 50.0)],
+            ],
+        };
+
+        let svg = render_svg(&scene);
+
+        assert_valid_svg(&svg);
+        assert!(svg.contains("Obstacle 3"));
+    }
+}
@@ -0,0 +1,674 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::collision_detection::oriented_box::{
+    intersect_circle_oriented_box, oriented_box_collision, OrientedBox,
+};
+use crate::collision_detection::sat::{
+    intersect_circle_capsule, intersect_circle_polygon, intersect_circle_sector,
+};
+use crate::collision_detection::{
+    circle_circle_collision, circle_polygon_collision, line_circle_collision, line_line_collision,
+    line_point_collision, line_polygon_collision, point_circle_collision, point_polygon_collision,
+};
+use crate::position::{Direction, Position};
+use crate::spatial_query::{entities_overlap, Sector};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "nif", derive(rustler::NifMap))]
+pub struct Entity {
+    pub id: u64,
+    pub shape: Shape,
+    pub position: Position,
+    pub radius: f32,
+    pub vertices: Vec<Position>,
+    pub speed: f32,
+    pub category: Category,
+    pub direction: Direction,
+    pub is_moving: bool,
+    pub name: String,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "nif", derive(rustler::NifTaggedEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    Circle,
+    Polygon,
+    Point,
+    Line,
+    // Circular sector with the apex on the entity position, facing the entity direction and
+    // spanning `half_angle` degrees at each side of it
+    Sector {
+        radius: f32,
+        half_angle: f32,
+    },
+    // Every point within `radius` of the segment between `a` and `b`
+    Capsule {
+        a: Position,
+        b: Position,
+        radius: f32,
+    },
+    // Rectangle centered on the entity position, rotated `rotation` degrees counterclockwise from
+    // the entity direction, with the width along it
+    OrientedBox {
+        half_width: f32,
+        half_height: f32,
+        rotation: f32,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "nif", derive(rustler::NifTaggedEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Player,
+    Projectile,
+    Obstacle,
+    PowerUp,
+    Pool,
+    Item,
+    Bush,
+    Crate,
+    Trap,
+}
+
+impl Entity {
+    pub fn new_point(id: u64, position: Position) -> Entity {
+        Entity {
+            id,
+            shape: Shape::Point,
+            position,
+            radius: 0.0,
+            vertices: Vec::new(),
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction: Direction { x: 0.0, y: 0.0 },
+            is_moving: false,
+            name: format!("{}{}", "Point ", id),
+        }
+    }
+
+    pub fn new_line(id: u64, vertices: Vec<Position>) -> Entity {
+        Entity {
+            id,
+            shape: Shape::Line,
+            position: Position { x: 0.0, y: 0.0 },
+            radius: 0.0,
+            vertices,
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction: Direction { x: 0.0, y: 0.0 },
+            is_moving: false,
+            name: format!("{}{}", "Line ", id),
+        }
+    }
+
+    pub fn new_polygon(id: u64, vertices: Vec<Position>) -> Entity {
+        Entity {
+            id,
+            shape: Shape::Polygon,
+            position: Position { x: 0.0, y: 0.0 },
+            radius: 0.0,
+            vertices,
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction: Direction { x: 0.0, y: 0.0 },
+            is_moving: false,
+            name: format!("{}{}", "Polygon ", id),
+        }
+    }
+
+    pub fn new_sector(
+        id: u64,
+        position: Position,
+        direction: Direction,
+        radius: f32,
+        half_angle: f32,
+    ) -> Entity {
+        Entity {
+            id,
+            shape: Shape::Sector { radius, half_angle },
+            position,
+            radius,
+            vertices: Vec::new(),
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction,
+            is_moving: false,
+            name: format!("{}{}", "Sector ", id),
+        }
+    }
+
+    pub fn new_capsule(id: u64, a: Position, b: Position, radius: f32) -> Entity {
+        Entity {
+            id,
+            shape: Shape::Capsule { a, b, radius },
+            position: Position {
+                x: (a.x + b.x) / 2.0,
+                y: (a.y + b.y) / 2.0,
+            },
+            radius,
+            vertices: Vec::new(),
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction: Direction { x: 0.0, y: 0.0 },
+            is_moving: false,
+            name: format!("{}{}", "Capsule ", id),
+        }
+    }
+
+    pub fn new_oriented_box(
+        id: u64,
+        position: Position,
+        direction: Direction,
+        half_width: f32,
+        half_height: f32,
+        rotation: f32,
+    ) -> Entity {
+        Entity {
+            id,
+            shape: Shape::OrientedBox {
+                half_width,
+                half_height,
+                rotation,
+            },
+            position,
+            // Radius of the circle around the box, for code that only looks at radiuses
+            radius: (half_width.powi(2) + half_height.powi(2)).sqrt(),
+            vertices: Vec::new(),
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction,
+            is_moving: false,
+            name: format!("{}{}", "OrientedBox ", id),
+        }
+    }
+
+    pub fn collides_with(&mut self, entities: &Vec<Entity>) -> Vec<u64> {
+        let mut result = Vec::new();
+
+        for entity in entities {
+            if entity.id == self.id {
+                continue;
+            }
+
+            let self_shape = self.shape.clone();
+            let entity_shape = entity.shape.clone();
+
+            match (self_shape, entity_shape) {
+                (Shape::Circle, Shape::Circle) => {
+                    if circle_circle_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Circle, Shape::Polygon) => {
+                    if circle_polygon_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Circle, Shape::Point) => {
+                    if point_circle_collision(entity, self) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Circle, Shape::Line) => {
+                    if line_circle_collision(entity, self) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Point, Shape::Point) => {
+                    if self.position == entity.position {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Point, Shape::Line) => {
+                    if line_point_collision(entity, self) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Point, Shape::Circle) => {
+                    if point_circle_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Point, Shape::Polygon) => {
+                    if point_polygon_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Line, Shape::Circle) => {
+                    if line_circle_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+
+                (Shape::Line, Shape::Polygon) => {
+                    if line_polygon_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Line, Shape::Point) => {
+                    if line_point_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Line, Shape::Line) => {
+                    if line_line_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Polygon, Shape::Circle) => {
+                    if circle_polygon_collision(entity, self) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Polygon, Shape::Point) => {
+                    if point_polygon_collision(entity, self) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Polygon, Shape::Line) => {
+                    if line_polygon_collision(entity, self) {
+                        result.push(entity.id);
+                    }
+                }
+                // Exact test that also finds polygons inside one another, concave ones included
+                (Shape::Polygon, Shape::Polygon) => {
+                    if entities_overlap(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::OrientedBox { .. }, _) | (_, Shape::OrientedBox { .. }) => {
+                    if oriented_box_collision(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+                (Shape::Sector { .. } | Shape::Capsule { .. }, _)
+                | (_, Shape::Sector { .. } | Shape::Capsule { .. }) => {
+                    if entities_overlap(self, entity) {
+                        result.push(entity.id);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn move_entity(&mut self, delta_time: f32) {
+        self.position = self.next_position(delta_time);
+    }
+
+    pub fn next_position(&self, delta_time: f32) -> Position {
+        Position {
+            x: self.position.x + self.direction.x * self.speed * delta_time,
+            y: self.position.y + self.direction.y * self.speed * delta_time,
+        }
+    }
+
+    pub fn move_entity_to_direction(&mut self, direction: Position, amount: f32) {
+        let position = Position {
+            x: self.position.x + direction.x * amount,
+            y: self.position.y + direction.y * amount,
+        };
+
+        self.position = position;
+    }
+
+    pub fn move_to_next_valid_position_inside(&mut self, external_wall: &Entity) {
+        self.position = self.find_edge_position_inside(external_wall);
+    }
+
+    pub fn move_to_next_valid_position_outside(
+        &mut self,
+        collided_with: Vec<&Entity>,
+        obstacles: &HashMap<u64, Entity>,
+        external_wall: &Entity,
+    ) {
+        for entity in collided_with {
+            match entity.shape {
+                Shape::Circle => {
+                    let mut normalized_direction = Position::sub(&self.position, &entity.position);
+                    normalized_direction.normalize();
+
+                    let new_pos = Position {
+                        x: entity.position.x
+                            + normalized_direction.x * entity.radius
+                            + normalized_direction.x * self.radius,
+                        y: entity.position.y
+                            + normalized_direction.y * entity.radius
+                            + normalized_direction.y * self.radius,
+                    };
+
+                    self.position = new_pos;
+                }
+                Shape::Polygon => {
+                    let (collided, direction, depth) =
+                        intersect_circle_polygon(self, entity, obstacles, external_wall);
+
+                    if collided {
+                        let new_pos = Position {
+                            x: self.position.x + direction.x * depth,
+                            y: self.position.y + direction.y * depth,
+                        };
+                        self.position = new_pos;
+                    }
+                }
+                Shape::Sector { .. } | Shape::Capsule { .. } => {
+                    let (collided, direction, depth) = match entity.shape {
+                        Shape::Sector { .. } => intersect_circle_sector(self, entity),
+                        _ => intersect_circle_capsule(self, entity),
+                    };
+
+                    if collided {
+                        self.position =
+                            Position::add(&self.position, &Position::mult(&direction, depth));
+                    }
+                }
+                Shape::OrientedBox { .. } => {
+                    let Some(oriented_box) = OrientedBox::of(entity) else {
+                        continue;
+                    };
+                    let (collided, direction, depth) =
+                        intersect_circle_oriented_box(self, &oriented_box);
+
+                    if collided {
+                        self.position =
+                            Position::add(&self.position, &Position::mult(&direction, depth));
+                    }
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    /*
+     * Brings players, power ups and items back inside the map and out of the obstacles they
+     * overlap with, other categories are left where they are
+     */
+    pub fn move_to_closest_available_position(
+        &mut self,
+        external_wall: &Entity,
+        obstacles: &HashMap<u64, Entity>,
+    ) {
+        let process_entity: bool = self.category == Category::Player
+            || self.category == Category::PowerUp
+            || self.category == Category::Item;
+
+        if process_entity && !self.is_inside_map(external_wall) {
+            self.move_to_next_valid_position_inside(external_wall);
+        }
+
+        let collides_with = self.collides_with(&obstacles.clone().into_values().collect());
+
+        if process_entity && !collides_with.is_empty() {
+            let collided_with: Vec<&Entity> = collides_with
+                .iter()
+                .map(|id| obstacles.get(id).unwrap())
+                .collect();
+            self.move_to_next_valid_position_outside(collided_with, obstacles, external_wall);
+        }
+    }
+
+    pub fn find_edge_position_inside(&mut self, external_wall: &Entity) -> Position {
+        let x = self.position.x;
+        let y = self.position.y;
+        let length = (x.powf(2.) + y.powf(2.)).sqrt();
+        let normalized_position = Position {
+            x: self.position.x / length,
+            y: self.position.y / length,
+        };
+        Position {
+            x: external_wall.position.x
+                + normalized_position.x * (external_wall.radius - self.radius),
+            y: external_wall.position.y
+                + normalized_position.y * (external_wall.radius - self.radius),
+        }
+    }
+
+    pub fn is_inside_map(&self, external_wall: &Entity) -> bool {
+        match self.shape {
+            Shape::Circle => {
+                let center_dist = ((external_wall.position.x - self.position.x).powi(2)
+                    + (external_wall.position.y - self.position.y).powi(2))
+                .sqrt();
+                external_wall.radius > center_dist + self.radius
+            }
+            Shape::Polygon | Shape::Line => {
+                for vertice in &external_wall.vertices {
+                    if !is_vertice_inside_circle(
+                        vertice,
+                        &external_wall.position,
+                        external_wall.radius,
+                    ) {
+                        return false;
+                    }
+                }
+                true
+            }
+            Shape::Point => is_vertice_inside_circle(
+                &self.position,
+                &external_wall.position,
+                external_wall.radius,
+            ),
+            Shape::Capsule { a, b, radius } => [a, b].iter().all(|vertice| {
+                is_vertice_inside_circle(
+                    vertice,
+                    &external_wall.position,
+                    external_wall.radius - radius,
+                )
+            }),
+            // The point of the sector farthest from the wall center is either a corner or the
+            // point of the arc in the direction from the wall center to the apex
+            Shape::Sector { radius, .. } => {
+                let Some(sector) = Sector::of(self) else {
+                    return false;
+                };
+                let mut outwards = Position::sub(&sector.apex, &external_wall.position);
+                let mut farthest = vec![sector.apex, sector.edges[0], sector.edges[1]];
+                if outwards.x != 0.0 || outwards.y != 0.0 {
+                    outwards.normalize();
+                    let arc_point = Position::add(&sector.apex, &Position::mult(&outwards, radius));
+                    if sector.in_wedge(&arc_point) {
+                        farthest.push(arc_point);
+                    }
+                }
+
+                farthest.iter().all(|vertice| {
+                    is_vertice_inside_circle(vertice, &external_wall.position, external_wall.radius)
+                })
+            }
+            Shape::OrientedBox { .. } => OrientedBox::of(self).is_some_and(|oriented_box| {
+                oriented_box.corners().iter().all(|vertice| {
+                    is_vertice_inside_circle(vertice, &external_wall.position, external_wall.radius)
+                })
+            }),
+        }
+    }
+
+    // Smallest axis aligned box containing the entity, as its bottom left and top right corners
+    pub fn bounding_box(&self) -> (Position, Position) {
+        let around = |center: &Position, radius: f32| {
+            (
+                Position {
+                    x: center.x - radius,
+                    y: center.y - radius,
+                },
+                Position {
+                    x: center.x + radius,
+                    y: center.y + radius,
+                },
+            )
+        };
+        let enclosing = |points: &[Position]| {
+            points.iter().fold(
+                (
+                    Position {
+                        x: f32::INFINITY,
+                        y: f32::INFINITY,
+                    },
+                    Position {
+                        x: f32::NEG_INFINITY,
+                        y: f32::NEG_INFINITY,
+                    },
+                ),
+                |(min, max), point| {
+                    (
+                        Position {
+                            x: min.x.min(point.x),
+                            y: min.y.min(point.y),
+                        },
+                        Position {
+                            x: max.x.max(point.x),
+                            y: max.y.max(point.y),
+                        },
+                    )
+                },
+            )
+        };
+
+        match &self.shape {
+            Shape::Circle | Shape::Point => around(&self.position, self.radius),
+            Shape::Polygon | Shape::Line => enclosing(&self.vertices),
+            Shape::Capsule { a, b, radius } => {
+                let (min, max) = enclosing(&[*a, *b]);
+                (
+                    Position::sub(
+                        &min,
+                        &Position {
+                            x: *radius,
+                            y: *radius,
+                        },
+                    ),
+                    Position::add(
+                        &max,
+                        &Position {
+                            x: *radius,
+                            y: *radius,
+                        },
+                    ),
+                )
+            }
+            // Corners plus the points of the arc reaching the farthest along each axis
+            Shape::Sector { radius, .. } => {
+                let Some(sector) = Sector::of(self) else {
+                    return around(&self.position, self.radius);
+                };
+                let (apex_min, apex_max) = around(&sector.apex, *radius);
+                let mut points = vec![sector.apex, sector.edges[0], sector.edges[1]];
+                points.extend(
+                    [
+                        Position {
+                            x: apex_min.x,
+                            y: sector.apex.y,
+                        },
+                        Position {
+                            x: apex_max.x,
+                            y: sector.apex.y,
+                        },
+                        Position {
+                            x: sector.apex.x,
+                            y: apex_min.y,
+                        },
+                        Position {
+                            x: sector.apex.x,
+                            y: apex_max.y,
+                        },
+                    ]
+                    .into_iter()
+                    .filter(|point| sector.in_wedge(point)),
+                );
+                enclosing(&points)
+            }
+            Shape::OrientedBox { .. } => match OrientedBox::of(self) {
+                Some(oriented_box) => enclosing(&oriented_box.corners()),
+                None => around(&self.position, self.radius),
+            },
+        }
+    }
+}
+
+pub fn is_vertice_inside_circle(
+    vertice: &Position,
+    circle_center: &Position,
+    circle_radius: f32,
+) -> bool {
+    let circle_center_dist =
+        ((vertice.x - circle_center.x).powi(2) + (vertice.y - circle_center.y).powi(2)).sqrt();
+    circle_center_dist < circle_radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(x: f32, y: f32) -> Position {
+        Position { x, y }
+    }
+
+    fn circle(id: u64, x: f32, y: f32, radius: f32) -> Entity {
+        Entity {
+            shape: Shape::Circle,
+            radius,
+            ..Entity::new_point(id, position(x, y))
+        }
+    }
+
+    fn square(id: u64, min_x: f32, min_y: f32, side: f32) -> Entity {
+        Entity::new_polygon(
+            id,
+            vec![
+                position(min_x, min_y),
+                position(min_x, min_y + side),
+                position(min_x + side, min_y + side),
+                position(min_x + side, min_y),
+            ],
+        )
+    }
+
+    #[test]
+    fn every_pair_of_basic_shapes_is_checked() {
+        let entities = vec![
+            circle(1, 0.0, 0.0, 100.0),
+            Entity::new_point(2, position(50.0, 0.0)),
+            Entity::new_line(3, vec![position(-200.0, 50.0), position(200.0, 50.0)]),
+            square(4, 80.0, -20.0, 100.0),
+            // Far from everything else
+            Entity::new_point(5, position(1000.0, 1000.0)),
+            Entity::new_line(6, vec![position(900.0, 0.0), position(900.0, 300.0)]),
+            square(7, 2000.0, 2000.0, 100.0),
+        ];
+        let collisions = |mut entity: Entity| {
+            let mut found = entity.collides_with(&entities);
+            found.sort();
+            found
+        };
+
+        assert_eq!(collisions(entities[0].clone()), vec![2, 3, 4]);
+        assert_eq!(collisions(entities[1].clone()), vec![1]);
+        assert_eq!(collisions(entities[2].clone()), vec![1, 4]);
+        assert_eq!(collisions(entities[3].clone()), vec![1, 3]);
+        assert!(collisions(entities[4].clone()).is_empty());
+        assert!(collisions(entities[5].clone()).is_empty());
+        assert!(collisions(entities[6].clone()).is_empty());
+
+        // Points on points, points on lines, crossing lines and polygons inside polygons
+        assert_eq!(
+            collisions(Entity::new_point(8, position(50.0, 0.0))),
+            vec![1, 2]
+        );
+        assert_eq!(
+            collisions(Entity::new_point(8, position(900.0, 100.0))),
+            vec![6]
+        );
+        assert_eq!(
+            collisions(Entity::new_line(
+                8,
+                vec![position(800.0, 200.0), position(1000.0, 200.0)]
+            )),
+            vec![6]
+        );
+        assert_eq!(collisions(square(8, 2025.0, 2025.0, 50.0)), vec![7]);
+    }
+}
@@ -0,0 +1,22 @@
+/*
+ * Shapes and collision math shared by the arena physics and the bots pathfinding.
+ * It's plain Rust, with the `nif` feature the entity types can also be passed to and from Elixir
+ * so the NIF crates use them as they are.
+ */
+
+pub mod collision_detection;
+pub mod debug_svg;
+pub mod entity;
+pub mod map_file;
+pub mod map_validation;
+pub mod position;
+pub mod spatial_query;
+pub mod tiled;
+
+pub use debug_svg::{render_svg, DebugGrid, DebugScene};
+pub use entity::{Category, Entity, Shape};
+pub use map_file::{load_map, Map};
+pub use map_validation::ValidationReport;
+pub use position::{Direction, Position};
+pub use spatial_query::{query, QueryArea, QueryFilter};
+pub use tiled::import_tiled_map;
@@ -0,0 +1,301 @@
+use serde::{de, Deserialize, Deserializer};
+
+use crate::entity::{Category, Entity, Shape};
+use crate::map_validation::{self, ValidationReport};
+use crate::position::{Direction, Position};
+
+/*
+ * Map file format
+ *
+ * Maps are JSON files with the version of the format they follow, so old files can keep being
+ * loaded when the format changes. Version 1 looks like:
+ *
+ * {
+ *   "version": 1,
+ *   "name": "Araban",
+ *   "radius": 15000,
+ *   "spawn_points": [{"x": 0, "y": 5000}],
+ *   "obstacles": [
+ *     {"name": "Rock", "shape": "circle", "position": {"x": 0, "y": 0}, "radius": 400},
+ *     {"name": "Box", "shape": "polygon", "vertices": [...], "category": "crate"}
+ *   ],
+ *   "bushes": [...],
+ *   "pools": [...]
+ * }
+ *
+ * The external wall is a circle of `radius` centered on the origin. Obstacles, bushes and pools are
+ * circles or polygons, obstacles can set any category and default to `obstacle`. Numbers can also
+ * come as strings, which is how the configurator encodes its decimals.
+ *
+ * Loading fails on anything that can't become a valid entity. Maps that load but are likely wrong
+ * come with a `ValidationReport` listing the problems.
+ */
+
+pub const CURRENT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "nif", derive(rustler::NifMap))]
+pub struct Map {
+    pub version: u32,
+    pub name: String,
+    pub external_wall: Entity,
+    pub spawn_points: Vec<Position>,
+    pub obstacles: Vec<Entity>,
+    pub bushes: Vec<Entity>,
+    pub pools: Vec<Entity>,
+}
+
+#[derive(Deserialize)]
+struct VersionedMapFile {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct MapFileV1 {
+    #[serde(default)]
+    name: String,
+    #[serde(deserialize_with = "decimal")]
+    radius: f32,
+    #[serde(default)]
+    spawn_points: Vec<PositionV1>,
+    #[serde(default)]
+    obstacles: Vec<ShapeV1>,
+    #[serde(default)]
+    bushes: Vec<ShapeV1>,
+    #[serde(default)]
+    pools: Vec<ShapeV1>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct PositionV1 {
+    #[serde(deserialize_with = "decimal")]
+    x: f32,
+    #[serde(deserialize_with = "decimal")]
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct ShapeV1 {
+    #[serde(default)]
+    name: String,
+    shape: Shape,
+    #[serde(default)]
+    position: Option<PositionV1>,
+    #[serde(default, deserialize_with = "decimal")]
+    radius: f32,
+    #[serde(default)]
+    vertices: Vec<PositionV1>,
+    #[serde(default)]
+    category: Option<Category>,
+}
+
+pub fn load_map(json: &str) -> Result<(Map, ValidationReport), String> {
+    let versioned: VersionedMapFile =
+        serde_json::from_str(json).map_err(|error| format!("Invalid map file: {}", error))?;
+
+    let map = match versioned.version {
+        1 => load_v1(json)?,
+        version => {
+            return Err(format!(
+                "Unsupported map version {}, the latest one is {}",
+                version, CURRENT_VERSION
+            ))
+        }
+    };
+
+    let report = map_validation::validate(&map);
+    Ok((map, report))
+}
+
+fn load_v1(json: &str) -> Result<Map, String> {
+    let file: MapFileV1 =
+        serde_json::from_str(json).map_err(|error| format!("Invalid map file: {}", error))?;
+
+    if file.radius <= 0.0 {
+        return Err("The map radius must be positive".to_string());
+    }
+
+    // Ids are given in order like the game does, the external wall takes 0
+    let mut last_id = 0;
+    let mut entities = |shapes: Vec<ShapeV1>, kind: &str, default_category: Category| {
+        shapes
+            .into_iter()
+            .enumerate()
+            .map(|(index, shape)| {
+                last_id += 1;
+                shape
+                    .into_entity(last_id, default_category.clone())
+                    .map_err(|error| format!("Invalid {} {}: {}", kind, index, error))
+            })
+            .collect::<Result<Vec<Entity>, String>>()
+    };
+
+    let obstacles = entities(file.obstacles, "obstacle", Category::Obstacle)?;
+    let bushes = entities(file.bushes, "bush", Category::Bush)?;
+    let pools = entities(file.pools, "pool", Category::Pool)?;
+
+    Ok(Map {
+        version: 1,
+        name: file.name,
+        external_wall: Entity {
+            id: 0,
+            shape: Shape::Circle,
+            position: Position { x: 0.0, y: 0.0 },
+            radius: file.radius,
+            vertices: Vec::new(),
+            speed: 0.0,
+            category: Category::Obstacle,
+            direction: Direction { x: 0.0, y: 0.0 },
+            is_moving: false,
+            name: "ExternalWall".to_string(),
+        },
+        spawn_points: file.spawn_points.into_iter().map(Into::into).collect(),
+        obstacles,
+        bushes,
+        pools,
+    })
+}
+
+impl ShapeV1 {
+    fn into_entity(self, id: u64, default_category: Category) -> Result<Entity, String> {
+        let vertices: Vec<Position> = self.vertices.into_iter().map(Into::into).collect();
+
+        let position = match self.shape {
+            Shape::Circle => {
+                if self.radius <= 0.0 {
+                    return Err("circles need a positive radius".to_string());
+                }
+                self.position
+                    .ok_or("circles need a position".to_string())?
+                    .into()
+            }
+            Shape::Polygon => {
+                if vertices.len() < 3 {
+                    return Err("polygons need at least 3 vertices".to_string());
+                }
+                // Polygons are positioned on their centroid when the file doesn't say otherwise
+                match self.position {
+                    Some(position) => position.into(),
+                    None => Position::mult(
+                        &vertices
+                            .iter()
+                            .fold(Position { x: 0.0, y: 0.0 }, |sum, vertex| {
+                                Position::add(&sum, vertex)
+                            }),
+                        1.0 / vertices.len() as f32,
+                    ),
+                }
+            }
+            Shape::Point
+            | Shape::Line
+            | Shape::Sector { .. }
+            | Shape::Capsule { .. }
+            | Shape::OrientedBox { .. } => {
+                return Err("only circles and polygons are supported".to_string())
+            }
+        };
+
+        if vertices
+            .iter()
+            .chain([&position])
+            .any(|vertex| !vertex.x.is_finite() || !vertex.y.is_finite())
+        {
+            return Err("coordinates must be finite numbers".to_string());
+        }
+
+        Ok(Entity {
+            id,
+            shape: self.shape,
+            position,
+            radius: self.radius,
+            vertices,
+            speed: 0.0,
+            category: self.category.unwrap_or(default_category),
+            direction: Direction { x: 0.0, y: 0.0 },
+            is_moving: false,
+            name: self.name,
+        })
+    }
+}
+
+impl From<PositionV1> for Position {
+    fn from(position: PositionV1) -> Self {
+        Position {
+            x: position.x,
+            y: position.y,
+        }
+    }
+}
+
+// Numbers as JSON numbers or as strings
+fn decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Decimal {
+        Number(f32),
+        Text(String),
+    }
+
+    match Decimal::deserialize(deserializer)? {
+        Decimal::Number(number) => Ok(number),
+        Decimal::Text(text) => text.trim().parse().map_err(de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_report_category_is_filled_when_loading() {
+        let json = r#"{
+            "version": 1,
+            "name": "Broken",
+            "radius": "5000",
+            "spawn_points": [{"x": 3000, "y": 0}, {"x": -3000, "y": 0}, {"x": 0, "y": "0.5"}],
+            "obstacles": [
+                {"shape": "circle", "position": {"x": 0, "y": 0}, "radius": 300},
+                {"shape": "circle", "position": {"x": 250, "y": 0}, "radius": 100},
+                {"shape": "polygon", "vertices": [
+                    {"x": 1000, "y": 1000}, {"x": 1200, "y": 1100},
+                    {"x": 1000, "y": 1200}, {"x": 1050, "y": 1100}
+                ]}
+            ],
+            "pools": [{"shape": "circle", "position": {"x": 0, "y": 4950}, "radius": "100"}]
+        }"#;
+
+        let (map, report) = load_map(json).unwrap();
+
+        assert_eq!(map.external_wall.radius, 5000.0);
+        assert_eq!(map.pools[0].category, Category::Pool);
+        assert_eq!(report.overlapping_obstacles, vec![(1, 2)]);
+        assert_eq!(report.outside_wall, vec![4]);
+        assert_eq!(report.unreachable_spawn_points, vec![2]);
+        assert_eq!(report.non_convex_polygons, vec![3]);
+    }
+
+    #[test]
+    fn files_that_can_not_become_a_map_fail_to_load() {
+        let unsupported_version = r#"{"version": 2, "radius": 5000}"#;
+        let unsupported_shape = r#"{
+            "version": 1,
+            "radius": 5000,
+            "obstacles": [{"shape": "point", "position": {"x": 0, "y": 0}}]
+        }"#;
+        let too_few_vertices = r#"{
+            "version": 1,
+            "radius": 5000,
+            "bushes": [{"shape": "polygon", "vertices": [{"x": 0, "y": 0}, {"x": 1, "y": 1}]}]
+        }"#;
+
+        assert!(load_map(unsupported_version).is_err());
+        assert_eq!(
+            load_map(unsupported_shape).unwrap_err(),
+            "Invalid obstacle 0: only circles and polygons are supported"
+        );
+        assert_eq!(
+            load_map(too_few_vertices).unwrap_err(),
+            "Invalid bush 0: polygons need at least 3 vertices"
+        );
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::collision_detection::oriented_box::oriented_box_collision;
+use crate::collision_detection::{circle_circle_collision, circle_polygon_collision};
+use crate::entity::{Entity, Shape};
+use crate::map_file::Map;
+use crate::position::Position;
+use crate::spatial_query::entities_overlap;
+
+/*
+ * Checks for maps that load fine but are likely wrong. None of them stop a match from running,
+ * so they are reported instead of failing the load.
+ */
+
+// Size of the cells used to check which spawn points reach each other, the same as the bots grid
+const REACHABILITY_CELL_SIZE: f32 = 150.0;
+// Overlaps smaller than this are considered touching
+const OVERLAP_TOLERANCE: f32 = 0.01;
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ValidationReport {
+    // Pairs of obstacle ids whose shapes overlap
+    pub overlapping_obstacles: Vec<(u64, u64)>,
+    // Ids of the obstacles, bushes and pools that aren't completely inside the external wall
+    pub outside_wall: Vec<u64>,
+    // Indexes of the spawn points that can't walk to most of the others, or that are blocked
+    pub unreachable_spawn_points: Vec<usize>,
+    // Ids of the obstacles that aren't convex, the physics needs them triangulated
+    pub non_convex_polygons: Vec<u64>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.overlapping_obstacles.is_empty()
+            && self.outside_wall.is_empty()
+            && self.unreachable_spawn_points.is_empty()
+            && self.non_convex_polygons.is_empty()
+    }
+}
+
+pub fn validate(map: &Map) -> ValidationReport {
+    let mut overlapping_obstacles = Vec::new();
+    for (index, obstacle) in map.obstacles.iter().enumerate() {
+        for other_obstacle in &map.obstacles[index + 1..] {
+            if shapes_overlap(obstacle, other_obstacle) {
+                overlapping_obstacles.push((obstacle.id, other_obstacle.id));
+            }
+        }
+    }
+
+    let outside_wall = map
+        .obstacles
+        .iter()
+        .chain(&map.bushes)
+        .chain(&map.pools)
+        .filter(|entity| !is_inside_wall(entity, &map.external_wall))
+        .map(|entity| entity.id)
+        .collect();
+
+    let non_convex_polygons = map
+        .obstacles
+        .iter()
+        .filter(|obstacle| obstacle.shape == Shape::Polygon && !is_convex(&obstacle.vertices))
+        .map(|obstacle| obstacle.id)
+        .collect();
+
+    ValidationReport {
+        overlapping_obstacles,
+        outside_wall,
+        unreachable_spawn_points: unreachable_spawn_points(map),
+        non_convex_polygons,
+    }
+}
+
+fn shapes_overlap(entity: &Entity, other_entity: &Entity) -> bool {
+    match (&entity.shape, &other_entity.shape) {
+        (Shape::Circle, Shape::Circle) => circle_circle_collision(entity, other_entity),
+        (Shape::Circle, Shape::Polygon) => circle_polygon_collision(entity, other_entity),
+        (Shape::Polygon, Shape::Circle) => circle_polygon_collision(other_entity, entity),
+        (Shape::Polygon, Shape::Polygon) => {
+            polygons_overlap(&entity.vertices, &other_entity.vertices)
+        }
+        (Shape::OrientedBox { .. }, _) | (_, Shape::OrientedBox { .. }) => {
+            oriented_box_collision(entity, other_entity)
+        }
+        // Sectors, capsules, points and lines
+        _ => entities_overlap(entity, other_entity),
+    }
+}
+
+/*
+ * Separating axis test, the polygons overlap unless their projections on the normal of one of
+ * their edges are apart. Polygons sharing an edge only touch, so they don't count as overlapping.
+ * Non convex polygons are tested with their convex hull, which may report a few false overlaps.
+ */
+fn polygons_overlap(vertices: &[Position], other_vertices: &[Position]) -> bool {
+    let edges = |vertices: &[Position]| {
+        (0..vertices.len())
+            .map(|index| Position::sub(&vertices[(index + 1) % vertices.len()], &vertices[index]))
+            .collect::<Vec<Position>>()
+    };
+
+    edges(vertices)
+        .into_iter()
+        .chain(edges(other_vertices))
+        .all(|edge| {
+            let mut normal = Position {
+                x: -edge.y,
+                y: edge.x,
+            };
+            if normal.x == 0.0 && normal.y == 0.0 {
+                return true;
+            }
+            normal.normalize();
+
+            let (min, max) = projection(vertices, &normal);
+            let (other_min, other_max) = projection(other_vertices, &normal);
+            max.min(other_max) - min.max(other_min) > OVERLAP_TOLERANCE
+        })
+}
+
+fn projection(vertices: &[Position], axis: &Position) -> (f32, f32) {
+    vertices
+        .iter()
+        .map(|vertex| vertex.x * axis.x + vertex.y * axis.y)
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        })
+}
+
+fn is_inside_wall(entity: &Entity, external_wall: &Entity) -> bool {
+    match entity.shape {
+        Shape::Circle => {
+            entity
+                .position
+                .distance_to_position(&external_wall.position)
+                + entity.radius
+                <= external_wall.radius
+        }
+        _ => entity.vertices.iter().all(|vertex| {
+            vertex.distance_to_position(&external_wall.position) <= external_wall.radius
+        }),
+    }
+}
+
+// Convex polygons turn the same way on every vertex, no matter their winding
+fn is_convex(vertices: &[Position]) -> bool {
+    let mut turns = (0..vertices.len()).map(|index| {
+        let previous = vertices[(index + vertices.len() - 1) % vertices.len()];
+        let current = vertices[index];
+        let next = vertices[(index + 1) % vertices.len()];
+
+        let to_previous = Position::sub(&previous, &current);
+        let to_next = Position::sub(&next, &current);
+        to_previous.x * to_next.y - to_previous.y * to_next.x
+    });
+
+    let has_left_turns = turns.clone().any(|turn| turn > 0.0);
+    let has_right_turns = turns.any(|turn| turn < 0.0);
+    !(has_left_turns && has_right_turns)
+}
+
+/*
+ * The map inside the external wall is split in cells and the ones whose center is free are
+ * flood filled, moving between neighbors only when nothing blocks the way between their centers.
+ *
+ * Spawn points are expected to be in the same region, the one with most of them. The ones in other
+ * regions, inside obstacles or outside the wall are unreachable.
+ */
+fn unreachable_spawn_points(map: &Map) -> Vec<usize> {
+    let grid = ReachabilityGrid::new(map);
+
+    let spawn_regions: Vec<Option<usize>> = map
+        .spawn_points
+        .iter()
+        .map(|spawn_point| grid.region_at(spawn_point))
+        .collect();
+
+    let mut spawns_per_region: Vec<(usize, usize)> = Vec::new();
+    for region in spawn_regions.iter().flatten() {
+        match spawns_per_region
+            .iter_mut()
+            .find(|(other_region, _)| other_region == region)
+        {
+            Some((_, count)) => *count += 1,
+            None => spawns_per_region.push((*region, 1)),
+        }
+    }
+    // Ties go to the region of the earliest spawn point
+    let main_region = spawns_per_region
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(region, _)| *region);
+
+    spawn_regions
+        .iter()
+        .enumerate()
+        .filter(|(_, region)| region.is_none() || **region != main_region)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+struct ReachabilityGrid<'a> {
+    map: &'a Map,
+    obstacle_bounding_boxes: Vec<(Position, Position)>,
+    size: usize,
+    origin: Position,
+    regions: Vec<Option<usize>>,
+}
+
+impl<'a> ReachabilityGrid<'a> {
+    fn new(map: &'a Map) -> ReachabilityGrid<'a> {
+        let radius = map.external_wall.radius;
+        let size = (2.0 * radius / REACHABILITY_CELL_SIZE).ceil() as usize;
+        let mut grid = ReachabilityGrid {
+            map,
+            obstacle_bounding_boxes: map.obstacles.iter().map(Entity::bounding_box).collect(),
+            size,
+            origin: Position {
+                x: map.external_wall.position.x - radius,
+                y: map.external_wall.position.y - radius,
+            },
+            regions: vec![None; size * size],
+        };
+
+        let free: Vec<bool> = (0..size * size)
+            .map(|index| grid.is_free(&grid.center(index)))
+            .collect();
+
+        let mut next_region = 0;
+        for start in 0..size * size {
+            if !free[start] || grid.regions[start].is_some() {
+                continue;
+            }
+
+            grid.regions[start] = Some(next_region);
+            let mut queue = VecDeque::from([start]);
+            while let Some(index) = queue.pop_front() {
+                let (row, col) = (index / size, index % size);
+                let neighbors = [
+                    (row > 0).then(|| index - size),
+                    (row + 1 < size).then(|| index + size),
+                    (col > 0).then(|| index - 1),
+                    (col + 1 < size).then(|| index + 1),
+                ];
+
+                for neighbor in neighbors.into_iter().flatten() {
+                    if free[neighbor]
+                        && grid.regions[neighbor].is_none()
+                        && grid.is_path_free(&grid.center(index), &grid.center(neighbor))
+                    {
+                        grid.regions[neighbor] = Some(next_region);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+
+            next_region += 1;
+        }
+
+        grid
+    }
+
+    // Region of the position, taken from the closest cell around it that it can walk to
+    fn region_at(&self, position: &Position) -> Option<usize> {
+        if !self.is_free(position) {
+            return None;
+        }
+
+        let col = ((position.x - self.origin.x) / REACHABILITY_CELL_SIZE).floor() as i64;
+        let row = ((position.y - self.origin.y) / REACHABILITY_CELL_SIZE).floor() as i64;
+        let size = self.size as i64;
+
+        let mut cells: Vec<usize> = (0..9)
+            .map(|offset| (row + offset / 3 - 1, col + offset % 3 - 1))
+            .filter(|(row, col)| *row >= 0 && *col >= 0 && *row < size && *col < size)
+            .map(|(row, col)| (row * size + col) as usize)
+            .collect();
+        cells.sort_by(|cell, other_cell| {
+            let distance = self.center(*cell).distance_to_position(position);
+            let other_distance = self.center(*other_cell).distance_to_position(position);
+            distance.total_cmp(&other_distance)
+        });
+
+        cells
+            .into_iter()
+            .find(|cell| {
+                self.regions[*cell].is_some() && self.is_path_free(position, &self.center(*cell))
+            })
+            .and_then(|cell| self.regions[cell])
+    }
+
+    fn center(&self, index: usize) -> Position {
+        Position {
+            x: self.origin.x + ((index % self.size) as f32 + 0.5) * REACHABILITY_CELL_SIZE,
+            y: self.origin.y + ((index / self.size) as f32 + 0.5) * REACHABILITY_CELL_SIZE,
+        }
+    }
+
+    fn is_free(&self, position: &Position) -> bool {
+        position.distance_to_position(&self.map.external_wall.position)
+            < self.map.external_wall.radius
+            && Entity::new_point(0, *position)
+                .collides_with(&self.obstacles_around(position, position))
+                .is_empty()
+    }
+
+    fn is_path_free(&self, from: &Position, to: &Position) -> bool {
+        Entity::new_line(0, vec![*from, *to])
+            .collides_with(&self.obstacles_around(from, to))
+            .is_empty()
+    }
+
+    // Obstacles whose bounding box touches the one of the segment, the rest can't collide with it
+    fn obstacles_around(&self, from: &Position, to: &Position) -> Vec<Entity> {
+        self.map
+            .obstacles
+            .iter()
+            .zip(&self.obstacle_bounding_boxes)
+            .filter(|(_, (min, max))| {
+                min.x <= from.x.max(to.x)
+                    && max.x >= from.x.min(to.x)
+                    && min.y <= from.y.max(to.y)
+                    && max.y >= from.y.min(to.y)
+            })
+            .map(|(obstacle, _)| obstacle.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Category;
+    use crate::position::Direction;
+
+    const MAP_RADIUS: f32 = 5000.0;
+
+    fn circle(id: u64, x: f32, y: f32, radius: f32) -> Entity {
+        Entity {
+            shape: Shape::Circle,
+            radius,
+            ..Entity::new_point(id, Position { x, y })
+        }
+    }
+
+    fn square(id: u64, min_x: f32, min_y: f32, side: f32) -> Entity {
+        Entity::new_polygon(
+            id,
+            vec![
+                Position { x: min_x, y: min_y },
+                Position {
+                    x: min_x,
+                    y: min_y + side,
+                },
+                Position {
+                    x: min_x + side,
+                    y: min_y + side,
+                },
+                Position {
+                    x: min_x + side,
+                    y: min_y,
+                },
+            ],
+        )
+    }
+
+    fn map(obstacles: Vec<Entity>, spawn_points: Vec<Position>) -> Map {
+        Map {
+            version: 1,
+            name: "Test".to_string(),
+            external_wall: circle(0, 0.0, 0.0, MAP_RADIUS),
+            spawn_points,
+            obstacles,
+            bushes: Vec::new(),
+            pools: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn overlapping_obstacles_are_reported() {
+        let report = validate(&map(
+            vec![
+                circle(1, 0.0, 0.0, 200.0),
+                square(2, 150.0, -50.0, 100.0),
+                // Shares an edge with the square, touching isn't overlapping
+                square(3, 250.0, -50.0, 100.0),
+            ],
+            Vec::new(),
+        ));
+
+        assert_eq!(report.overlapping_obstacles, vec![(1, 2)]);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn entities_outside_the_wall_are_reported() {
+        let mut map = map(
+            vec![
+                circle(1, MAP_RADIUS - 100.0, 0.0, 200.0),
+                square(2, 0.0, 0.0, 100.0),
+            ],
+            Vec::new(),
+        );
+        let mut bush = square(3, 0.0, MAP_RADIUS - 50.0, 100.0);
+        bush.category = Category::Bush;
+        map.bushes.push(bush);
+
+        let report = validate(&map);
+
+        assert_eq!(report.outside_wall, vec![1, 3]);
+        assert!(report.overlapping_obstacles.is_empty());
+    }
+
+    #[test]
+    fn spawn_points_cut_off_from_the_rest_are_reported() {
+        // Ring of obstacles closing a room around the second spawn point
+        let walls = (0..24)
+            .map(|index| {
+                let angle = index as f32 * std::f32::consts::TAU / 24.0;
+                circle(index + 1, 1000.0 * angle.cos(), 1000.0 * angle.sin(), 200.0)
+            })
+            .collect();
+        let spawn_points = vec![
+            Position { x: 3000.0, y: 0.0 },
+            Position { x: 0.0, y: 0.0 },
+            Position { x: -3000.0, y: 0.0 },
+            // Inside one of the walls
+            Position { x: 1000.0, y: 0.0 },
+        ];
+
+        let report = validate(&map(walls, spawn_points));
+
+        assert_eq!(report.unreachable_spawn_points, vec![1, 3]);
+    }
+
+    #[test]
+    fn non_convex_polygons_are_reported() {
+        let arrow = Entity::new_polygon(
+            1,
+            vec![
+                Position { x: 0.0, y: 0.0 },
+                Position { x: 200.0, y: 100.0 },
+                Position { x: 0.0, y: 200.0 },
+                Position { x: 50.0, y: 100.0 },
+            ],
+        );
+
+        let report = validate(&map(vec![arrow, square(2, 1000.0, 0.0, 100.0)], Vec::new()));
+
+        assert_eq!(report.non_convex_polygons, vec![1]);
+    }
+
+    #[test]
+    fn every_shape_is_checked_for_overlaps() {
+        let origin = Position { x: 0.0, y: 0.0 };
+        let right = Direction { x: 1.0, y: 0.0 };
+        let sector = Entity::new_sector(1, origin, right, 500.0, 30.0);
+        let capsule = Entity::new_capsule(
+            2,
+            Position { x: 0.0, y: -1000.0 },
+            Position {
+                x: 1000.0,
+                y: -1000.0,
+            },
+            50.0,
+        );
+        let oriented_box = Entity::new_oriented_box(3, origin, right, 100.0, 20.0, 45.0);
+        let line = Entity::new_line(
+            4,
+            vec![
+                Position {
+                    x: 400.0,
+                    y: -1200.0,
+                },
+                Position {
+                    x: 400.0,
+                    y: -800.0,
+                },
+            ],
+        );
+
+        // The sector's arc reaches the circle, the capsule crosses the square and the line
+        assert!(shapes_overlap(&sector, &circle(5, 550.0, 0.0, 60.0)));
+        assert!(shapes_overlap(&square(6, 500.0, -1100.0, 100.0), &capsule));
+        assert!(shapes_overlap(&capsule, &line));
+        assert!(shapes_overlap(&oriented_box, &sector));
+        assert!(shapes_overlap(&oriented_box, &circle(7, 70.0, 70.0, 10.0)));
+        assert!(shapes_overlap(&line, &square(8, 350.0, -1000.0, 100.0)));
+
+        assert!(!shapes_overlap(&sector, &capsule));
+        assert!(!shapes_overlap(&oriented_box, &capsule));
+        assert!(!shapes_overlap(&line, &circle(9, 0.0, 0.0, 100.0)));
+    }
+}
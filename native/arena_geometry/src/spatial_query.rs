@@ -0,0 +1,704 @@
+use crate::collision_detection::oriented_box::OrientedBox;
+use crate::entity::{Category, Entity, Shape};
+use crate::position::{Direction, Position};
+
+/*
+ * Spatial queries
+ *
+ * Finds the entities overlapping an area, e.g. the targets of a skill. Every entity shape is
+ * tested against the exact area, the cone in particular is a true circular sector so targets
+ * touching its arc are found, which a triangle would miss.
+ *
+ * Results are sorted by the distance from the area origin (the center of circles and capsules, the
+ * apex of cones and the center of polygons and boxes) to the closest point of each entity, so big
+ * entities and polygons, whose position may be far from their vertices, sort by where they are.
+ */
+
+pub enum QueryArea {
+    Circle {
+        center: Position,
+        radius: f32,
+    },
+    // Sector of `range` radius spanning `half_angle` degrees at each side of `direction`
+    Cone {
+        origin: Position,
+        direction: Direction,
+        range: f32,
+        half_angle: f32,
+    },
+    Polygon {
+        vertices: Vec<Position>,
+    },
+    // Axis aligned box
+    Aabb {
+        min: Position,
+        max: Position,
+    },
+    // Points within `radius` of the segment between `a` and `b`
+    Capsule {
+        a: Position,
+        b: Position,
+        radius: f32,
+    },
+}
+
+#[derive(Default)]
+pub struct QueryFilter {
+    // Categories to look for, any category when empty
+    pub categories: Vec<Category>,
+    // Ids to leave out, usually the caster
+    pub exclude: Vec<u64>,
+}
+
+pub fn query(area: &QueryArea, entities: &[Entity], filter: &QueryFilter) -> Vec<u64> {
+    let area = PreparedArea::new(area);
+
+    let mut found: Vec<(f32, u64)> = entities
+        .iter()
+        .filter(|entity| {
+            filter.categories.is_empty() || filter.categories.contains(&entity.category)
+        })
+        .filter(|entity| !filter.exclude.contains(&entity.id))
+        .filter(|entity| area.overlaps(entity))
+        .map(|entity| (distance_to_entity(&area.origin, entity), entity.id))
+        .collect();
+
+    found.sort_by(|(distance_a, _), (distance_b, _)| distance_a.total_cmp(distance_b));
+    found.into_iter().map(|(_, id)| id).collect()
+}
+
+/*
+ * Exact overlap test used for the shapes the collision functions don't know about, sectors and
+ * capsules. Either of the entities can be the sector or capsule.
+ */
+pub fn entities_overlap(entity: &Entity, other_entity: &Entity) -> bool {
+    let (area_entity, entity) = match entity.shape {
+        Shape::Sector { .. } | Shape::Capsule { .. } => (entity, other_entity),
+        _ => (other_entity, entity),
+    };
+
+    area_of(area_entity).is_some_and(|area| PreparedArea::new(&area).overlaps(entity))
+}
+
+fn area_of(entity: &Entity) -> Option<QueryArea> {
+    match &entity.shape {
+        Shape::Circle => Some(QueryArea::Circle {
+            center: entity.position,
+            radius: entity.radius,
+        }),
+        Shape::Polygon => Some(QueryArea::Polygon {
+            vertices: entity.vertices.clone(),
+        }),
+        Shape::Sector { radius, half_angle } => Some(QueryArea::Cone {
+            origin: entity.position,
+            direction: entity.direction,
+            range: *radius,
+            half_angle: *half_angle,
+        }),
+        Shape::Capsule { a, b, radius } => Some(QueryArea::Capsule {
+            a: *a,
+            b: *b,
+            radius: *radius,
+        }),
+        Shape::OrientedBox { .. } => {
+            OrientedBox::of(entity).map(|oriented_box| QueryArea::Polygon {
+                vertices: oriented_box.corners(),
+            })
+        }
+        Shape::Point | Shape::Line => None,
+    }
+}
+
+// Query area with what every test needs computed once
+struct PreparedArea {
+    origin: Position,
+    kind: AreaKind,
+}
+
+enum AreaKind {
+    Circle {
+        radius: f32,
+    },
+    Sector(Sector),
+    Polygon {
+        vertices: Vec<Position>,
+    },
+    Aabb {
+        min: Position,
+        max: Position,
+        vertices: Vec<Position>,
+    },
+    Capsule {
+        a: Position,
+        b: Position,
+        radius: f32,
+    },
+}
+
+pub(crate) struct Sector {
+    pub(crate) apex: Position,
+    // Unit vector in the middle of the sector
+    direction: Position,
+    range: f32,
+    cos_half_angle: f32,
+    // Ends of the arc
+    pub(crate) edges: [Position; 2],
+}
+
+impl PreparedArea {
+    fn new(area: &QueryArea) -> PreparedArea {
+        match area {
+            QueryArea::Circle { center, radius } => PreparedArea {
+                origin: *center,
+                kind: AreaKind::Circle { radius: *radius },
+            },
+            QueryArea::Cone {
+                origin,
+                direction,
+                range,
+                half_angle,
+            } => PreparedArea {
+                origin: *origin,
+                kind: AreaKind::Sector(Sector::new(origin, direction, *range, *half_angle)),
+            },
+            QueryArea::Polygon { vertices } => PreparedArea {
+                origin: centroid(vertices),
+                kind: AreaKind::Polygon {
+                    vertices: vertices.clone(),
+                },
+            },
+            QueryArea::Aabb { min, max } => PreparedArea {
+                origin: Position {
+                    x: (min.x + max.x) / 2.0,
+                    y: (min.y + max.y) / 2.0,
+                },
+                kind: AreaKind::Aabb {
+                    min: *min,
+                    max: *max,
+                    vertices: vec![
+                        *min,
+                        Position { x: min.x, y: max.y },
+                        *max,
+                        Position { x: max.x, y: min.y },
+                    ],
+                },
+            },
+            QueryArea::Capsule { a, b, radius } => PreparedArea {
+                origin: Position {
+                    x: (a.x + b.x) / 2.0,
+                    y: (a.y + b.y) / 2.0,
+                },
+                kind: AreaKind::Capsule {
+                    a: *a,
+                    b: *b,
+                    radius: *radius,
+                },
+            },
+        }
+    }
+
+    fn overlaps(&self, entity: &Entity) -> bool {
+        match &entity.shape {
+            Shape::Circle => self.distance_to(&entity.position) <= entity.radius,
+            Shape::Point => self.distance_to(&entity.position) <= 0.0,
+            Shape::Line => {
+                edges(&entity.vertices, false).any(|(a, b)| self.segment_distance(a, b) <= 0.0)
+            }
+            Shape::Polygon => self.overlaps_polygon(&entity.vertices),
+            Shape::OrientedBox { .. } => OrientedBox::of(entity)
+                .is_some_and(|oriented_box| self.overlaps_polygon(&oriented_box.corners())),
+            Shape::Capsule { a, b, radius } => self.segment_distance(a, b) <= *radius,
+            // Same as polygons, with the arc as one more edge
+            Shape::Sector { radius, half_angle } => {
+                let sector = Sector::new(&entity.position, &entity.direction, *radius, *half_angle);
+
+                sector.distance_to(&self.inner_point()) <= 0.0
+                    || sector
+                        .sides()
+                        .any(|(a, b)| self.segment_distance(a, b) <= 0.0)
+                    || self.touches_arc(&sector)
+            }
+        }
+    }
+
+    // Either an edge of the polygon touches the area or the area is inside of it
+    fn overlaps_polygon(&self, vertices: &[Position]) -> bool {
+        point_in_polygon(&self.inner_point(), vertices)
+            || edges(vertices, true).any(|(a, b)| self.segment_distance(a, b) <= 0.0)
+    }
+
+    // Distance from the position to the closest point of the area, zero inside of it
+    fn distance_to(&self, position: &Position) -> f32 {
+        match &self.kind {
+            AreaKind::Circle { radius } => {
+                (self.origin.distance_to_position(position) - radius).max(0.0)
+            }
+            AreaKind::Sector(sector) => sector.distance_to(position),
+            AreaKind::Polygon { vertices } => polygon_distance(position, vertices),
+            AreaKind::Aabb { min, max, .. } => {
+                let x = (min.x - position.x).max(position.x - max.x).max(0.0);
+                let y = (min.y - position.y).max(position.y - max.y).max(0.0);
+                (x.powi(2) + y.powi(2)).sqrt()
+            }
+            AreaKind::Capsule { a, b, radius } => {
+                (segment_distance(position, a, b) - radius).max(0.0)
+            }
+        }
+    }
+
+    // Distance from the segment to the closest point of the area, zero if they overlap
+    fn segment_distance(&self, a: &Position, b: &Position) -> f32 {
+        match &self.kind {
+            AreaKind::Circle { radius } => (segment_distance(&self.origin, a, b) - radius).max(0.0),
+            AreaKind::Capsule {
+                a: capsule_a,
+                b: capsule_b,
+                radius,
+            } => (segments_distance(a, b, capsule_a, capsule_b) - radius).max(0.0),
+            AreaKind::Sector(sector) => {
+                if sector.distance_to(a) <= 0.0 {
+                    return 0.0;
+                }
+                sector
+                    .sides()
+                    .map(|(c, d)| segments_distance(a, b, c, d))
+                    .fold(sector.segment_arc_distance(a, b), f32::min)
+            }
+            AreaKind::Polygon { vertices } | AreaKind::Aabb { vertices, .. } => {
+                if point_in_polygon(a, vertices) {
+                    return 0.0;
+                }
+                edges(vertices, true)
+                    .map(|(c, d)| segments_distance(a, b, c, d))
+                    .fold(f32::INFINITY, f32::min)
+            }
+        }
+    }
+
+    // Whether the arc of the sector touches the area
+    fn touches_arc(&self, sector: &Sector) -> bool {
+        match &self.kind {
+            AreaKind::Circle { radius } => sector.arc_distance(&self.origin) <= *radius,
+            AreaKind::Capsule { a, b, radius } => sector.segment_arc_distance(a, b) <= *radius,
+            AreaKind::Polygon { vertices } | AreaKind::Aabb { vertices, .. } => {
+                edges(vertices, true).any(|(a, b)| sector.segment_arc_distance(a, b) <= 0.0)
+            }
+            AreaKind::Sector(other_sector) => {
+                other_sector
+                    .sides()
+                    .any(|(a, b)| sector.segment_arc_distance(a, b) <= 0.0)
+                    || circle_circle_intersections(
+                        &sector.apex,
+                        sector.range,
+                        &other_sector.apex,
+                        other_sector.range,
+                    )
+                    .iter()
+                    .any(|point| sector.in_wedge(point) && other_sector.in_wedge(point))
+            }
+        }
+    }
+
+    // Any point of the area, the center of a concave polygon may be outside of it
+    fn inner_point(&self) -> Position {
+        match &self.kind {
+            AreaKind::Circle { .. } | AreaKind::Sector(_) => self.origin,
+            AreaKind::Polygon { vertices } | AreaKind::Aabb { vertices, .. } => {
+                vertices.first().copied().unwrap_or(self.origin)
+            }
+            AreaKind::Capsule { a, .. } => *a,
+        }
+    }
+}
+
+impl Sector {
+    pub(crate) fn of(entity: &Entity) -> Option<Sector> {
+        match entity.shape {
+            Shape::Sector { radius, half_angle } => Some(Sector::new(
+                &entity.position,
+                &entity.direction,
+                radius,
+                half_angle,
+            )),
+            _ => None,
+        }
+    }
+
+    fn new(apex: &Position, direction: &Direction, range: f32, half_angle: f32) -> Sector {
+        let mut direction = Position {
+            x: direction.x,
+            y: direction.y,
+        };
+        if direction.x == 0.0 && direction.y == 0.0 {
+            // Like `calculate_triangle_vertices`, no direction means facing right
+            direction.x = 1.0;
+        }
+        direction.normalize();
+        let half_angle = half_angle.to_radians();
+        let edge = |angle: f32| Position {
+            x: apex.x + (direction.x * angle.cos() - direction.y * angle.sin()) * range,
+            y: apex.y + (direction.x * angle.sin() + direction.y * angle.cos()) * range,
+        };
+
+        Sector {
+            apex: *apex,
+            direction,
+            range,
+            cos_half_angle: half_angle.cos(),
+            edges: [edge(half_angle), edge(-half_angle)],
+        }
+    }
+
+    fn sides(&self) -> impl Iterator<Item = (&Position, &Position)> {
+        self.edges.iter().map(move |edge| (&self.apex, edge))
+    }
+
+    // Smallest and largest projection of the sector on the axis, the arc reaches the farthest in
+    // the axis direction if it's inside the wedge
+    pub(crate) fn project(&self, axis: Position) -> (f32, f32) {
+        let apex = self.apex.x * axis.x + self.apex.y * axis.y;
+        let (min, max) = self
+            .edges
+            .iter()
+            .map(|edge| edge.x * axis.x + edge.y * axis.y)
+            .fold((apex, apex), |(min, max), value| {
+                (min.min(value), max.max(value))
+            });
+
+        let along =
+            |sign: f32| self.in_wedge(&Position::add(&self.apex, &Position::mult(&axis, sign)));
+        (
+            if along(-1.0) { apex - self.range } else { min },
+            if along(1.0) { apex + self.range } else { max },
+        )
+    }
+
+    pub(crate) fn in_wedge(&self, position: &Position) -> bool {
+        let offset = Position::sub(position, &self.apex);
+        let length = (offset.x.powi(2) + offset.y.powi(2)).sqrt();
+        offset.x * self.direction.x + offset.y * self.direction.y >= length * self.cos_half_angle
+    }
+
+    fn distance_to(&self, position: &Position) -> f32 {
+        if self.in_wedge(position) {
+            (self.apex.distance_to_position(position) - self.range).max(0.0)
+        } else {
+            // Outside of the wedge the closest point is on one of the straight sides
+            self.sides()
+                .map(|(a, b)| segment_distance(position, a, b))
+                .fold(f32::INFINITY, f32::min)
+        }
+    }
+
+    fn arc_distance(&self, position: &Position) -> f32 {
+        if self.in_wedge(position) {
+            (self.apex.distance_to_position(position) - self.range).abs()
+        } else {
+            position
+                .distance_to_position(&self.edges[0])
+                .min(position.distance_to_position(&self.edges[1]))
+        }
+    }
+
+    // The closest points are either the ends of the segment or the arc, where the segment
+    // crosses the arc or the point of the segment closest to the apex
+    fn segment_arc_distance(&self, a: &Position, b: &Position) -> f32 {
+        let crosses_arc = segment_circle_intersections(a, b, &self.apex, self.range)
+            .iter()
+            .any(|point| self.in_wedge(point));
+        if crosses_arc {
+            return 0.0;
+        }
+
+        let closest_to_apex = closest_point(&self.apex, a, b);
+        let interior = if self.in_wedge(&closest_to_apex) {
+            self.arc_distance(&closest_to_apex)
+        } else {
+            f32::INFINITY
+        };
+
+        [
+            self.arc_distance(a),
+            self.arc_distance(b),
+            segment_distance(&self.edges[0], a, b),
+            segment_distance(&self.edges[1], a, b),
+        ]
+        .into_iter()
+        .fold(interior, f32::min)
+    }
+}
+
+// Distance from the position to the closest point of the entity, zero inside of it
+fn distance_to_entity(position: &Position, entity: &Entity) -> f32 {
+    match &entity.shape {
+        Shape::Circle => (position.distance_to_position(&entity.position) - entity.radius).max(0.0),
+        Shape::Point => position.distance_to_position(&entity.position),
+        Shape::Line => edges(&entity.vertices, false)
+            .map(|(a, b)| segment_distance(position, a, b))
+            .fold(f32::INFINITY, f32::min),
+        Shape::Polygon => polygon_distance(position, &entity.vertices),
+        Shape::Capsule { a, b, radius } => (segment_distance(position, a, b) - radius).max(0.0),
+        Shape::Sector { radius, half_angle } => {
+            Sector::new(&entity.position, &entity.direction, *radius, *half_angle)
+                .distance_to(position)
+        }
+        Shape::OrientedBox { .. } => match OrientedBox::of(entity) {
+            Some(oriented_box) => polygon_distance(position, &oriented_box.corners()),
+            None => position.distance_to_position(&entity.position),
+        },
+    }
+}
+
+fn edges(vertices: &[Position], closed: bool) -> impl Iterator<Item = (&Position, &Position)> {
+    let wrap = if closed && vertices.len() > 2 {
+        vertices.last().zip(vertices.first())
+    } else {
+        None
+    };
+
+    vertices
+        .windows(2)
+        .map(|pair| (&pair[0], &pair[1]))
+        .chain(wrap)
+}
+
+fn centroid(vertices: &[Position]) -> Position {
+    let count = vertices.len().max(1) as f32;
+    Position {
+        x: vertices.iter().map(|vertex| vertex.x).sum::<f32>() / count,
+        y: vertices.iter().map(|vertex| vertex.y).sum::<f32>() / count,
+    }
+}
+
+fn polygon_distance(position: &Position, vertices: &[Position]) -> f32 {
+    if point_in_polygon(position, vertices) {
+        return 0.0;
+    }
+
+    edges(vertices, true)
+        .map(|(a, b)| segment_distance(position, a, b))
+        .fold(f32::INFINITY, f32::min)
+}
+
+// Same ray casting as `point_polygon_collision`, without building an entity for the point
+pub fn point_in_polygon(position: &Position, vertices: &[Position]) -> bool {
+    edges(vertices, true).fold(false, |inside, (current, next)| {
+        let crosses = (current.y >= position.y) != (next.y >= position.y)
+            && position.x
+                < (next.x - current.x) * (position.y - current.y) / (next.y - current.y)
+                    + current.x;
+        inside != crosses
+    })
+}
+
+// Point of the segment between a and b closest to the position
+pub fn closest_point(position: &Position, a: &Position, b: &Position) -> Position {
+    let segment = Position::sub(b, a);
+    let length_squared = segment.x.powi(2) + segment.y.powi(2);
+    if length_squared == 0.0 {
+        return *a;
+    }
+
+    let t = (((position.x - a.x) * segment.x + (position.y - a.y) * segment.y) / length_squared)
+        .clamp(0.0, 1.0);
+    Position::add(a, &Position::mult(&segment, t))
+}
+
+fn segment_distance(position: &Position, a: &Position, b: &Position) -> f32 {
+    position.distance_to_position(&closest_point(position, a, b))
+}
+
+fn segments_distance(a: &Position, b: &Position, c: &Position, d: &Position) -> f32 {
+    if segments_intersect(a, b, c, d) {
+        return 0.0;
+    }
+
+    segment_distance(a, c, d)
+        .min(segment_distance(b, c, d))
+        .min(segment_distance(c, a, b))
+        .min(segment_distance(d, a, b))
+}
+
+fn segments_intersect(a: &Position, b: &Position, c: &Position, d: &Position) -> bool {
+    let side = |from: &Position, to: &Position, point: &Position| {
+        (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+    };
+    let (ab_c, ab_d) = (side(a, b, c), side(a, b, d));
+    let (cd_a, cd_b) = (side(c, d, a), side(c, d, b));
+
+    if ab_c * ab_d < 0.0 && cd_a * cd_b < 0.0 {
+        return true;
+    }
+
+    // Touching counts as crossing
+    (ab_c == 0.0 && segment_distance(c, a, b) == 0.0)
+        || (ab_d == 0.0 && segment_distance(d, a, b) == 0.0)
+        || (cd_a == 0.0 && segment_distance(a, c, d) == 0.0)
+        || (cd_b == 0.0 && segment_distance(b, c, d) == 0.0)
+}
+
+// Points where the segment crosses the circle outline
+fn segment_circle_intersections(
+    a: &Position,
+    b: &Position,
+    center: &Position,
+    radius: f32,
+) -> Vec<Position> {
+    let segment = Position::sub(b, a);
+    let offset = Position::sub(a, center);
+    let qa = segment.x.powi(2) + segment.y.powi(2);
+    let qb = 2.0 * (offset.x * segment.x + offset.y * segment.y);
+    let qc = offset.x.powi(2) + offset.y.powi(2) - radius.powi(2);
+    let discriminant = qb.powi(2) - 4.0 * qa * qc;
+    if qa == 0.0 || discriminant < 0.0 {
+        return Vec::new();
+    }
+
+    let root = discriminant.sqrt();
+    [(-qb - root) / (2.0 * qa), (-qb + root) / (2.0 * qa)]
+        .into_iter()
+        .filter(|t| (0.0..=1.0).contains(t))
+        .map(|t| Position::add(a, &Position::mult(&segment, t)))
+        .collect()
+}
+
+fn circle_circle_intersections(
+    center: &Position,
+    radius: f32,
+    other_center: &Position,
+    other_radius: f32,
+) -> Vec<Position> {
+    let distance = center.distance_to_position(other_center);
+    if distance == 0.0
+        || distance > radius + other_radius
+        || distance < (radius - other_radius).abs()
+    {
+        return Vec::new();
+    }
+
+    // Distance from `center` to the chord joining both points and half of its length
+    let along = (radius.powi(2) - other_radius.powi(2) + distance.powi(2)) / (2.0 * distance);
+    let half_chord = (radius.powi(2) - along.powi(2)).max(0.0).sqrt();
+    let unit = Position::mult(&Position::sub(other_center, center), 1.0 / distance);
+    let middle = Position::add(center, &Position::mult(&unit, along));
+
+    vec![
+        Position {
+            x: middle.x - unit.y * half_chord,
+            y: middle.y + unit.x * half_chord,
+        },
+        Position {
+            x: middle.x + unit.y * half_chord,
+            y: middle.y - unit.x * half_chord,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(id: u64, x: f32, y: f32, radius: f32) -> Entity {
+        Entity {
+            shape: Shape::Circle,
+            radius,
+            ..Entity::new_point(id, Position { x, y })
+        }
+    }
+
+    fn square(id: u64, min_x: f32, min_y: f32, side: f32) -> Entity {
+        Entity::new_polygon(
+            id,
+            vec![
+                Position { x: min_x, y: min_y },
+                Position {
+                    x: min_x,
+                    y: min_y + side,
+                },
+                Position {
+                    x: min_x + side,
+                    y: min_y + side,
+                },
+                Position {
+                    x: min_x + side,
+                    y: min_y,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn polygons_are_sorted_by_their_closest_point() {
+        // Both polygons keep the default (0, 0) position, only their vertices say where they are
+        let far = square(1, 1100.0, -50.0, 100.0);
+        let near = square(2, 1020.0, 100.0, 100.0);
+        let area = QueryArea::Circle {
+            center: Position {
+                x: 1000.0,
+                y: 100.0,
+            },
+            radius: 200.0,
+        };
+
+        let found = query(&area, &[far, near], &QueryFilter::default());
+
+        assert_eq!(found, vec![2, 1]);
+    }
+
+    #[test]
+    fn cone_finds_targets_touching_its_arc() {
+        // The inscribed triangle ends at the chord on x = 866, the arc reaches x = 1000
+        let cone = QueryArea::Cone {
+            origin: Position { x: 0.0, y: 0.0 },
+            direction: Direction { x: 1.0, y: 0.0 },
+            range: 1000.0,
+            half_angle: 30.0,
+        };
+        let touching_arc = circle(1, 1010.0, 0.0, 20.0);
+        let past_arc = circle(2, 1030.0, 0.0, 20.0);
+        let beside_chord_end = circle(3, 900.0, 560.0, 20.0);
+
+        let found = query(
+            &cone,
+            &[touching_arc, past_arc, beside_chord_end],
+            &QueryFilter::default(),
+        );
+
+        assert_eq!(found, vec![1]);
+    }
+
+    #[test]
+    fn filters_leave_out_other_categories_and_excluded_ids() {
+        let area = QueryArea::Circle {
+            center: Position { x: 0.0, y: 0.0 },
+            radius: 500.0,
+        };
+        let mut caster = circle(1, 0.0, 0.0, 50.0);
+        caster.category = Category::Player;
+        let mut target = circle(2, 200.0, 0.0, 50.0);
+        target.category = Category::Player;
+        let obstacle = circle(3, 100.0, 0.0, 50.0);
+        let entities = [caster, target, obstacle];
+
+        let players = QueryFilter {
+            categories: vec![Category::Player],
+            exclude: Vec::new(),
+        };
+        let others = QueryFilter {
+            categories: Vec::new(),
+            exclude: vec![1],
+        };
+        let other_players = QueryFilter {
+            categories: vec![Category::Player],
+            exclude: vec![1],
+        };
+
+        assert_eq!(
+            query(&area, &entities, &QueryFilter::default()),
+            vec![1, 3, 2]
+        );
+        assert_eq!(query(&area, &entities, &players), vec![1, 2]);
+        assert_eq!(query(&area, &entities, &others), vec![3, 2]);
+        assert_eq!(query(&area, &entities, &other_players), vec![2]);
+    }
+}
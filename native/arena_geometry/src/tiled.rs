@@ -0,0 +1,469 @@
+use std::f32::consts::TAU;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::collision_detection::ear_clipping::{is_clockwise, try_triangulate_polygon};
+use crate::collision_detection::line_line_collision;
+use crate::entity::{Category, Entity, Shape};
+use crate::position::{Direction, Position};
+
+/*
+ * Tiled map import
+ *
+ * Turns the object layers of a map saved as JSON by Tiled (https://www.mapeditor.org) into
+ * entities. Tiled measures in pixels from the top left corner with the y axis pointing down, the
+ * arena measures from the center of the map with the y axis pointing up, so objects are moved
+ * and flipped accordingly. A `scale` property on the map sets how many world units a pixel is,
+ * 1 by default.
+ *
+ * - Rectangles and polygons become polygons, split in triangles when they aren't convex.
+ * - Ellipses become circles, or polygons approximating them when they aren't round.
+ * - Polylines become a thin polygon per segment, as wide as their `thickness` property.
+ * - Points, tiles and texts aren't shapes and are skipped, like hidden layers and objects.
+ *
+ * The category comes from the type (class since Tiled 1.9) of the object, or the class of its
+ * layer, e.g. `bush` or `pool`. Objects without one are obstacles.
+ */
+
+// Sides of the polygons approximating ellipses
+const ELLIPSE_SIDES: usize = 16;
+// Ellipses whose width and height differ less than this ratio are circles
+const ROUND_ELLIPSE_TOLERANCE: f32 = 0.01;
+// Width of the polylines without a `thickness` property, in world units
+const DEFAULT_POLYLINE_THICKNESS: f32 = 50.0;
+
+#[derive(Deserialize)]
+struct TiledMap {
+    width: f32,
+    height: f32,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    properties: Vec<Property>,
+    #[serde(default)]
+    layers: Vec<Layer>,
+}
+
+#[derive(Deserialize)]
+struct Layer {
+    #[serde(rename = "type")]
+    layer_type: String,
+    #[serde(default = "visible")]
+    visible: bool,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    objects: Vec<Object>,
+    // Layers inside of group layers
+    #[serde(default)]
+    layers: Vec<Layer>,
+}
+
+#[derive(Deserialize)]
+struct Object {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    object_type: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    // Degrees clockwise around (x, y)
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "visible")]
+    visible: bool,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<Point>>,
+    polyline: Option<Vec<Point>>,
+    gid: Option<u32>,
+    text: Option<Value>,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct Property {
+    name: String,
+    value: Value,
+}
+
+// Moves pixels from the Tiled map to world positions
+struct Transform {
+    width: f32,
+    height: f32,
+    scale: f32,
+}
+
+pub fn import_tiled_map(json: &str) -> Result<Vec<Entity>, String> {
+    let map: TiledMap =
+        serde_json::from_str(json).map_err(|error| format!("Invalid Tiled map: {}", error))?;
+
+    let scale = number_property(&map.properties, "scale").unwrap_or(1.0);
+    if scale <= 0.0 {
+        return Err("The scale of the map must be positive".to_string());
+    }
+    let transform = Transform {
+        width: map.width * map.tilewidth,
+        height: map.height * map.tileheight,
+        scale,
+    };
+
+    let mut entities = Vec::new();
+    for layer in &map.layers {
+        import_layer(layer, Point { x: 0.0, y: 0.0 }, &transform, &mut entities)?;
+    }
+
+    Ok(entities)
+}
+
+fn import_layer(
+    layer: &Layer,
+    parent_offset: Point,
+    transform: &Transform,
+    entities: &mut Vec<Entity>,
+) -> Result<(), String> {
+    if !layer.visible {
+        return Ok(());
+    }
+
+    let offset = Point {
+        x: parent_offset.x + layer.offsetx,
+        y: parent_offset.y + layer.offsety,
+    };
+
+    match layer.layer_type.as_str() {
+        "group" => {
+            for child_layer in &layer.layers {
+                import_layer(child_layer, offset, transform, entities)?;
+            }
+        }
+        "objectgroup" => {
+            for object in &layer.objects {
+                if !object.visible {
+                    continue;
+                }
+
+                let category = category(object, layer)
+                    .map_err(|error| format!("Object {:?}: {}", object.name, error))?;
+                let shapes = object_shapes(object, offset, transform)
+                    .map_err(|error| format!("Object {:?}: {}", object.name, error))?;
+
+                for mut entity in shapes {
+                    entity.id = entities.len() as u64 + 1;
+                    entity.category = category.clone();
+                    entity.name = object.name.clone();
+                    entities.push(entity);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn category(object: &Object, layer: &Layer) -> Result<Category, String> {
+    let category = [&object.object_type, &object.class, &layer.class]
+        .into_iter()
+        .find(|category| !category.is_empty());
+
+    match category {
+        Some(category) => serde_json::from_value(Value::String(snake_case(category)))
+            .map_err(|_| format!("unknown category {}", category)),
+        None => Ok(Category::Obstacle),
+    }
+}
+
+// Categories are snake case, but Tiled classes are usually written like `PowerUp` or `Power Up`
+fn snake_case(text: &str) -> String {
+    let mut snake_case = String::new();
+    let mut previous: Option<char> = None;
+    for character in text.trim().chars() {
+        if character == ' ' || character == '-' {
+            snake_case.push('_');
+        } else {
+            let after_word = previous
+                .is_some_and(|previous| previous.is_lowercase() || previous.is_ascii_digit());
+            if character.is_uppercase() && after_word {
+                snake_case.push('_');
+            }
+            snake_case.extend(character.to_lowercase());
+        }
+        previous = Some(character);
+    }
+    snake_case
+}
+
+// Entities of the object with world positions, without ids or categories yet
+fn object_shapes(
+    object: &Object,
+    offset: Point,
+    transform: &Transform,
+) -> Result<Vec<Entity>, String> {
+    let origin = Point {
+        x: object.x + offset.x,
+        y: object.y + offset.y,
+    };
+    let to_world = |point: &Point| transform.to_world(&rotate(point, object.rotation), &origin);
+
+    if object.point || object.gid.is_some() || object.text.is_some() {
+        Ok(Vec::new())
+    } else if let Some(points) = &object.polygon {
+        polygon(points.iter().map(to_world).collect())
+    } else if let Some(points) = &object.polyline {
+        let thickness =
+            number_property(&object.properties, "thickness").unwrap_or(DEFAULT_POLYLINE_THICKNESS);
+        let points: Vec<Position> = points.iter().map(to_world).collect();
+
+        points
+            .windows(2)
+            .map(|segment| thick_segment(&segment[0], &segment[1], thickness))
+            .filter(|vertices| !vertices.is_empty())
+            .map(polygon)
+            .collect::<Result<Vec<Vec<Entity>>, String>>()
+            .map(|pieces| pieces.into_iter().flatten().collect())
+    } else if object.ellipse {
+        let radius_x = object.width / 2.0;
+        let radius_y = object.height / 2.0;
+        if radius_x <= 0.0 || radius_y <= 0.0 {
+            return Err("ellipses need a width and a height".to_string());
+        }
+
+        if (radius_x - radius_y).abs() <= radius_x.max(radius_y) * ROUND_ELLIPSE_TOLERANCE {
+            let center = to_world(&Point {
+                x: radius_x,
+                y: radius_y,
+            });
+            Ok(vec![Entity {
+                id: 0,
+                shape: Shape::Circle,
+                position: center,
+                radius: (radius_x + radius_y) / 2.0 * transform.scale,
+                vertices: Vec::new(),
+                speed: 0.0,
+                category: Category::Obstacle,
+                direction: Direction { x: 0.0, y: 0.0 },
+                is_moving: false,
+                name: String::new(),
+            }])
+        } else {
+            polygon(
+                (0..ELLIPSE_SIDES)
+                    .map(|side| {
+                        let angle = side as f32 * TAU / ELLIPSE_SIDES as f32;
+                        to_world(&Point {
+                            x: radius_x + radius_x * angle.cos(),
+                            y: radius_y + radius_y * angle.sin(),
+                        })
+                    })
+                    .collect(),
+            )
+        }
+    } else {
+        if object.width <= 0.0 || object.height <= 0.0 {
+            return Err("rectangles need a width and a height".to_string());
+        }
+
+        polygon(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point {
+                    x: object.width,
+                    y: 0.0,
+                },
+                Point {
+                    x: object.width,
+                    y: object.height,
+                },
+                Point {
+                    x: 0.0,
+                    y: object.height,
+                },
+            ]
+            .iter()
+            .map(to_world)
+            .collect(),
+        )
+    }
+}
+
+/*
+ * Polygon entity, triangulated if it isn't convex. Flipping the y axis reverses the winding of
+ * the Tiled polygons, so the vertices are put in the clockwise order the triangulation expects.
+ */
+fn polygon(mut vertices: Vec<Position>) -> Result<Vec<Entity>, String> {
+    if vertices.len() < 3 {
+        return Err("polygons need at least 3 points".to_string());
+    }
+
+    if !is_clockwise(&vertices) {
+        vertices.reverse();
+    }
+
+    if is_self_intersecting(&vertices) {
+        return Err("polygon edges can't cross each other".to_string());
+    }
+
+    let position = Position::mult(
+        &vertices
+            .iter()
+            .fold(Position { x: 0.0, y: 0.0 }, |sum, vertex| {
+                Position::add(&sum, vertex)
+            }),
+        1.0 / vertices.len() as f32,
+    );
+
+    let pieces = try_triangulate_polygon(Entity {
+        position,
+        ..Entity::new_polygon(0, vertices)
+    })
+    .map_err(|error| format!("polygon can't be split in triangles ({})", error))?;
+
+    // Points in a line are dropped by the triangulation, leaving nothing with an area
+    if pieces.iter().any(|piece| piece.vertices.len() < 3) {
+        return Err("polygon points can't all be in a line".to_string());
+    }
+
+    Ok(pieces)
+}
+
+// Rectangle around the segment, empty if both ends are the same point
+fn thick_segment(from: &Position, to: &Position, thickness: f32) -> Vec<Position> {
+    let mut normal = Position {
+        x: from.y - to.y,
+        y: to.x - from.x,
+    };
+    if normal.x == 0.0 && normal.y == 0.0 {
+        return Vec::new();
+    }
+    normal.normalize();
+    let offset = Position::mult(&normal, thickness / 2.0);
+
+    vec![
+        Position::add(from, &offset),
+        Position::add(to, &offset),
+        Position::sub(to, &offset),
+        Position::sub(from, &offset),
+    ]
+}
+
+fn is_self_intersecting(vertices: &[Position]) -> bool {
+    let edges: Vec<Entity> = (0..vertices.len())
+        .map(|index| {
+            Entity::new_line(
+                0,
+                vec![vertices[index], vertices[(index + 1) % vertices.len()]],
+            )
+        })
+        .collect();
+
+    // Neighbor edges always touch at their shared vertex
+    (0..edges.len()).any(|index| {
+        (index + 2..edges.len())
+            .filter(|other_index| !(index == 0 && *other_index == edges.len() - 1))
+            .any(|other_index| line_line_collision(&edges[index], &edges[other_index]))
+    })
+}
+
+// Tiled rotates clockwise on screen, which is the usual rotation with its y axis pointing down
+fn rotate(point: &Point, degrees: f32) -> Point {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Point {
+        x: point.x * cos - point.y * sin,
+        y: point.x * sin + point.y * cos,
+    }
+}
+
+impl Transform {
+    fn to_world(&self, point: &Point, origin: &Point) -> Position {
+        Position {
+            x: (origin.x + point.x - self.width / 2.0) * self.scale,
+            y: (self.height / 2.0 - origin.y - point.y) * self.scale,
+        }
+    }
+}
+
+fn number_property(properties: &[Property], name: &str) -> Option<f32> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .and_then(|property| property.value.as_f64())
+        .map(|value| value as f32)
+}
+
+fn visible() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_with_objects(objects: &str) -> String {
+        format!(
+            r#"{{"width": 10, "height": 10, "tilewidth": 32, "tileheight": 32,
+                "layers": [{{"type": "objectgroup", "objects": [{}]}}]}}"#,
+            objects
+        )
+    }
+
+    #[test]
+    fn categories_are_read_in_any_case() {
+        let objects = ["PowerUp", "Power Up", "power_up", "POWER_UP"]
+            .iter()
+            .map(|class| {
+                format!(
+                    r#"{{"x": 0, "y": 0, "width": 10, "height": 10, "type": "{}"}}"#,
+                    class
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let entities = import_tiled_map(&map_with_objects(&objects)).unwrap();
+
+        assert_eq!(entities.len(), 4);
+        assert!(entities
+            .iter()
+            .all(|entity| entity.category == Category::PowerUp));
+    }
+
+    #[test]
+    fn polygons_that_cant_be_triangulated_are_reported() {
+        let polygon = r#"[{"x": 3, "y": 1}, {"x": 3, "y": 3}, {"x": 2, "y": 2},
+                          {"x": 2, "y": 3}, {"x": 0, "y": 0}, {"x": 1, "y": 1}]"#;
+        let object = format!(r#"{{"x": 0, "y": 0, "polygon": {}}}"#, polygon);
+
+        let error = import_tiled_map(&map_with_objects(&object)).unwrap_err();
+
+        assert!(error.contains("triangles"), "{}", error);
+    }
+
+    #[test]
+    fn polygons_without_area_are_reported() {
+        let object = r#"{"x": 0, "y": 0, "polygon": [{"x": 0, "y": 0}, {"x": 10, "y": 0},
+                                                     {"x": 20, "y": 0}]}"#;
+
+        assert!(import_tiled_map(&map_with_objects(object)).is_err());
+    }
+}
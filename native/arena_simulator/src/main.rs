@@ -0,0 +1,105 @@
+/*
+ * Headless match simulator
+ *
+ * Runs a scenario (map, players and how long to simulate) with the arena physics and the bots
+ * pathfinding without booting the Elixir apps, and prints a JSON report with the players that got
+ * stuck, the ones that went through obstacles and the paths that couldn't be found. It's meant for
+ * regression testing map and physics changes.
+ *
+ * Usage: arena_simulator <scenario.json> [--fail-on-issues] [--svg <output.svg>]
+ *
+ * With `--fail-on-issues` it exits with status 2 when the report has any of those issues.
+ * With `--svg` it also draws how the match ended: the map over the bots collision grid, the
+ * players and the paths the bots were following.
+ * See `scenario.rs` for the scenario format. The report includes the validation report of the map.
+ */
+
+mod scenario;
+mod simulation;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::scenario::Scenario;
+use crate::simulation::Simulation;
+
+const USAGE: &str =
+    "Usage: arena_simulator <scenario.json> [--fail-on-issues] [--svg <output.svg>]";
+
+fn main() -> ExitCode {
+    let mut fail_on_issues = false;
+    let mut svg_path = None;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fail-on-issues" => fail_on_issues = true,
+            "--svg" => match args.next() {
+                Some(path) => svg_path = Some(path),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ => paths.push(arg),
+        }
+    }
+
+    let [path] = paths.as_slice() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let mut simulation = match load(path) {
+        Ok(simulation) => simulation,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    simulation.run();
+
+    if let Some(svg_path) = svg_path {
+        if let Err(error) = fs::write(&svg_path, simulation.render_svg()) {
+            eprintln!("Couldn't write {}: {}", svg_path, error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let report = simulation.into_report();
+
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(error) => {
+            eprintln!("Couldn't encode the report: {}", error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if fail_on_issues && report.has_issues() {
+        ExitCode::from(2)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn load(path: &str) -> Result<Simulation, String> {
+    let scenario: Scenario = serde_json::from_str(&read(Path::new(path))?)
+        .map_err(|error| format!("Invalid scenario {}: {}", path, error))?;
+
+    let map_path = Path::new(path)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(&scenario.map);
+    let (map, map_report) = arena_geometry::load_map(&read(&map_path)?)
+        .map_err(|error| format!("Couldn't load {}: {}", map_path.display(), error))?;
+
+    Simulation::new(scenario, map, map_report)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|error| format!("Couldn't read {}: {}", path.display(), error))
+}
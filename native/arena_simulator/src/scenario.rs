@@ -0,0 +1,60 @@
+use arena_geometry::Position;
+use serde::Deserialize;
+
+/*
+ * Scenario files describe what to simulate: the map, how long to run and the players on it.
+ *
+ * {
+ *   "map": "maps/araban.json",
+ *   "tick_duration": 30,
+ *   "duration": 60000,
+ *   "players": [
+ *     {"radius": 100, "speed": 0.6, "controller": "bot", "targets": [{"x": 0, "y": 5000}]},
+ *     {"radius": 100, "speed": 0.6, "controller": "scripted",
+ *      "moves": [{"direction": {"x": 1, "y": 0}, "duration": 2000}]}
+ *   ]
+ * }
+ *
+ * The map is a map file (see `arena_geometry::map_file`), relative to the scenario file.
+ * Players without a position start at the spawn points of the map, in order.
+ */
+
+#[derive(Deserialize)]
+pub struct Scenario {
+    pub map: String,
+    // Milliseconds per tick, the same as the game tick rate by default
+    #[serde(default = "default_tick_duration")]
+    pub tick_duration: f32,
+    // Milliseconds to simulate
+    pub duration: f32,
+    pub players: Vec<PlayerConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct PlayerConfig {
+    pub position: Option<Position>,
+    pub radius: f32,
+    // Units per millisecond, like in the game
+    pub speed: f32,
+    #[serde(flatten)]
+    pub controller: ControllerConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "controller", rename_all = "snake_case")]
+pub enum ControllerConfig {
+    // Walks to each target in order with the bots pathfinding
+    Bot { targets: Vec<Position> },
+    // Moves in each direction for its duration in milliseconds, then stays still
+    Scripted { moves: Vec<ScriptedMove> },
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct ScriptedMove {
+    pub direction: Position,
+    pub duration: f32,
+}
+
+fn default_tick_duration() -> f32 {
+    30.0
+}
@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+
+use arena_geometry::{
+    Category, DebugScene, Direction, Entity, Map, Position, Shape, ValidationReport,
+};
+use astarnative::navigation::{Navigation, PathResult};
+use serde::Serialize;
+
+use crate::scenario::{ControllerConfig, Scenario, ScriptedMove};
+
+/*
+ * Runs a scenario tick by tick the way the game moves players: each tick every player moves
+ * in its direction and is then pushed back inside the map and out of the obstacles.
+ *
+ * Bots get their direction from the same pathfinding and path following the bot manager uses,
+ * scripted players from their list of moves. Like in the game, players don't collide with each
+ * other.
+ */
+
+// Players that move less than this ratio of their speed during the window are stuck
+const STUCK_WINDOW: f32 = 1000.0;
+const STUCK_RATIO: f32 = 0.1;
+
+#[derive(Serialize)]
+pub struct Report {
+    // Problems found loading the map, they don't count as issues of the simulation
+    pub map: ValidationReport,
+    pub ticks: usize,
+    pub players: Vec<PlayerSummary>,
+    pub stuck: Vec<StuckEvent>,
+    pub tunneling: Vec<TunnelingEvent>,
+    pub path_failures: Vec<PathFailure>,
+}
+
+// Players and obstacles are identified by their index in the scenario and map lists
+#[derive(Serialize)]
+pub struct PlayerSummary {
+    pub player: usize,
+    pub final_position: Position,
+    pub distance_travelled: f32,
+    pub targets_reached: usize,
+    pub replans: usize,
+}
+
+// The player wanted to move but barely did during the last window
+#[derive(Serialize)]
+pub struct StuckEvent {
+    pub player: usize,
+    pub tick: usize,
+    pub position: Position,
+}
+
+// The player went through an obstacle or ended the tick inside of it
+#[derive(Serialize)]
+pub struct TunnelingEvent {
+    pub player: usize,
+    pub tick: usize,
+    pub obstacle: usize,
+    pub from: Position,
+    pub to: Position,
+}
+
+#[derive(Serialize)]
+pub struct PathFailure {
+    pub player: usize,
+    pub tick: usize,
+    pub from: Position,
+    pub to: Position,
+    pub reason: PathFailureReason,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathFailureReason {
+    // The target can't be reached, the bot went as close as it could
+    Partial,
+    // The bot or the target have no free cell around them, the target was skipped
+    Blocked,
+}
+
+impl Report {
+    pub fn has_issues(&self) -> bool {
+        !self.stuck.is_empty() || !self.tunneling.is_empty() || !self.path_failures.is_empty()
+    }
+}
+
+struct Player {
+    entity: Entity,
+    controller: Controller,
+    distance_travelled: f32,
+    targets_reached: usize,
+    replans: usize,
+    // Time spent trying to move since the start of the stuck window and where it started
+    stuck_window: (f32, Position),
+    is_stuck: bool,
+}
+
+enum Controller {
+    Bot {
+        targets: Vec<Position>,
+        target_index: usize,
+        path: Option<Vec<Position>>,
+        waypoint_index: usize,
+        // The path only gets close to the target
+        is_partial: bool,
+    },
+    Scripted {
+        moves: Vec<ScriptedMove>,
+        move_index: usize,
+        elapsed: f32,
+    },
+}
+
+pub struct Simulation {
+    tick_duration: f32,
+    ticks: usize,
+    external_wall: Entity,
+    obstacles: HashMap<u64, Entity>,
+    navigation: Navigation,
+    players: Vec<Player>,
+    report: Report,
+}
+
+impl Simulation {
+    pub fn new(
+        scenario: Scenario,
+        map: Map,
+        map_report: ValidationReport,
+    ) -> Result<Simulation, String> {
+        if scenario.tick_duration <= 0.0 {
+            return Err("tick_duration must be positive".to_string());
+        }
+
+        let first_player_id = map.obstacles.len() + map.bushes.len() + map.pools.len() + 1;
+
+        let players = scenario
+            .players
+            .into_iter()
+            .enumerate()
+            .map(|(index, config)| {
+                let position = config
+                    .position
+                    .or_else(|| map.spawn_points.get(index).copied())
+                    .ok_or(format!("Player {} has no position", index))?;
+
+                let controller = match config.controller {
+                    ControllerConfig::Bot { targets } => Controller::Bot {
+                        targets,
+                        target_index: 0,
+                        path: None,
+                        waypoint_index: 0,
+                        is_partial: false,
+                    },
+                    ControllerConfig::Scripted { moves } => Controller::Scripted {
+                        moves,
+                        move_index: 0,
+                        elapsed: 0.0,
+                    },
+                };
+
+                Ok(Player {
+                    entity: Entity {
+                        id: (first_player_id + index) as u64,
+                        shape: Shape::Circle,
+                        position,
+                        radius: config.radius,
+                        vertices: Vec::new(),
+                        speed: config.speed,
+                        category: Category::Player,
+                        direction: Direction { x: 0.0, y: 0.0 },
+                        is_moving: false,
+                        name: format!("Player {}", index),
+                    },
+                    controller,
+                    distance_travelled: 0.0,
+                    targets_reached: 0,
+                    replans: 0,
+                    stuck_window: (0.0, position),
+                    is_stuck: false,
+                })
+            })
+            .collect::<Result<Vec<Player>, String>>()?;
+
+        Ok(Simulation {
+            tick_duration: scenario.tick_duration,
+            ticks: (scenario.duration / scenario.tick_duration).ceil() as usize,
+            external_wall: map.external_wall,
+            navigation: Navigation::new(map.obstacles.clone()),
+            obstacles: map
+                .obstacles
+                .into_iter()
+                .map(|obstacle| (obstacle.id, obstacle))
+                .collect(),
+            players,
+            report: Report {
+                map: map_report,
+                ticks: 0,
+                players: Vec::new(),
+                stuck: Vec::new(),
+                tunneling: Vec::new(),
+                path_failures: Vec::new(),
+            },
+        })
+    }
+
+    pub fn run(&mut self) {
+        for tick in 0..self.ticks {
+            self.step(tick);
+        }
+    }
+
+    // The map, where the players are and the paths the bots are following, on the collision grid
+    pub fn render_svg(&self) -> String {
+        let mut obstacles: Vec<Entity> = self.obstacles.values().cloned().collect();
+        obstacles.sort_by_key(|obstacle| obstacle.id);
+
+        let paths = self
+            .players
+            .iter()
+            .filter_map(|player| match &player.controller {
+                Controller::Bot { path, .. } => path.clone(),
+                Controller::Scripted { .. } => None,
+            })
+            .collect();
+
+        arena_geometry::render_svg(&DebugScene {
+            external_wall: Some(self.external_wall.clone()),
+            obstacles,
+            entities: self
+                .players
+                .iter()
+                .map(|player| player.entity.clone())
+                .collect(),
+            grid: Some(self.navigation.debug_grid()),
+            paths,
+        })
+    }
+
+    pub fn into_report(mut self) -> Report {
+        self.report.ticks = self.ticks;
+        self.report.players = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| PlayerSummary {
+                player: index,
+                final_position: player.entity.position,
+                distance_travelled: player.distance_travelled,
+                targets_reached: player.targets_reached,
+                replans: player.replans,
+            })
+            .collect();
+
+        self.report
+    }
+
+    fn step(&mut self, tick: usize) {
+        let obstacles: Vec<Entity> = self.obstacles.values().cloned().collect();
+
+        for index in 0..self.players.len() {
+            let wants_to_move = self.steer(index, tick);
+
+            let player = &mut self.players[index];
+            let from = player.entity.position;
+            if player.entity.is_moving {
+                player.entity.move_entity(self.tick_duration);
+                player
+                    .entity
+                    .move_to_closest_available_position(&self.external_wall, &self.obstacles);
+            }
+            let to = player.entity.position;
+
+            player.distance_travelled += from.distance_to_position(&to);
+
+            for obstacle_id in crossed_obstacles(&from, &to, &obstacles) {
+                self.report.tunneling.push(TunnelingEvent {
+                    player: index,
+                    tick,
+                    obstacle: obstacle_id as usize - 1,
+                    from,
+                    to,
+                });
+            }
+
+            self.check_stuck(index, tick, wants_to_move);
+        }
+    }
+
+    // Points the player to where its controller wants to go, returns whether it wants to move
+    fn steer(&mut self, index: usize, tick: usize) -> bool {
+        let player = &mut self.players[index];
+        let position = player.entity.position;
+        let step = player.entity.speed * self.tick_duration;
+
+        let direction = match &mut player.controller {
+            Controller::Scripted {
+                moves,
+                move_index,
+                elapsed,
+            } => {
+                while *move_index < moves.len() && *elapsed >= moves[*move_index].duration {
+                    *elapsed -= moves[*move_index].duration;
+                    *move_index += 1;
+                }
+
+                let Some(scripted_move) = moves.get(*move_index) else {
+                    return stop(player);
+                };
+                *elapsed += self.tick_duration;
+
+                let mut direction = scripted_move.direction;
+                direction.normalize();
+                direction
+            }
+            Controller::Bot {
+                targets,
+                target_index,
+                path,
+                waypoint_index,
+                is_partial,
+            } => {
+                let Some(target) = targets.get(*target_index).copied() else {
+                    return stop(player);
+                };
+
+                if path.is_none() {
+                    let failure = |reason| PathFailure {
+                        player: index,
+                        tick,
+                        from: position,
+                        to: target,
+                        reason,
+                    };
+
+                    match self.navigation.shortest_path(&position, &target) {
+                        PathResult::Found(new_path) => {
+                            *path = Some(new_path);
+                            *is_partial = false;
+                        }
+                        PathResult::Partial(new_path) => {
+                            self.report
+                                .path_failures
+                                .push(failure(PathFailureReason::Partial));
+                            *path = Some(new_path);
+                            *is_partial = true;
+                        }
+                        PathResult::Blocked => {
+                            self.report
+                                .path_failures
+                                .push(failure(PathFailureReason::Blocked));
+                            *target_index += 1;
+                            stop(player);
+                            return true;
+                        }
+                    }
+                    *waypoint_index = 0;
+                }
+
+                let current_path = path.as_deref().unwrap_or_default();
+                let path_step =
+                    self.navigation
+                        .follow_path(current_path, *waypoint_index, &position, step);
+                *waypoint_index = path_step.waypoint_index;
+
+                if path_step.needs_replan {
+                    *path = None;
+                    player.replans += 1;
+                    stop(player);
+                    return true;
+                }
+
+                if *waypoint_index == current_path.len() {
+                    if !*is_partial {
+                        player.targets_reached += 1;
+                    }
+                    *target_index += 1;
+                    *path = None;
+                    stop(player);
+                    return true;
+                }
+
+                Position {
+                    x: path_step.direction.x,
+                    y: path_step.direction.y,
+                }
+            }
+        };
+
+        if direction.x.is_nan() || direction.y.is_nan() {
+            return stop(player);
+        }
+
+        player.entity.direction = Direction {
+            x: direction.x,
+            y: direction.y,
+        };
+        player.entity.is_moving = true;
+        true
+    }
+
+    fn check_stuck(&mut self, index: usize, tick: usize, wants_to_move: bool) {
+        let player = &mut self.players[index];
+        let position = player.entity.position;
+
+        if !wants_to_move {
+            player.stuck_window = (0.0, position);
+            player.is_stuck = false;
+            return;
+        }
+
+        let (elapsed, start) = player.stuck_window;
+        let elapsed = elapsed + self.tick_duration;
+        if elapsed < STUCK_WINDOW {
+            player.stuck_window = (elapsed, start);
+            return;
+        }
+
+        let is_stuck =
+            start.distance_to_position(&position) < player.entity.speed * elapsed * STUCK_RATIO;
+        // Only the start of each stuck streak is reported
+        if is_stuck && !player.is_stuck {
+            self.report.stuck.push(StuckEvent {
+                player: index,
+                tick,
+                position,
+            });
+        }
+
+        player.is_stuck = is_stuck;
+        player.stuck_window = (0.0, position);
+    }
+}
+
+// Stops the player for this tick, returns that it doesn't want to move
+fn stop(player: &mut Player) -> bool {
+    player.entity.is_moving = false;
+    false
+}
+
+// Obstacles the player went through moving between the positions or ended up inside of
+fn crossed_obstacles(from: &Position, to: &Position, obstacles: &Vec<Entity>) -> Vec<u64> {
+    let mut crossed = if from == to {
+        Vec::new()
+    } else {
+        Entity::new_line(0, vec![*from, *to]).collides_with(obstacles)
+    };
+
+    for obstacle_id in Entity::new_point(0, *to).collides_with(obstacles) {
+        if !crossed.contains(&obstacle_id) {
+            crossed.push(obstacle_id);
+        }
+    }
+
+    crossed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A wall across the middle of the map, from x 1000 to 1200
+    const MAP: &str = r#"{
+        "version": 1,
+        "radius": 5000,
+        "obstacles": [{"shape": "polygon", "vertices": [
+            {"x": 1000, "y": -1000}, {"x": 1000, "y": 1000},
+            {"x": 1200, "y": 1000}, {"x": 1200, "y": -1000}
+        ]}]
+    }"#;
+
+    fn run(players: &str) -> Report {
+        let scenario: Scenario = serde_json::from_str(&format!(
+            r#"{{"map": "wall.json", "duration": 10000, "players": [{}]}}"#,
+            players
+        ))
+        .unwrap();
+        let (map, map_report) = arena_geometry::load_map(MAP).unwrap();
+        assert!(map_report.is_valid());
+
+        let mut simulation = Simulation::new(scenario, map, map_report).unwrap();
+        simulation.run();
+        simulation.into_report()
+    }
+
+    #[test]
+    fn player_walking_into_a_wall_does_not_go_through_it() {
+        let report = run(r#"{
+            "position": {"x": 0, "y": 0}, "radius": 100, "speed": 0.6, "controller": "scripted",
+            "moves": [{"direction": {"x": 1, "y": 0}, "duration": 10000}]
+        }"#);
+
+        assert!(report.tunneling.is_empty());
+        assert!(report.players[0].final_position.x <= 1000.0 - 100.0 + 1.0);
+        // It kept pushing against the wall
+        assert_eq!(report.stuck.len(), 1);
+    }
+
+    #[test]
+    fn bot_walks_around_the_wall_to_its_target() {
+        let report = run(r#"{
+            "position": {"x": 0, "y": 0}, "radius": 100, "speed": 0.6, "controller": "bot",
+            "targets": [{"x": 2500, "y": 0}]
+        }"#);
+
+        assert!(!report.has_issues());
+        assert_eq!(report.players[0].targets_reached, 1);
+    }
+}